@@ -0,0 +1,43 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Decrypt an age-encrypted file in memory and return its plaintext.
+///
+/// The identity (secret key) is piped to `age --decrypt -i -` on stdin, so
+/// neither the key nor the plaintext ever touches the disk. Error messages
+/// never include the plaintext.
+pub fn decrypt_file(path: &Path, identity: &str) -> Result<String> {
+    let mut child = Command::new("age")
+        .arg("--decrypt")
+        .args(["-i", "-"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run the age CLI (is it installed and on PATH?)")?;
+
+    child
+        .stdin
+        .take()
+        .context("Failed to open age stdin")?
+        .write_all(identity.as_bytes())
+        .context("Failed to pass the identity to age")?;
+
+    let output = child.wait_with_output().context("Failed to wait for age")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = if stderr.contains("no identity matched") {
+            "the identity does not match any recipient of the file".to_string()
+        } else {
+            stderr.trim().to_string()
+        };
+        anyhow::bail!("Failed to decrypt {}: {}", path.display(), reason);
+    }
+
+    String::from_utf8(output.stdout)
+        .context(format!("Decrypted {} is not valid UTF-8", path.display()))
+}
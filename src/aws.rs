@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Fetch every `[env.aws_secrets]` entry, returning a map of env var name to value.
+///
+/// Entries are `VAR = "secret-id"` or `VAR = "secret-id#json-key"` for JSON
+/// secrets. Secrets are read through the `aws` CLI so credentials come from the
+/// standard AWS provider chain. Values are never included in error messages.
+pub fn fetch_secrets(mappings: &HashMap<String, String>) -> Result<HashMap<String, String>> {
+    let mut cache: HashMap<&str, String> = HashMap::new();
+    let mut secrets = HashMap::new();
+
+    for (var, reference) in mappings {
+        let (secret_id, json_key) = match reference.split_once('#') {
+            Some((id, key)) => (id, Some(key)),
+            None => (reference.as_str(), None),
+        };
+
+        if !cache.contains_key(secret_id) {
+            cache.insert(secret_id, get_secret_string(secret_id)?);
+        }
+        let secret = &cache[secret_id];
+
+        let value = match json_key {
+            Some(key) => extract_json_key(secret, key).context(format!(
+                "Key '{}' not found in AWS secret '{}'",
+                key, secret_id
+            ))?,
+            None => secret.clone(),
+        };
+        secrets.insert(var.clone(), value);
+    }
+
+    Ok(secrets)
+}
+
+fn get_secret_string(secret_id: &str) -> Result<String> {
+    let output = Command::new("aws")
+        .args([
+            "secretsmanager",
+            "get-secret-value",
+            "--secret-id",
+            secret_id,
+        ])
+        .args(["--query", "SecretString", "--output", "text"])
+        .output()
+        .context("Failed to run the aws CLI (is it installed and on PATH?)")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to read AWS secret '{}': {}",
+            secret_id,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+fn extract_json_key(secret: &str, key: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(secret).ok()?;
+
+    match json.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Check that the installed forge supports signing with AWS KMS (`--aws`)
+pub fn ensure_forge_supports_kms() -> Result<()> {
+    let output = Command::new("forge")
+        .args(["script", "--help"])
+        .output()
+        .context("Failed to run forge")?;
+
+    if !String::from_utf8_lossy(&output.stdout).contains("--aws") {
+        anyhow::bail!(
+            "The installed forge does not support --aws. AWS KMS signing needs a Foundry \
+             release built with the aws-kms feature (official releases since v1.0, install \
+             with `foundryup`)"
+        );
+    }
+
+    Ok(())
+}
+
+/// Derive the sender address of the KMS key via `cast wallet address --aws`
+pub fn kms_sender_address() -> Result<String> {
+    let output = Command::new("cast")
+        .args(["wallet", "address", "--aws"])
+        .output()
+        .context("Failed to run cast")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to derive the AWS KMS sender address: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_json_key() {
+        let secret = r#"{"etherscan":"abc","port":8545}"#;
+
+        assert_eq!(
+            extract_json_key(secret, "etherscan"),
+            Some("abc".to_string())
+        );
+        assert_eq!(extract_json_key(secret, "port"), Some("8545".to_string()));
+        assert_eq!(extract_json_key(secret, "missing"), None);
+        assert_eq!(extract_json_key("plain-text", "etherscan"), None);
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::summary::DeploymentResult;
+
+/// A config file listing several projects to deploy together (`[[batch]]`)
+#[derive(Debug, Deserialize)]
+pub struct BatchConfig {
+    pub batch: Vec<BatchEntry>,
+}
+
+/// One project of a batch run
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchEntry {
+    /// Label in the output, the config path when omitted
+    pub name: Option<String>,
+    /// The project's deployment config, relative to the batch file
+    pub config: String,
+    /// Network to deploy to instead of the config's `project.network`
+    pub network: Option<String>,
+    /// Script to run instead of the config's `project.script`
+    pub script: Option<String>,
+    /// Deploy position, lower first; entries with the same order keep file order
+    #[serde(default)]
+    pub order: i64,
+}
+
+impl BatchEntry {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.config)
+    }
+}
+
+impl BatchConfig {
+    /// Load a batch file, resolving each entry's config path against the file's directory
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read batch file: {}", path))?;
+        let mut config: BatchConfig =
+            toml::from_str(&content).context(format!("Failed to parse batch file: {}", path))?;
+
+        if config.batch.is_empty() {
+            anyhow::bail!("Batch file {} has no [[batch]] entries", path);
+        }
+
+        let base = Path::new(path).parent().unwrap_or(Path::new(""));
+        for entry in config.batch.iter_mut() {
+            entry.config = base.join(&entry.config).to_string_lossy().to_string();
+        }
+
+        Ok(config)
+    }
+}
+
+/// Whether a config file is a batch file, i.e. has a top-level `batch` array
+pub fn is_batch_file(path: &str) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| toml::from_str::<toml::Table>(&content).ok())
+        .is_some_and(|table| table.contains_key("batch"))
+}
+
+/// What happened to one entry of a batch run
+#[derive(Debug)]
+pub enum BatchStatus {
+    Deployed(Box<DeploymentResult>),
+    Failed(anyhow::Error),
+    /// Not deployed because an earlier entry failed in fail-fast mode
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub name: String,
+    pub status: BatchStatus,
+}
+
+/// Prepare every entry concurrently, at most `max_parallel` at a time, then
+/// deploy them one by one in `order`.
+///
+/// `prepare` does the clone and setup and runs on worker threads; `deploy`
+/// runs on the calling thread so confirmation prompts don't overlap. An entry
+/// whose preparation failed is reported as failed without deploying; in
+/// fail-fast mode any failure skips the entries deployed after it, and their
+/// prepared checkouts are handed to `release`.
+pub fn run<T, P, D, R>(
+    entries: &[BatchEntry],
+    max_parallel: usize,
+    keep_going: bool,
+    prepare: P,
+    mut deploy: D,
+    mut release: R,
+) -> Vec<BatchOutcome>
+where
+    T: Send,
+    P: Fn(&BatchEntry) -> Result<T> + Sync,
+    D: FnMut(&BatchEntry, T) -> Result<DeploymentResult>,
+    R: FnMut(&BatchEntry, T) -> Result<()>,
+{
+    println!(
+        "{}",
+        format!(
+            "Preparing {} projects ({} at a time)...",
+            entries.len(),
+            max_parallel.max(1)
+        )
+        .blue()
+    );
+    let mut prepared: Vec<Option<Result<T>>> = prepare_all(entries, max_parallel, &prepare)
+        .into_iter()
+        .map(Some)
+        .collect();
+
+    let mut deploy_order: Vec<usize> = (0..entries.len()).collect();
+    deploy_order.sort_by_key(|&i| entries[i].order);
+
+    let mut outcomes = Vec::with_capacity(entries.len());
+    let mut failed = false;
+
+    for i in deploy_order {
+        let entry = &entries[i];
+        let ready = prepared[i].take().expect("each entry is deployed once");
+
+        let status = if failed && !keep_going {
+            if let Ok(ready) = ready
+                && let Err(e) = release(entry, ready)
+            {
+                println!(
+                    "{}",
+                    format!("Failed to clean up {}: {:#}", entry.label(), e).yellow()
+                );
+            }
+            BatchStatus::Skipped
+        } else {
+            println!(
+                "\n{}",
+                format!("══════════ Deploying {} ══════════", entry.label()).green()
+            );
+
+            match ready.and_then(|ready| deploy(entry, ready)) {
+                Ok(result) => BatchStatus::Deployed(Box::new(result)),
+                Err(e) => {
+                    println!(
+                        "{}",
+                        format!("Deployment of {} failed: {:#}", entry.label(), e).red()
+                    );
+                    failed = true;
+                    BatchStatus::Failed(e)
+                }
+            }
+        };
+
+        outcomes.push(BatchOutcome {
+            name: entry.label().to_string(),
+            status,
+        });
+    }
+
+    outcomes
+}
+
+/// Run `prepare` for every entry on up to `max_parallel` threads, keeping entry order
+fn prepare_all<T, P>(entries: &[BatchEntry], max_parallel: usize, prepare: &P) -> Vec<Result<T>>
+where
+    T: Send,
+    P: Fn(&BatchEntry) -> Result<T> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<T>>>> = Mutex::new(entries.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..max_parallel.clamp(1, entries.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(entry) = entries.get(i) else {
+                        break;
+                    };
+                    let result =
+                        prepare(entry).context(format!("Failed to prepare {}", entry.label()));
+                    results.lock().unwrap()[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every entry is prepared"))
+        .collect()
+}
+
+pub fn print_summary(outcomes: &[BatchOutcome]) {
+    println!(
+        "\n{}",
+        "═══════════════════════════════════ BATCH SUMMARY ═══════════════════════════════════"
+            .green()
+    );
+
+    for outcome in outcomes {
+        match &outcome.status {
+            BatchStatus::Deployed(result) => println!(
+                "{} {} ({} on {}): {}, {} contract(s)",
+                "✓".green(),
+                outcome.name,
+                result.script,
+                result.network,
+                result.status,
+                result.contracts.len()
+            ),
+            BatchStatus::Failed(e) => println!("{} {}: {:#}", "✗".red(), outcome.name, e),
+            BatchStatus::Skipped => println!("{} {}: skipped", "-".yellow(), outcome.name),
+        }
+    }
+
+    println!(
+        "{}",
+        "═══════════════════════════════════════════════════════════════════════════════════════"
+            .green()
+    );
+}
+
+/// Fail the run if any entry failed, keeping the first error as the source
+pub fn into_result(outcomes: Vec<BatchOutcome>) -> Result<()> {
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, BatchStatus::Failed(_)))
+        .map(|o| o.name.clone())
+        .collect();
+
+    let first_error = outcomes.into_iter().find_map(|o| match o.status {
+        BatchStatus::Failed(e) => Some(e),
+        _ => None,
+    });
+
+    match first_error {
+        Some(e) => Err(e.context(format!("Batch failed for: {}", failed.join(", ")))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::DeploymentStatus;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_batch_with_two_entries() {
+        let temp_dir = tempdir().unwrap();
+        let batch_path = temp_dir.path().join("batch.toml");
+        fs::write(
+            &batch_path,
+            r#"
+[[batch]]
+name = "vault"
+config = "vault/deploy.toml"
+order = 2
+
+[[batch]]
+name = "token"
+config = "token/deploy.toml"
+network = "sepolia"
+order = 1
+"#,
+        )
+        .unwrap();
+        let batch_path = batch_path.to_str().unwrap();
+
+        assert!(is_batch_file(batch_path));
+        let config = BatchConfig::from_file(batch_path).unwrap();
+        assert_eq!(
+            config.batch[1].config,
+            temp_dir.path().join("token/deploy.toml").to_string_lossy()
+        );
+
+        // Both entries are prepared at the same time
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let mut deployed = Vec::new();
+
+        let outcomes = run(
+            &config.batch,
+            2,
+            false,
+            |entry| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(format!("{}-checkout", entry.label()))
+            },
+            |entry, checkout| {
+                deployed.push(checkout);
+                Ok(DeploymentResult::not_deployed(
+                    DeploymentStatus::Succeeded,
+                    entry.label(),
+                    entry.network.as_deref().unwrap_or("mainnet"),
+                    "Deploy.s.sol",
+                ))
+            },
+            |_, _| unreachable!("nothing is skipped"),
+        );
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(deployed, vec!["token-checkout", "vault-checkout"]);
+        assert_eq!(outcomes[0].name, "token");
+        assert!(matches!(outcomes[1].status, BatchStatus::Deployed(_)));
+        assert!(into_result(outcomes).is_ok());
+
+        // A failed preparation fails its entry and skips the rest in fail-fast mode,
+        // releasing their checkouts
+        let mut released = Vec::new();
+        let outcomes = run(
+            &config.batch,
+            1,
+            false,
+            |entry| match entry.label() {
+                "token" => anyhow::bail!("clone failed"),
+                label => Ok(format!("{}-checkout", label)),
+            },
+            |entry, _| {
+                Ok(DeploymentResult::not_deployed(
+                    DeploymentStatus::Succeeded,
+                    entry.label(),
+                    "mainnet",
+                    "Deploy.s.sol",
+                ))
+            },
+            |_, checkout| {
+                released.push(checkout);
+                Ok(())
+            },
+        );
+        assert!(matches!(outcomes[0].status, BatchStatus::Failed(_)));
+        assert!(matches!(outcomes[1].status, BatchStatus::Skipped));
+        assert_eq!(released, vec!["vault-checkout"]);
+        let err = into_result(outcomes).unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to prepare token: clone failed"));
+    }
+}
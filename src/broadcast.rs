@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A contract created by a broadcast transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedContract {
+    pub name: String,
+    pub address: String,
+}
+
+/// Directory where forge writes the broadcast artifacts of a script run on a chain
+pub fn run_dir(
+    project_dir: &Path,
+    broadcast_dir: &str,
+    script_name: &str,
+    chain_id: u64,
+) -> PathBuf {
+    project_dir
+        .join(broadcast_dir)
+        .join(script_name)
+        .join(chain_id.to_string())
+}
+
+/// Latest broadcast artifact in a run directory.
+///
+/// Prefers forge's `run-latest.json`, falling back to the most recently
+/// modified `run-*.json`. Returns `None` if there is no artifact at all.
+pub fn latest_run_path(run_dir: &Path) -> Option<PathBuf> {
+    let latest = run_dir.join("run-latest.json");
+    if latest.is_file() {
+        return Some(latest);
+    }
+
+    fs::read_dir(run_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with("run-") && name.ends_with(".json")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Parse the contracts created (CREATE / CREATE2) in a broadcast artifact
+pub fn parse_deployed_contracts(path: &Path) -> Result<Vec<DeployedContract>> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read broadcast file: {}", path.display()))?;
+    let json: Value = serde_json::from_str(&content).context(format!(
+        "Failed to parse broadcast file: {}",
+        path.display()
+    ))?;
+
+    let transactions = json
+        .get("transactions")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let contracts = transactions
+        .iter()
+        .filter(|tx| {
+            matches!(
+                tx.get("transactionType").and_then(Value::as_str),
+                Some("CREATE") | Some("CREATE2")
+            )
+        })
+        .filter_map(|tx| {
+            Some(DeployedContract {
+                name: tx
+                    .get("contractName")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown")
+                    .to_string(),
+                address: tx
+                    .get("contractAddress")
+                    .and_then(Value::as_str)?
+                    .to_string(),
+            })
+        })
+        .collect();
+
+    Ok(contracts)
+}
+
+/// Number of transactions recorded in a broadcast artifact
+pub fn transaction_count(path: &Path) -> Result<usize> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read broadcast file: {}", path.display()))?;
+    let json: Value = serde_json::from_str(&content).context(format!(
+        "Failed to parse broadcast file: {}",
+        path.display()
+    ))?;
+
+    Ok(json
+        .get("transactions")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len))
+}
+
+/// Commit forge recorded in a broadcast artifact (`commit`), if any
+pub fn run_commit(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let json: Value = serde_json::from_str(&content).ok()?;
+    json.get("commit")?.as_str().map(str::to_string)
+}
+
+/// Total gas forge estimated for the transactions in a (dry-run) broadcast artifact
+pub fn estimated_gas(path: &Path) -> Result<u128> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read broadcast file: {}", path.display()))?;
+    let json: Value = serde_json::from_str(&content).context(format!(
+        "Failed to parse broadcast file: {}",
+        path.display()
+    ))?;
+
+    json.get("transactions")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tx| tx.get("transaction")?.get("gas"))
+        .map(crate::rpc::parse_quantity)
+        .sum()
+}
+
+/// Check deployed contracts against `expectations` (contract name to address).
+///
+/// Addresses are compared case-insensitively. All mismatches, including expected
+/// contracts that were not deployed at all, are reported in a single error.
+pub fn check_expectations(
+    contracts: &[DeployedContract],
+    expectations: &HashMap<String, String>,
+) -> Result<()> {
+    let mut names: Vec<&String> = expectations.keys().collect();
+    names.sort();
+
+    let mut mismatches = Vec::new();
+    for name in names {
+        let expected = &expectations[name];
+        let deployed: Vec<&str> = contracts
+            .iter()
+            .filter(|c| &c.name == name)
+            .map(|c| c.address.as_str())
+            .collect();
+
+        if deployed.is_empty() {
+            mismatches.push(format!("{}: expected {}, not deployed", name, expected));
+        } else if !deployed.iter().any(|a| a.eq_ignore_ascii_case(expected)) {
+            mismatches.push(format!(
+                "{}: expected {}, deployed {}",
+                name,
+                expected,
+                deployed.join(", ")
+            ));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        anyhow::bail!(
+            "Deployed addresses do not match [expectations]:\n  {}",
+            mismatches.join("\n  ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    pub(crate) const BROADCAST_CONTENT: &str = r#"{
+  "transactions": [
+    {
+      "transactionType": "CREATE",
+      "contractName": "MyToken",
+      "contractAddress": "0x5fbdb2315678afecb367f032d93f642f64180aa3"
+    },
+    {
+      "transactionType": "CALL",
+      "contractName": "MyToken",
+      "contractAddress": "0x5fbdb2315678afecb367f032d93f642f64180aa3"
+    },
+    {
+      "transactionType": "CREATE2",
+      "contractName": "Vault",
+      "contractAddress": "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512"
+    }
+  ],
+  "receipts": [],
+  "commit": "4b825dc"
+}"#;
+
+    #[test]
+    fn test_parse_deployed_contracts() {
+        let temp_dir = tempdir().unwrap();
+        let path =
+            run_dir(temp_dir.path(), "broadcast", "Deploy.s.sol", 31337).join("run-latest.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, BROADCAST_CONTENT).unwrap();
+
+        let contracts = parse_deployed_contracts(&path).unwrap();
+        assert_eq!(
+            contracts,
+            vec![
+                DeployedContract {
+                    name: "MyToken".to_string(),
+                    address: "0x5fbdb2315678afecb367f032d93f642f64180aa3".to_string(),
+                },
+                DeployedContract {
+                    name: "Vault".to_string(),
+                    address: "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimated_gas() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("run-latest.json");
+        fs::write(
+            &path,
+            r#"{"transactions":[{"transaction":{"gas":"0x124f80"}},{"transaction":{"gas":"0xc3500"}},{"transactionType":"CALL"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(estimated_gas(&path).unwrap(), 1_200_000 + 800_000);
+    }
+
+    #[test]
+    fn test_latest_run_in_custom_broadcast_dir() {
+        let temp_dir = tempdir().unwrap();
+        let dir = run_dir(temp_dir.path(), "out/broadcast", "Deploy.s.sol", 31337);
+        assert_eq!(latest_run_path(&dir), None);
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("run-1700000000.json"), BROADCAST_CONTENT).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(dir.join("run-1700000100.json"), BROADCAST_CONTENT).unwrap();
+        fs::write(dir.join("notes.json"), "{}").unwrap();
+        assert_eq!(latest_run_path(&dir), Some(dir.join("run-1700000100.json")));
+
+        fs::write(dir.join("run-latest.json"), BROADCAST_CONTENT).unwrap();
+        assert_eq!(latest_run_path(&dir), Some(dir.join("run-latest.json")));
+    }
+
+    #[test]
+    fn test_check_expectations() {
+        let contracts = vec![DeployedContract {
+            name: "MyToken".to_string(),
+            address: "0x5fbdb2315678afecb367f032d93f642f64180aa3".to_string(),
+        }];
+
+        let matching = HashMap::from([(
+            "MyToken".to_string(),
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+        )]);
+        assert!(check_expectations(&contracts, &matching).is_ok());
+
+        let mismatching = HashMap::from([
+            (
+                "MyToken".to_string(),
+                "0x0000000000000000000000000000000000000001".to_string(),
+            ),
+            (
+                "Vault".to_string(),
+                "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512".to_string(),
+            ),
+        ]);
+        let err = check_expectations(&contracts, &mismatching)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("MyToken: expected 0x0000000000000000000000000000000000000001"));
+        assert!(
+            err.contains(
+                "Vault: expected 0xe7f1725e7734ce288f8367e1bb143e90bb3f0512, not deployed"
+            )
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::rpc::RpcClient;
+
+/// Fetches the runtime bytecode deployed at an address
+pub trait CodeSource {
+    fn get_code(&self, address: &str) -> Result<String>;
+}
+
+impl CodeSource for RpcClient {
+    fn get_code(&self, address: &str) -> Result<String> {
+        RpcClient::get_code(self, address)
+    }
+}
+
+/// How the on-chain code at an address compares to a local artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytecodeDiff {
+    /// Byte-for-byte identical, including the metadata hash
+    Identical,
+    /// Same code, only the trailing CBOR metadata differs
+    MetadataOnly,
+    Changed,
+    /// Nothing is deployed at the address yet
+    NotDeployed,
+}
+
+impl BytecodeDiff {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BytecodeDiff::Identical => "identical to the local artifact",
+            BytecodeDiff::MetadataOnly => "same code, only the metadata hash differs",
+            BytecodeDiff::Changed => "differs from the local artifact",
+            BytecodeDiff::NotDeployed => "no code deployed at this address",
+        }
+    }
+}
+
+/// Compare on-chain runtime code with a locally compiled one (both hex, `0x` optional)
+pub fn compare(onchain: &str, local: &str) -> BytecodeDiff {
+    let onchain = normalize(onchain);
+    let local = normalize(local);
+
+    if onchain.is_empty() {
+        BytecodeDiff::NotDeployed
+    } else if onchain == local {
+        BytecodeDiff::Identical
+    } else if strip_metadata(&onchain) == strip_metadata(&local) {
+        BytecodeDiff::MetadataOnly
+    } else {
+        BytecodeDiff::Changed
+    }
+}
+
+fn normalize(code: &str) -> String {
+    code.trim()
+        .strip_prefix("0x")
+        .unwrap_or(code.trim())
+        .to_lowercase()
+}
+
+/// Drop solc's CBOR metadata, whose length is stored in the last two bytes
+fn strip_metadata(code: &str) -> &str {
+    let Some(length_hex) = code.get(code.len().saturating_sub(4)..) else {
+        return code;
+    };
+    let Ok(length) = usize::from_str_radix(length_hex, 16) else {
+        return code;
+    };
+
+    let metadata_chars = (length + 2) * 2;
+    if metadata_chars > code.len() {
+        return code;
+    }
+    &code[..code.len() - metadata_chars]
+}
+
+/// Read the runtime bytecode from a forge artifact (`out/<File>.sol/<Contract>.json`)
+pub fn read_deployed_bytecode(path: &Path) -> Result<String> {
+    let content =
+        fs::read_to_string(path).context(format!("Failed to read artifact: {}", path.display()))?;
+    let json: Value = serde_json::from_str(&content)
+        .context(format!("Failed to parse artifact: {}", path.display()))?;
+
+    let deployed = json.get("deployedBytecode").context(format!(
+        "Artifact has no deployedBytecode: {}",
+        path.display()
+    ))?;
+
+    deployed
+        .get("object")
+        .unwrap_or(deployed)
+        .as_str()
+        .map(str::to_string)
+        .context(format!(
+            "Artifact deployedBytecode is not a hex string: {}",
+            path.display()
+        ))
+}
+
+/// Compare the code at `address` with the artifact at `artifact_path`
+pub fn diff(address: &str, artifact_path: &Path, source: &dyn CodeSource) -> Result<BytecodeDiff> {
+    let local = read_deployed_bytecode(artifact_path)?;
+    let onchain = source
+        .get_code(address)
+        .context(format!("Failed to fetch code at {}", address))?;
+
+    Ok(compare(&onchain, &local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const RUNTIME: &str = "6080604052348015600f57600080fd5b50";
+    // 6 bytes of CBOR metadata followed by its 2-byte length 0x0006
+    const METADATA_A: &str = "a264697066ff0006";
+    const METADATA_B: &str = "a264697066ee0006";
+
+    struct MockCode(String);
+
+    impl CodeSource for MockCode {
+        fn get_code(&self, _address: &str) -> Result<String> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_bytecode_diff() {
+        let temp_dir = tempdir().unwrap();
+        let artifact = temp_dir.path().join("Vault.json");
+        fs::write(
+            &artifact,
+            format!(
+                r#"{{"deployedBytecode":{{"object":"0x{}{}"}}}}"#,
+                RUNTIME, METADATA_A
+            ),
+        )
+        .unwrap();
+
+        let address = "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512";
+        let identical = format!("0x{}{}", RUNTIME, METADATA_A);
+        let recompiled = format!("0x{}{}", RUNTIME, METADATA_B);
+        let changed = format!("0x{}00{}", RUNTIME, METADATA_A);
+
+        let check = |code: &str| diff(address, &artifact, &MockCode(code.to_string())).unwrap();
+        assert_eq!(check(&identical), BytecodeDiff::Identical);
+        assert_eq!(check(&recompiled), BytecodeDiff::MetadataOnly);
+        assert_eq!(check(&changed), BytecodeDiff::Changed);
+        assert_eq!(check("0x"), BytecodeDiff::NotDeployed);
+    }
+}
@@ -0,0 +1,60 @@
+/// Defaults for a well-known chain, selected with `networks.<name>.chain`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownChain {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub explorer_url: &'static str,
+    /// `forge --verifier` value
+    pub verifier: &'static str,
+    /// `forge --verifier-url`, when forge can't derive it from the chain id
+    pub verifier_url: Option<&'static str>,
+}
+
+const fn etherscan(name: &'static str, chain_id: u64, explorer_url: &'static str) -> KnownChain {
+    KnownChain {
+        name,
+        chain_id,
+        explorer_url,
+        verifier: "etherscan",
+        verifier_url: None,
+    }
+}
+
+const KNOWN_CHAINS: &[KnownChain] = &[
+    etherscan("mainnet", 1, "https://etherscan.io"),
+    etherscan("sepolia", 11155111, "https://sepolia.etherscan.io"),
+    etherscan("holesky", 17000, "https://holesky.etherscan.io"),
+    etherscan("optimism", 10, "https://optimistic.etherscan.io"),
+    etherscan(
+        "optimism-sepolia",
+        11155420,
+        "https://sepolia-optimism.etherscan.io",
+    ),
+    etherscan("arbitrum", 42161, "https://arbiscan.io"),
+    etherscan("arbitrum-sepolia", 421614, "https://sepolia.arbiscan.io"),
+    etherscan("base", 8453, "https://basescan.org"),
+    etherscan("base-sepolia", 84532, "https://sepolia.basescan.org"),
+    etherscan("polygon", 137, "https://polygonscan.com"),
+    etherscan("polygon-amoy", 80002, "https://amoy.polygonscan.com"),
+    etherscan("bsc", 56, "https://bscscan.com"),
+    etherscan("avalanche", 43114, "https://snowtrace.io"),
+    KnownChain {
+        name: "gnosis",
+        chain_id: 100,
+        explorer_url: "https://gnosis.blockscout.com",
+        verifier: "blockscout",
+        verifier_url: Some("https://gnosis.blockscout.com/api/"),
+    },
+];
+
+/// Look up a built-in chain by name (case-insensitive)
+pub fn find(name: &str) -> Option<&'static KnownChain> {
+    KNOWN_CHAINS
+        .iter()
+        .find(|chain| chain.name.eq_ignore_ascii_case(name))
+}
+
+/// Names of all built-in chains, for error messages
+pub fn names() -> Vec<&'static str> {
+    KNOWN_CHAINS.iter().map(|chain| chain.name).collect()
+}
@@ -1,4 +1,9 @@
-use clap::Parser;
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::command_export::CommandFormat;
+use crate::multi_network::SummaryOrder;
+use crate::safe::SafeCheck;
 
 #[derive(Parser)]
 #[command(
@@ -8,9 +13,21 @@ use clap::Parser;
     long_about = "A Rust-based binary that allows you to clone and deploy smart contracts using TOML configuration files, making deployments consistent and reproducible."
 )]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
     /// Configuration file path
-    #[arg(short, long, value_name = "CONFIG_FILE")]
-    pub config: String,
+    #[arg(short, long, value_name = "CONFIG_FILE", global = true)]
+    pub config: Option<String>,
+
+    /// Base directory for relative paths inside the config
+    #[arg(
+        long,
+        value_name = "DIR",
+        global = true,
+        help = "Resolve relative networks_file, env.load_files, [env.encrypted] and project.cwd paths against DIR (default: the config file's directory)"
+    )]
+    pub config_dir: Option<String>,
 
     /// Skip confirmation prompt and auto-confirm deployment
     #[arg(
@@ -20,21 +37,464 @@ pub struct Args {
     )]
     pub skip_confirmation: bool,
 
+    /// Networks whose confirmation prompt is skipped
+    #[arg(
+        long,
+        value_name = "NETWORKS",
+        value_delimiter = ',',
+        help = "Auto-confirm only these networks (comma separated); others still prompt"
+    )]
+    pub assume_yes_for: Vec<String>,
+
     /// Script name to run (overrides config file script)
     #[arg(
         short('s'),
         long,
         value_name = "SCRIPT",
+        global = true,
         help = "Script name to run (e.g., Deploy, ScriptA)"
     )]
     pub script: Option<String>,
 
-    /// Network to deploy to (overrides config file network)
+    /// Networks to deploy to (overrides config file network)
     #[arg(
         short('n'),
         long,
         value_name = "NETWORK",
-        help = "Network to deploy to (e.g., sepolia, mainnet)"
+        value_delimiter = ',',
+        global = true,
+        help = "Network(s) to deploy to, comma-separated or repeated (e.g., sepolia,mainnet)"
+    )]
+    pub network: Vec<String>,
+
+    /// Stop at the first failed network in a multi-network run (default)
+    #[arg(long, help = "Stop at the first failed network (default)")]
+    pub fail_fast: bool,
+
+    /// Attempt every network in a multi-network run
+    #[arg(
+        long,
+        conflicts_with = "fail_fast",
+        help = "Attempt every network and report failures at the end"
+    )]
+    pub keep_going: bool,
+
+    /// Order of the multi-network summary
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SummaryOrder::Given,
+        help = "Order networks in the multi-network summary as given with --network or alphabetically"
+    )]
+    pub summary_order: SummaryOrder,
+
+    /// Redeploy to a local network whenever src/ or script/ change
+    #[arg(
+        long,
+        conflicts_with_all = ["simulate_all", "plan_out", "plan", "explain"],
+        help = "Redeploy to a local network whenever src/ or script/ change, until Ctrl-C"
+    )]
+    pub watch: bool,
+
+    /// Foundry project directory for deployments without a repo (overrides project.cwd)
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Foundry project to deploy from when no repo is configured, overriding project.cwd"
+    )]
+    pub cwd: Option<String>,
+
+    /// Branch, tag or commit to deploy (overrides project.git_ref / git_branch)
+    #[arg(
+        long,
+        value_name = "REF",
+        help = "Branch, tag or commit to check out after cloning, overriding project.git_ref and git_branch"
+    )]
+    pub git_ref: Option<String>,
+
+    /// Concurrent clone and setup jobs in a batch run
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 4,
+        help = "Projects cloned and set up at the same time when the config is a [[batch]] file"
+    )]
+    pub max_parallel: usize,
+
+    /// Query the deployer balance before confirming
+    #[arg(
+        long,
+        help = "Show the deployer balance before confirming (always on when min_balance is set)"
+    )]
+    pub check_balance: bool,
+
+    /// Deploy even when a preflight check such as require_synced fails
+    #[arg(
+        long,
+        help = "Deploy even when the node looks out of sync (networks.<name>.require_synced)"
+    )]
+    pub force: bool,
+
+    /// Require every preflight check to pass
+    #[arg(
+        long,
+        help = "Enforce chain id, sync, balance, empty-variable, strict ${VAR} and typed confirmation checks"
+    )]
+    pub safe: bool,
+
+    /// Safe-mode checks to leave out
+    #[arg(
+        long,
+        value_name = "CHECK",
+        value_delimiter = ',',
+        requires = "safe",
+        help = "Checks to leave out of --safe (comma separated)"
+    )]
+    pub safe_skip: Vec<SafeCheck>,
+
+    /// Estimate the repository size before cloning
+    #[arg(
+        long,
+        help = "Ask the remote for the repository size before cloning and warn if it exceeds project.max_clone_mb (GitHub only, best effort)"
+    )]
+    pub estimate_clone: bool,
+
+    /// Update the cached clone before deploying
+    #[arg(
+        long,
+        help = "Fetch into the project.clone_cache_dir clone even when the tag or commit to check out is already cached"
+    )]
+    pub refresh_cache: bool,
+
+    /// Treat warnings as errors
+    #[arg(
+        long,
+        help = "Fail on the first warning (cleanup failure, skipped checks, missing env file, ...) instead of printing it"
+    )]
+    pub abort_on_warning: bool,
+
+    /// Skip the check that forge actually broadcast something
+    #[arg(
+        long,
+        help = "Don't warn when forge exits 0 without writing a new broadcast artifact"
+    )]
+    pub no_broadcast_check: bool,
+
+    /// Keep the cloned repository after deployment
+    #[arg(
+        long,
+        help = "Keep the cloned repository after deployment (e.g. to copy out broadcast/)"
+    )]
+    pub no_cleanup: bool,
+
+    /// Print the clone plan without cloning
+    #[arg(
+        long,
+        help = "Print the resolved repository, target directory, ref and depth without cloning"
+    )]
+    pub explain: bool,
+
+    /// Simulate every script in project.scripts in order without broadcasting
+    #[arg(
+        long,
+        help = "Simulate project.scripts in order without broadcasting and report estimated gas"
+    )]
+    pub simulate_all: bool,
+
+    /// Block to pin the simulation fork to
+    #[arg(
+        long,
+        value_name = "BLOCK",
+        requires = "simulate_all",
+        help = "Simulate against the network state at BLOCK (--fork-block-number), overriding networks.<name>.fork_block"
+    )]
+    pub fork_block: Option<u64>,
+
+    /// Write a reviewable deployment plan instead of broadcasting
+    #[arg(
+        long,
+        value_name = "PLAN_FILE",
+        help = "Write the resolved plan (config, commit, command; no secrets) to a JSON file and stop before broadcasting"
+    )]
+    pub plan_out: Option<String>,
+
+    /// Print the forge command instead of running it
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["plan_out", "simulate_all", "watch"],
+        help = "Print the forge command (program, redacted args, working dir, env var names) as json or a shell line and stop; the checkout is kept"
+    )]
+    pub emit_command: Option<CommandFormat>,
+
+    /// Replay a plan written with --plan-out
+    #[arg(
+        long,
+        value_name = "PLAN_FILE",
+        conflicts_with_all = ["plan_out", "network", "script", "git_ref"],
+        help = "Deploy exactly what a --plan-out file describes; secrets are read from the environment"
+    )]
+    pub plan: Option<String>,
+
+    /// Re-deploy the latest run recorded in --output-dir
+    #[arg(
+        long,
+        requires = "output_dir",
+        conflicts_with_all = ["plan", "network", "script", "git_ref", "watch"],
+        help = "Re-deploy the network, script and commit of the latest manifest in --output-dir"
+    )]
+    pub from_last: bool,
+
+    /// Skip the deploy when the commit is the one last deployed
+    #[arg(
+        long,
+        requires = "output_dir",
+        conflicts_with = "from_last",
+        help = "Exit successfully without deploying when the commit matches the latest manifest for the network and script in --output-dir (--force deploys anyway)"
+    )]
+    pub only_if_changed: bool,
+
+    /// List the variables set on the forge process before confirming
+    #[arg(
+        long,
+        help = "List the env var names (values masked) passed to forge, including build metadata, before the confirmation prompt"
+    )]
+    pub dump_forge_env: bool,
+
+    /// Print the resolved environment and exit
+    #[arg(
+        long,
+        help = "Print every resolved variable with its source (secrets masked) and exit"
+    )]
+    pub print_env: bool,
+
+    /// Write the resolved environment to a sourceable file and exit
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the resolved environment as export KEY='value' lines to PATH and exit"
+    )]
+    pub export_env: Option<String>,
+
+    /// Include secret-looking variables in --export-env
+    #[arg(long, requires = "export_env")]
+    pub include_secrets: bool,
+
+    /// Print the expanded RPC URL of the selected network and exit
+    #[arg(
+        long,
+        help = "Print the fully expanded RPC URL of the selected network (keys redacted) and exit"
+    )]
+    pub resolve_rpc: bool,
+
+    /// Additional env files to load (repeatable)
+    #[arg(
+        long = "env-file",
+        value_name = "PATH",
+        global = true,
+        help = "Load an extra env file after the configured load_files (repeatable)"
+    )]
+    pub env_files: Vec<String>,
+
+    /// Directory to collect run artifacts into
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Collect broadcast files, manifest and resolved config into <DIR>/<network>-<timestamp>/"
+    )]
+    pub output_dir: Option<String>,
+
+    /// Cancel if the confirmation prompt gets no answer in time
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Cancel the deployment if the confirmation prompt is not answered within SECS seconds"
+    )]
+    pub confirm_timeout: Option<u64>,
+
+    /// Run forge clean before setup
+    #[arg(
+        long,
+        help = "Run `forge clean` in the project before setup for a fresh compile"
+    )]
+    pub clean: bool,
+
+    /// Override project.setup_command for this run
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "Run COMMAND through `sh -c` instead of project.setup_command (empty skips setup)"
+    )]
+    pub setup_command: Option<String>,
+
+    /// Notify locally when the deploy finishes
+    #[arg(
+        long,
+        help = "Ring the terminal bell and run notify.on_complete_command when the deploy finishes"
+    )]
+    pub notify_local: bool,
+
+    /// Deploy again although project.guard_redeploy found a prior deployment
+    #[arg(
+        long,
+        help = "Allow redeploying a script that already deployed on the network (project.guard_redeploy)"
+    )]
+    pub redeploy: bool,
+
+    /// Run with flags from project.dangerous_flags without the extra confirmation
+    #[arg(
+        long,
+        help = "Don't ask again before running forge with a flag listed in project.dangerous_flags (e.g. an inline --private-key)"
+    )]
+    pub force_dangerous: bool,
+
+    /// Derive --sender from the keystore account with `cast wallet address`
+    #[arg(
+        long,
+        help = "Derive the sender address from the keystore account instead of BROADCAST_ACCOUNT"
+    )]
+    pub sender_from_keystore: bool,
+
+    /// Append the deployment summary to the GitHub Actions job summary
+    #[arg(
+        long,
+        help = "Append the deployment summary as markdown to $GITHUB_STEP_SUMMARY (no-op outside GitHub Actions)"
+    )]
+    pub github_summary: bool,
+
+    /// Skip dependency installation entirely
+    #[arg(
+        long,
+        conflicts_with = "setup_command",
+        help = "Skip the setup step, for working dirs whose dependencies are already installed"
+    )]
+    pub no_setup: bool,
+
+    /// Limit how long the repository clone may take
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Abort the clone after SECS seconds (overrides project.clone_timeout_secs)"
+    )]
+    pub clone_timeout: Option<u64>,
+
+    /// Limit how long each RPC preflight call may take
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Give up on each JSON-RPC call after SECS seconds (overrides project.rpc_timeout_secs, default 10)"
+    )]
+    pub rpc_timeout: Option<u64>,
+
+    /// Network defined as JSON for this run
+    #[arg(
+        long,
+        value_name = "JSON",
+        global = true,
+        help = "Register a temporary network from JSON, e.g. '{\"name\":\"custom\",\"chain_id\":31337,\"rpc_url\":\"http://localhost:8545\",\"verify\":false}'"
     )]
-    pub network: Option<String>,
+    pub network_override: Option<String>,
+
+    /// Override project.gas_limit
+    #[arg(
+        long,
+        value_name = "GAS",
+        help = "Gas limit per transaction (overrides project.gas_limit)"
+    )]
+    pub gas_limit: Option<String>,
+
+    /// Override project.gas_price
+    #[arg(
+        long,
+        value_name = "WEI",
+        help = "Gas price in wei (overrides project.gas_price)"
+    )]
+    pub gas_price: Option<String>,
+
+    /// Override project.priority_fee
+    #[arg(
+        long,
+        value_name = "WEI",
+        help = "Priority fee in wei (overrides project.priority_fee)"
+    )]
+    pub priority_fee: Option<String>,
+
+    /// Extra arguments passed to forge script
+    #[arg(
+        last = true,
+        value_name = "FORGE_ARGS",
+        help = "Arguments after `--` are appended to the forge script command"
+    )]
+    pub forge_args: Vec<String>,
+
+    /// Output format for reports
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Human,
+        global = true,
+        help = "Output format for reports (human or json)"
+    )]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Check the configuration and environment without deploying
+    Validate,
+    /// Print the JSON Schema of the configuration file
+    Schema,
+    /// Compare two config files and exit non-zero if they differ
+    DiffConfig {
+        /// Config before the change
+        #[arg(value_name = "OLD")]
+        old: String,
+        /// Config after the change
+        #[arg(value_name = "NEW")]
+        new: String,
+    },
+    /// Verify one already deployed contract with forge verify-contract
+    Verify {
+        /// Address of the deployed contract
+        #[arg(long, value_name = "ADDRESS")]
+        address: String,
+        /// Contract to verify against, e.g. src/Token.sol:Token
+        #[arg(long, value_name = "PATH:NAME")]
+        contract: String,
+    },
+    /// Print the version; with --full also forge, git and the current commit
+    Version {
+        /// Include the toolchain fingerprint
+        #[arg(long)]
+        full: bool,
+    },
+    /// Prompt for missing required variables and write them to an env file
+    SetupEnv {
+        /// Env file to write, default the first of env.load_files or .env
+        #[arg(long, value_name = "FILE")]
+        file: Option<String>,
+    },
+    /// List the latest deployed contracts from the manifests in an --output-dir
+    Deployed {
+        /// Directory the runs were collected into with --output-dir
+        #[arg(value_name = "DIR")]
+        dir: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl Args {
+    /// Get the configuration file path, which every config-based command requires
+    pub fn config_path(&self) -> Result<&str> {
+        self.config
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("--config <CONFIG_FILE> is required"))
+    }
 }
@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bytecode::read_deployed_bytecode;
+
+/// Maximum runtime bytecode size in bytes (EIP-170)
+pub const EIP170_LIMIT: usize = 24576;
+
+/// A compiled contract whose runtime bytecode is over the limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oversized {
+    pub contract: String,
+    pub size: usize,
+}
+
+/// Contracts compiled with the script at `script_path` (e.g. `script/Deploy.s.sol`)
+/// whose runtime bytecode exceeds `limit` bytes, largest first.
+///
+/// Artifacts are matched to sources by the full path in their metadata, so
+/// scripts in subdirectories and same-named sources resolve correctly. The
+/// script's artifact lists every source it was compiled with; the artifacts
+/// of those sources, except the script's own, are checked.
+pub fn oversized(out_dir: &Path, script_path: &str, limit: usize) -> Result<Vec<Oversized>> {
+    let mut paths = Vec::new();
+    collect_artifacts(out_dir, &mut paths)?;
+    let artifacts: Vec<Artifact> = paths
+        .iter()
+        .filter_map(|path| Artifact::read(path))
+        .collect();
+
+    let script = artifacts
+        .iter()
+        .find(|artifact| artifact.source == script_path)
+        .context(format!(
+            "No compiled artifact for {} in {}",
+            script_path,
+            out_dir.display()
+        ))?;
+    let sources: Vec<&str> = script
+        .metadata
+        .get("sources")
+        .and_then(Value::as_object)
+        .map(|sources| sources.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+
+    let mut found = Vec::new();
+    for artifact in &artifacts {
+        if artifact.source == script_path || !sources.contains(&artifact.source.as_str()) {
+            continue;
+        }
+        // Artifacts without runtime code (interfaces, abstract contracts) count as empty
+        let Ok(code) = read_deployed_bytecode(&artifact.path) else {
+            continue;
+        };
+        let size = code.trim().trim_start_matches("0x").len() / 2;
+        if size > limit {
+            found.push(Oversized {
+                contract: artifact.contract.clone(),
+                size,
+            });
+        }
+    }
+
+    found.sort_by(|a, b| b.size.cmp(&a.size).then(a.contract.cmp(&b.contract)));
+    Ok(found)
+}
+
+/// A compiled artifact with the source path and contract name from its metadata
+struct Artifact {
+    path: PathBuf,
+    source: String,
+    contract: String,
+    metadata: Value,
+}
+
+impl Artifact {
+    /// `None` for files that aren't contract artifacts with metadata
+    fn read(path: &Path) -> Option<Artifact> {
+        let json: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+
+        // forge writes `metadata` as an object; `rawMetadata` holds the same as a string
+        let metadata = match (json.get("metadata"), json.get("rawMetadata")) {
+            (Some(metadata), _) if metadata.is_object() => metadata.clone(),
+            (_, Some(Value::String(raw))) => serde_json::from_str(raw).ok()?,
+            _ => return None,
+        };
+        let (source, contract) = metadata
+            .get("settings")?
+            .get("compilationTarget")?
+            .as_object()?
+            .iter()
+            .next()?;
+
+        Some(Artifact {
+            path: path.to_path_buf(),
+            source: source.clone(),
+            contract: contract.as_str()?.to_string(),
+            metadata,
+        })
+    }
+}
+
+/// Every `.json` file under `dir`, sorted, skipping `build-info`; none when it doesn't exist
+fn collect_artifacts(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "build-info") {
+                collect_artifacts(&path, found)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    /// Write `out_dir/<dir>/<contract>.json` compiled from `source`, importing `sources`
+    fn write_artifact(
+        out_dir: &Path,
+        dir: &str,
+        source: &str,
+        contract: &str,
+        bytes: usize,
+        sources: &[&str],
+    ) {
+        let dir = out_dir.join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        let sources: serde_json::Map<String, Value> = sources
+            .iter()
+            .map(|source| (source.to_string(), json!({})))
+            .collect();
+        let artifact = json!({
+            "deployedBytecode": { "object": format!("0x{}", "60".repeat(bytes)) },
+            "metadata": {
+                "settings": { "compilationTarget": { source: contract } },
+                "sources": sources
+            }
+        });
+        fs::write(dir.join(format!("{}.json", contract)), artifact.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_oversized_contracts() {
+        let temp_dir = tempdir().unwrap();
+        let out_dir = temp_dir.path();
+
+        let script = "script/deploy/Deploy.s.sol";
+        write_artifact(
+            out_dir,
+            "Deploy.s.sol",
+            script,
+            "DeployScript",
+            0,
+            &[script, "src/Token.sol", "src/Vault.sol", "src/IVault.sol"],
+        );
+        write_artifact(out_dir, "Token.sol", "src/Token.sol", "Token", 1200, &[]);
+        write_artifact(
+            out_dir,
+            "Vault.sol",
+            "src/Vault.sol",
+            "Vault",
+            EIP170_LIMIT + 1,
+            &[],
+        );
+        write_artifact(
+            out_dir,
+            "Vault.sol",
+            "src/Vault.sol",
+            "VaultLib",
+            EIP170_LIMIT,
+            &[],
+        );
+        write_artifact(out_dir, "IVault.sol", "src/IVault.sol", "IVault", 0, &[]);
+        // Not imported by the script, though named like a source that is
+        write_artifact(
+            out_dir,
+            "lib/mock/Token.sol",
+            "lib/mock/Token.sol",
+            "Token",
+            30000,
+            &[],
+        );
+        write_artifact(out_dir, "Huge.sol", "src/Huge.sol", "Huge", 30000, &[]);
+        fs::create_dir_all(out_dir.join("build-info")).unwrap();
+        fs::write(out_dir.join("build-info/abc.json"), "{}").unwrap();
+
+        assert_eq!(
+            oversized(out_dir, script, EIP170_LIMIT).unwrap(),
+            [Oversized {
+                contract: "Vault".to_string(),
+                size: 24577
+            }]
+        );
+        assert!(oversized(out_dir, "script/Missing.s.sol", EIP170_LIMIT).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Output of `--emit-command`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CommandFormat {
+    /// Program, args, working directory and env var names as JSON
+    Json,
+    /// A shell line that runs the command, preceded by the env var names it expects
+    Shell,
+}
+
+/// The forge invocation of a deploy, for an external runner to execute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportedCommand {
+    pub program: String,
+    /// Secret values appear as `${VAR}`, API keys in the RPC URL are masked
+    pub args: Vec<String>,
+    pub working_dir: String,
+    /// Variables set on the process on top of the inherited environment, sorted
+    pub env: Vec<String>,
+}
+
+impl ExportedCommand {
+    pub fn render(&self, format: CommandFormat) -> Result<String> {
+        Ok(match format {
+            CommandFormat::Json => serde_json::to_string_pretty(self)?,
+            CommandFormat::Shell => self.to_shell(),
+        })
+    }
+
+    fn to_shell(&self) -> String {
+        let command: Vec<String> = std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|arg| shell_quote(arg))
+            .collect();
+        format!(
+            "# env: {}\ncd {} && {}",
+            self.env.join(" "),
+            shell_quote(&self.working_dir),
+            command.join(" ")
+        )
+    }
+}
+
+/// Quote `arg` for sh; a `${VAR}` placeholder stays expandable
+fn shell_quote(arg: &str) -> String {
+    let is_placeholder = arg
+        .strip_prefix("${")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .is_some_and(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if is_placeholder {
+        return format!("\"{}\"", arg);
+    }
+
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_line() {
+        let command = ExportedCommand {
+            program: "forge".to_string(),
+            args: vec![
+                "script".to_string(),
+                "script/Deploy.s.sol".to_string(),
+                "--password".to_string(),
+                "${KEYSTORE_PASSWORD}".to_string(),
+                "--sig".to_string(),
+                "run(string)".to_string(),
+                "it's".to_string(),
+            ],
+            working_dir: "/tmp/my token".to_string(),
+            env: vec![
+                "DEPLOY_NETWORK".to_string(),
+                "KEYSTORE_PASSWORD".to_string(),
+            ],
+        };
+
+        assert_eq!(
+            command.render(CommandFormat::Shell).unwrap(),
+            "# env: DEPLOY_NETWORK KEYSTORE_PASSWORD\n\
+             cd '/tmp/my token' && forge script script/Deploy.s.sol --password \"${KEYSTORE_PASSWORD}\" --sig 'run(string)' 'it'\\''s'"
+        );
+    }
+}
@@ -1,56 +1,813 @@
+use crate::chains;
+use crate::environment::Environment;
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct NetworkConfig {
+    /// Required unless `chain` names a built-in chain
+    #[serde(default)]
     pub chain_id: u64,
+    /// Built from `[rpc_template]` when omitted
+    #[serde(default)]
     pub rpc_url: String,
+    /// Built-in chain (e.g. `base-sepolia`) providing defaults for `chain_id`,
+    /// `explorer_url` and the verifier settings
+    pub chain: Option<String>,
+    /// `forge --verifier`, e.g. `etherscan` or `blockscout`
+    pub verifier: Option<String>,
+    /// `forge --verifier-url` for explorers forge can't derive from the chain id
+    pub verifier_url: Option<String>,
     pub verify: bool,
     pub extra_args: Option<HashMap<String, String>>,
+    /// Minimum deployer balance (in ETH) expected before deploying
+    pub min_balance: Option<f64>,
+    /// Force (`true`) or forbid (`false`) skipping the confirmation prompt, regardless of `-y`
+    pub auto_confirm: Option<bool>,
+    /// Block explorer base URL used for address links, e.g. `https://sepolia.etherscan.io`
+    pub explorer_url: Option<String>,
+    /// Abort unless the RPC node is synced and its latest block is recent
+    #[serde(default)]
+    pub require_synced: bool,
+    /// Maximum age (in seconds) of the latest block when `require_synced` is on, default 60
+    pub max_block_lag_secs: Option<u64>,
+    /// Shell command that approves (exit 0) or rejects the deploy instead of the prompt
+    pub approval_command: Option<String>,
+    /// Warning printed right before the confirmation, e.g. "PRODUCTION - funds at risk"
+    pub confirm_message: Option<String>,
+    /// Gas strategy for this network, overriding `project.tx_type` and the project gas prices
+    pub gas: Option<GasStrategy>,
+    /// Proxy for this network's RPC calls, overriding `HTTPS_PROXY` / `HTTP_PROXY` and `NO_PROXY`
+    pub proxy: Option<String>,
+    /// Block the `--simulate-all` fork runs against (`--fork-block-number`)
+    pub fork_block: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ProjectConfig {
     pub name: String,
+    /// Script name without `.s.sol`; discovered from `script/` when omitted
+    #[serde(default)]
     pub script: String,
+    /// Scripts of a multi-step rollout in run order, used by `--simulate-all`
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    /// Default network; when omitted it is picked interactively or from a single-network config
+    #[serde(default)]
     pub network: String,
-    pub setup_command: String,
+    /// Dependency install command; detected from lockfiles when omitted
+    pub setup_command: Option<String>,
+    /// Replaces `setup_command` when running on Windows
+    pub setup_command_windows: Option<String>,
+    /// Replaces `setup_command` when running on Unix-like systems
+    pub setup_command_unix: Option<String>,
     pub repo: Option<String>,
     pub path: Option<String>,
+    /// Foundry project to deploy from when no `repo` is set, instead of the current directory
+    pub cwd: Option<String>,
+    /// Keep clones here across runs (fetched and checked out again) instead of a temporary directory
+    pub clone_cache_dir: Option<String>,
+    /// Name template for the clone directory, e.g. `{name}-{network}-{timestamp}`
+    pub workdir_template: Option<String>,
+    /// Refuse to broadcast a script that already deployed on the network unless `--redeploy`/`--force`
+    #[serde(default)]
+    pub guard_redeploy: bool,
+    /// Fail the run when the temporary directory cannot be removed
+    #[serde(default)]
+    pub strict_cleanup: bool,
+    /// Forge broadcast directory relative to the project, for non-default foundry.toml setups
+    #[serde(default = "default_broadcast_dir")]
+    pub broadcast_dir: String,
+    /// Forge artifact directory relative to the project (foundry.toml `out`)
+    #[serde(default = "default_out_dir")]
+    pub out_dir: String,
+    /// forge arguments appended on every network, before network `extra_args`
+    #[serde(default)]
+    pub default_forge_args: Vec<String>,
+    /// What to do when extra forge arguments repeat a flag the tool sets (`--broadcast`, `--rpc-url`, ...)
+    #[serde(default)]
+    pub duplicate_flags: FlagPolicy,
+    /// Abort the repository clone after this many seconds
+    pub clone_timeout_secs: Option<u64>,
+    /// Timeout for each JSON-RPC preflight call, default 10 seconds
+    pub rpc_timeout_secs: Option<u64>,
+    /// Abort the repository clone once more than this many MB have been received
+    pub max_clone_mb: Option<u64>,
+    /// Command prefixed to the forge script invocation, e.g. `time -v` or `strace -f`
+    pub command_wrapper: Option<String>,
+    /// Set `<prefix>GIT_SHA`, `<prefix>TIMESTAMP` and `<prefix>NETWORK` for the forge script
+    #[serde(default = "default_metadata_env")]
+    pub metadata_env: bool,
+    /// Prefix of the build metadata variables
+    #[serde(default = "default_metadata_env_prefix")]
+    pub metadata_env_prefix: String,
+    /// Verify each contract after the broadcast instead of with `forge script --verify`
+    #[serde(default)]
+    pub deferred_verify: bool,
+    /// Seconds to wait after the broadcast before the first deferred verification attempt
+    #[serde(default = "default_verify_delay_secs")]
+    pub verify_delay_secs: u64,
+    /// Extra deferred verification attempts per contract, with doubling backoff
+    #[serde(default = "default_verify_retries")]
+    pub verify_retries: u32,
+    /// Extra attempts at reading the broadcast artifact after forge exits
+    #[serde(default = "default_broadcast_read_retries")]
+    pub broadcast_read_retries: u32,
+    /// Milliseconds between broadcast artifact read attempts
+    #[serde(default = "default_broadcast_read_delay_ms")]
+    pub broadcast_read_delay_ms: u64,
+    /// On-chain contracts compared with local artifacts before broadcasting (advisory)
+    #[serde(default)]
+    pub bytecode_diff: Vec<BytecodeCheck>,
+    /// Branch to clone and track instead of the remote's default branch
+    pub git_branch: Option<String>,
+    /// Tag or commit to check out after cloning (conflicts with `git_branch`)
+    pub git_ref: Option<String>,
+    /// Name of the cloned remote, `origin` when omitted
+    pub git_remote: Option<String>,
+    /// Proxy for cloning and fetching the repository, overriding `HTTPS_PROXY` / `HTTP_PROXY`
+    pub git_proxy: Option<String>,
+    /// Gas limit for every transaction (`--gas-limit`), `${VAR}`-expandable
+    pub gas_limit: Option<String>,
+    /// Gas price in wei (`--with-gas-price`), `${VAR}`-expandable
+    pub gas_price: Option<String>,
+    /// Priority fee in wei (`--priority-gas-price`), `${VAR}`-expandable
+    pub priority_fee: Option<String>,
+    /// Transaction type; `legacy` passes `--legacy`, omitted leaves it to forge (EIP-1559)
+    pub tx_type: Option<TxType>,
+    /// Enable (`--optimize`) or disable the optimizer, overriding foundry.toml
+    pub optimize: Option<bool>,
+    /// Optimizer runs (`--optimizer-runs`), `${VAR}`-expandable
+    pub optimizer_runs: Option<String>,
+    /// Files forge leaves out of compilation (`--skip`), e.g. `["test/**"]`
+    #[serde(default)]
+    pub skip: Vec<String>,
+    /// Keystore accounts to deploy the script with, one run each (keystore auth only)
+    #[serde(default)]
+    pub senders: Vec<SenderConfig>,
+    /// Commit the run's manifest to the project's repository after a successful deploy (no push)
+    #[serde(default)]
+    pub commit_manifest: bool,
+    /// Message of that commit; `{project}`, `{network}`, `{script}` and `{sha}` are filled in
+    pub commit_message: Option<String>,
+    /// Before broadcasting, build the script and warn about contracts over the EIP-170 size limit
+    #[serde(default)]
+    pub check_code_size: bool,
+    /// forge flags that need an extra confirmation on non-local networks, even with `-y`,
+    /// mapped to why they are risky; replaces the default list
+    #[serde(default = "default_dangerous_flags")]
+    pub dangerous_flags: HashMap<String, String>,
+    /// Treat every warning as an error, like `--abort-on-warning`
+    #[serde(default)]
+    pub strict: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// An address whose on-chain code is compared with a compiled artifact
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct BytecodeCheck {
+    pub address: String,
+    /// forge artifact relative to `out_dir`, e.g. `Vault.sol/Vault.json`
+    /// (a path already starting with `out_dir` is also accepted)
+    pub artifact: String,
+}
+
+fn default_broadcast_dir() -> String {
+    "broadcast".to_string()
+}
+
+fn default_out_dir() -> String {
+    "out".to_string()
+}
+
+fn default_verify_delay_secs() -> u64 {
+    30
+}
+
+fn default_verify_retries() -> u32 {
+    3
+}
+
+fn default_broadcast_read_retries() -> u32 {
+    3
+}
+
+fn default_broadcast_read_delay_ms() -> u64 {
+    500
+}
+
+fn default_dangerous_flags() -> HashMap<String, String> {
+    [
+        (
+            "--private-key",
+            "the key is visible to other processes and ends up in shell history and CI logs",
+        ),
+        (
+            "--mnemonics",
+            "the seed phrase is visible to other processes and ends up in shell history and CI logs",
+        ),
+        (
+            "--unlocked",
+            "transactions are signed by an account unlocked on the RPC node, which a public RPC must never hold",
+        ),
+    ]
+    .into_iter()
+    .map(|(flag, reason)| (flag.to_string(), reason.to_string()))
+    .collect()
+}
+
+fn default_metadata_env() -> bool {
+    true
+}
+
+fn default_metadata_env_prefix() -> String {
+    "DEPLOY_".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct EnvConfig {
     pub vars: HashMap<String, String>,
     pub load_files: Option<Vec<String>>,
+    /// Secrets fetched from HashiCorp Vault (requires the `vault` feature)
+    pub vault: Option<VaultConfig>,
+    /// Env var name to AWS Secrets Manager secret id (requires the `aws` feature)
+    pub aws_secrets: Option<HashMap<String, String>>,
+    /// `.env` file encrypted with age, decrypted in memory (requires the `age` feature)
+    pub encrypted: Option<EncryptedEnvConfig>,
+    /// Treat required variables set to an empty value as missing
+    #[serde(default = "default_reject_empty_required")]
+    pub reject_empty_required: bool,
+    /// Required variables that may intentionally be empty
+    #[serde(default)]
+    pub allow_empty: Vec<String>,
+    /// Variables with this prefix also reach forge without it, e.g. `FORGE_` makes
+    /// `FORGE_PRIVATE_KEY` available to the script as `PRIVATE_KEY`
+    pub forge_prefix: Option<String>,
+}
+
+fn default_reject_empty_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct EncryptedEnvConfig {
+    /// Path of the encrypted env file, e.g. `secrets.env.age`
+    pub file: String,
+    /// Variable holding the age identity (`AGE-SECRET-KEY-...`)
+    pub identity_var: Option<String>,
+    /// File containing the age identity, used when `identity_var` is unset
+    pub identity_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct VaultConfig {
+    /// Vault server address, defaults to `VAULT_ADDR`
+    pub address: Option<String>,
+    /// Name of the variable holding the Vault token
+    #[serde(default = "default_vault_token_var")]
+    pub token_var: String,
+    pub secrets: Vec<VaultSecret>,
+}
+
+/// Maps one key of a Vault secret to an environment variable
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct VaultSecret {
+    /// Secret path, e.g. `secret/data/deploy` for a KV v2 mount
+    pub path: String,
+    /// Key within the secret
+    pub key: String,
+    /// Environment variable to set
+    pub var: String,
+}
+
+fn default_vault_token_var() -> String {
+    "VAULT_TOKEN".to_string()
+}
+
+/// Reaction to a problem that is usually, but not always, a mistake
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagPolicy {
+    /// Print a warning and continue
+    #[default]
+    Warn,
+    /// Abort the run
+    Error,
+}
+
+/// Transaction envelope forge broadcasts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxType {
+    /// Type 2 transactions with a base and priority fee
+    Eip1559,
+    /// Pre-EIP-1559 transactions with a single gas price (`--legacy`)
+    Legacy,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Per-network gas settings, e.g. `{ type = "legacy" }` or `{ type = "eip1559", priority_fee = "2" }`
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GasStrategy {
+    #[serde(rename = "type")]
+    pub tx_type: TxType,
+    /// Gas price in wei (`--with-gas-price`), the max fee per gas for EIP-1559
+    pub gas_price: Option<String>,
+    /// Priority fee in wei (`--priority-gas-price`), EIP-1559 only
+    pub priority_fee: Option<String>,
+}
+
+/// One deployer account of `project.senders`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SenderConfig {
+    /// Foundry keystore account name, `${VAR}`-expandable
+    pub account: String,
+    /// Address the account must resolve to; derived from the keystore when omitted
+    pub address: Option<String>,
+    /// Variable holding this account's password instead of `auth.password_var`
+    pub password_var: Option<String>,
+}
+
+/// How forge signs the broadcast transactions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Foundry keystore account (`--account` / `--password`)
+    #[default]
+    Keystore,
+    /// AWS KMS key from `AWS_KMS_KEY_ID` (`--aws`, requires the `aws` feature)
+    AwsKms,
+    /// Account unlocked on the node, e.g. anvil (`--unlocked`)
+    Unlocked,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub method: AuthMethod,
+    /// Env var holding the keystore account name
+    #[serde(default = "default_account_var")]
+    pub account_var: String,
+    /// Env var holding the keystore password
+    #[serde(default = "default_password_var")]
+    pub password_var: String,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            method: AuthMethod::default(),
+            account_var: default_account_var(),
+            password_var: default_password_var(),
+        }
+    }
+}
+
+fn default_account_var() -> String {
+    "KEYSTORE_ACCOUNT".to_string()
+}
+
+fn default_password_var() -> String {
+    "KEYSTORE_PASSWORD".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct DeploymentConfig {
+    /// Shared TOML file with `[networks.*]` tables merged into `networks`
+    pub networks_file: Option<String>,
+    /// Command printing a JSON map of network name to network config, merged into `networks`
+    pub networks_command: Option<String>,
     pub project: ProjectConfig,
     pub env: EnvConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
     pub networks: HashMap<String, NetworkConfig>,
+    /// Expected deployed address per contract name, checked after the broadcast
+    #[serde(default)]
+    pub expectations: HashMap<String, String>,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// RPC URL shared by networks without an `rpc_url`
+    pub rpc_template: Option<RpcTemplate>,
+    /// Base for relative paths in the config (`networks_file`, `env.load_files`,
+    /// `[env.encrypted]` files, `project.cwd`, `project.clone_cache_dir`): the config file's
+    /// directory or `--config-dir`
+    #[serde(skip)]
+    pub config_dir: PathBuf,
+}
+
+/// Provider URL with `{chain}` (the network's `chain`, or its name) and `{chain_id}` placeholders
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct RpcTemplate {
+    /// e.g. `https://eth-{chain}.g.alchemy.com/v2/${ALCHEMY_KEY}`
+    pub base: String,
+}
+
+impl RpcTemplate {
+    fn url_for(&self, network_name: &str, network: &NetworkConfig) -> String {
+        let slug = network.chain.as_deref().unwrap_or(network_name);
+        self.base
+            .replace("{chain}", slug)
+            .replace("{chain_id}", &network.chain_id.to_string())
+    }
+}
+
+/// Local notification when a deployment finishes
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct NotifyConfig {
+    /// Notify on completion, like `--notify-local`
+    #[serde(default)]
+    pub local: bool,
+    /// Ring the terminal bell
+    #[serde(default = "default_bell")]
+    pub bell: bool,
+    /// Shell command run on completion with `DEPLOY_STATUS`, `DEPLOY_PROJECT` and `DEPLOY_NETWORK` set
+    pub on_complete_command: Option<String>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            local: false,
+            bell: default_bell(),
+            on_complete_command: None,
+        }
+    }
+}
+
+fn default_bell() -> bool {
+    true
+}
+
+/// Network passed as JSON on the command line (`--network-override`)
+#[derive(Debug, Deserialize)]
+struct NetworkDefinition {
+    name: String,
+    #[serde(flatten)]
+    network: NetworkConfig,
+}
+
+/// Contents of a shared `networks_file`
+#[derive(Debug, Deserialize)]
+struct NetworksFile {
+    #[serde(default)]
+    networks: HashMap<String, NetworkConfig>,
+}
+
+impl NetworkConfig {
+    /// Fail unless `min_balance` is a non-negative ETH amount with at most 18 decimals
+    fn check_min_balance(&self, network_name: &str) -> Result<()> {
+        let Some(min_balance) = self.min_balance else {
+            return Ok(());
+        };
+        if !min_balance.is_finite() || min_balance < 0.0 {
+            anyhow::bail!(
+                "networks.{}.min_balance must be a non-negative ETH amount, got {}",
+                network_name,
+                min_balance
+            );
+        }
+        if let Err(e) = crate::rpc::parse_ether(&min_balance.to_string()) {
+            anyhow::bail!("networks.{}.min_balance is invalid: {}", network_name, e);
+        }
+        Ok(())
+    }
+
+    /// Fill unset fields from the built-in chain named by `chain`; explicit fields win.
+    ///
+    /// An explicit `chain_id` of another chain keeps only the name: that chain's
+    /// explorer and verifier would point at the wrong network.
+    fn apply_chain_defaults(&mut self, network_name: &str) -> Result<()> {
+        if let Some(chain_name) = &self.chain {
+            let known = chains::find(chain_name).context(format!(
+                "networks.{}.chain: unknown chain '{}' (known: {})",
+                network_name,
+                chain_name,
+                chains::names().join(", ")
+            ))?;
+
+            if self.chain_id == 0 {
+                self.chain_id = known.chain_id;
+            }
+            if self.chain_id != known.chain_id {
+                return Ok(());
+            }
+            self.explorer_url
+                .get_or_insert_with(|| known.explorer_url.to_string());
+            self.verifier
+                .get_or_insert_with(|| known.verifier.to_string());
+            if self.verifier_url.is_none() {
+                self.verifier_url = known.verifier_url.map(str::to_string);
+            }
+        }
+
+        if self.chain_id == 0 {
+            anyhow::bail!(
+                "networks.{}: chain_id is required unless `chain` names a built-in chain",
+                network_name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Fill a missing `rpc_url` from `[rpc_template]`
+    fn apply_rpc_template(
+        &mut self,
+        network_name: &str,
+        template: Option<&RpcTemplate>,
+    ) -> Result<()> {
+        if !self.rpc_url.is_empty() {
+            return Ok(());
+        }
+
+        match template {
+            Some(template) => {
+                self.rpc_url = template.url_for(network_name, self);
+                Ok(())
+            }
+            None => anyhow::bail!(
+                "networks.{}: rpc_url is required unless [rpc_template] is configured",
+                network_name
+            ),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Locate a compiled artifact inside the project's `out_dir`
+    pub fn artifact_path(&self, project_dir: &Path, artifact: &str) -> PathBuf {
+        let out_dir = Path::new(&self.out_dir);
+        let artifact = Path::new(artifact);
+
+        if artifact.is_absolute() || artifact.starts_with(out_dir) {
+            project_dir.join(artifact)
+        } else {
+            project_dir.join(out_dir).join(artifact)
+        }
+    }
+
+    /// The configured setup command for the platform and the key it came from.
+    ///
+    /// `setup_command_windows` / `setup_command_unix` win over the generic
+    /// `setup_command`; `None` means no command is configured at all.
+    pub fn setup_command_for(&self, windows: bool) -> Option<(&'static str, &str)> {
+        let platform = if windows {
+            ("setup_command_windows", &self.setup_command_windows)
+        } else {
+            ("setup_command_unix", &self.setup_command_unix)
+        };
+
+        [platform, ("setup_command", &self.setup_command)]
+            .into_iter()
+            .find_map(|(key, command)| Some((key, command.as_deref()?)))
+    }
 }
 
 impl DeploymentConfig {
+    /// JSON Schema of the configuration file, derived from the serde structs
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(DeploymentConfig).to_value()
+    }
+
     pub fn from_file(path: &str) -> Result<Self> {
+        Self::from_file_in(path, None)
+    }
+
+    /// Load a config whose relative paths resolve against `config_dir`
+    /// instead of the config file's directory
+    pub fn from_file_in(path: &str, config_dir: Option<&str>) -> Result<Self> {
         let content = fs::read_to_string(path).context("Failed to read configuration file")?;
 
-        let config: DeploymentConfig =
+        let mut config: DeploymentConfig =
             toml::from_str(&content).context("Failed to parse TOML configuration")?;
+        // An empty entry would run `script/.s.sol`
+        if let Some(index) = config
+            .project
+            .scripts
+            .iter()
+            .position(|script| script.trim().is_empty())
+        {
+            anyhow::bail!("project.scripts[{}] is empty", index);
+        }
+
+        config.config_dir = match config_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+        };
+
+        if let Some(networks_file) = config.networks_file.clone() {
+            config.merge_networks_file(&config.config_dir.join(networks_file))?;
+        }
+        for (name, network) in config.networks.iter_mut() {
+            network.apply_chain_defaults(name)?;
+            network.check_min_balance(name)?;
+            network.apply_rpc_template(name, config.rpc_template.as_ref())?;
+        }
 
         Ok(config)
     }
 
+    /// Merge networks from a shared file; networks defined in this config win
+    fn merge_networks_file(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read networks file: {}", path.display()))?;
+        let external: NetworksFile = toml::from_str(&content)
+            .context(format!("Failed to parse networks file: {}", path.display()))?;
+
+        for (name, network) in external.networks {
+            self.networks.entry(name).or_insert(network);
+        }
+
+        Ok(())
+    }
+
+    /// Merge networks printed by `networks_command`; networks from the files win.
+    ///
+    /// The command runs through `sh -c` in the config directory with `env`, once
+    /// the deployer has loaded the environment; parsing the file alone never runs it.
+    pub fn merge_networks_command(&mut self, env: &Environment) -> Result<()> {
+        let Some(command) = self.networks_command.clone() else {
+            return Ok(());
+        };
+        let command = command.as_str();
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .envs(env.get_vars())
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit());
+        if !self.config_dir.as_os_str().is_empty() {
+            cmd.current_dir(&self.config_dir);
+        }
+
+        let output = cmd
+            .output()
+            .context(format!("Failed to run networks_command: {}", command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "networks_command failed ({}): {}",
+                output
+                    .status
+                    .code()
+                    .map(|code| format!("exit code {}", code))
+                    .unwrap_or_else(|| "terminated by signal".to_string()),
+                command
+            );
+        }
+
+        let external: HashMap<String, NetworkConfig> = serde_json::from_slice(&output.stdout)
+            .context("networks_command did not print a JSON map of networks")?;
+        for (name, mut network) in external {
+            if self.networks.contains_key(&name) {
+                continue;
+            }
+            network.apply_chain_defaults(&name)?;
+            network.check_min_balance(&name)?;
+            network.apply_rpc_template(&name, self.rpc_template.as_ref())?;
+            self.networks.insert(name, network);
+        }
+
+        Ok(())
+    }
+
+    /// Register a network from a JSON definition with a `name` and the
+    /// `NetworkConfig` fields, replacing a configured network of the same name.
+    ///
+    /// Only the in-memory config changes; the file on disk is left untouched.
+    pub fn add_network_json(&mut self, json: &str) -> Result<String> {
+        let definition: NetworkDefinition =
+            serde_json::from_str(json).context("Invalid --network-override JSON")?;
+        if definition.name.trim().is_empty() {
+            anyhow::bail!("Invalid --network-override JSON: name must not be empty");
+        }
+        let mut network = definition.network;
+        network.apply_chain_defaults(&definition.name)?;
+        network.check_min_balance(&definition.name)?;
+        network.apply_rpc_template(&definition.name, self.rpc_template.as_ref())?;
+
+        self.networks.insert(definition.name.clone(), network);
+        Ok(definition.name)
+    }
+
+    /// A copy of the config with `${VAR}` references expanded from `env`.
+    ///
+    /// Covers repository and path settings, the setup command, forge and gas
+    /// arguments, expectations and every network's URLs, `extra_args` and
+    /// `approval_command`.
+    /// `[env]` itself is left as written.
+    pub fn expand_with(&self, env: &Environment) -> DeploymentConfig {
+        let expand = |value: &str| env.expand_variables(value);
+        let expand_opt = |value: &Option<String>| value.as_deref().map(expand);
+
+        let mut config = self.clone();
+
+        let project = &mut config.project;
+        project.repo = expand_opt(&project.repo);
+        project.path = expand_opt(&project.path);
+        project.cwd = expand_opt(&project.cwd);
+        project.clone_cache_dir = expand_opt(&project.clone_cache_dir);
+        project.setup_command = expand_opt(&project.setup_command);
+        project.setup_command_windows = expand_opt(&project.setup_command_windows);
+        project.setup_command_unix = expand_opt(&project.setup_command_unix);
+        project.command_wrapper = expand_opt(&project.command_wrapper);
+        project.broadcast_dir = expand(&project.broadcast_dir);
+        project.out_dir = expand(&project.out_dir);
+        project.git_branch = expand_opt(&project.git_branch);
+        project.git_ref = expand_opt(&project.git_ref);
+        project.git_remote = expand_opt(&project.git_remote);
+        project.git_proxy = expand_opt(&project.git_proxy);
+        project.gas_limit = expand_opt(&project.gas_limit);
+        project.gas_price = expand_opt(&project.gas_price);
+        project.priority_fee = expand_opt(&project.priority_fee);
+        project.optimizer_runs = expand_opt(&project.optimizer_runs);
+        for sender in project.senders.iter_mut() {
+            sender.account = expand(&sender.account);
+            sender.address = expand_opt(&sender.address);
+        }
+        for arg in project.default_forge_args.iter_mut() {
+            *arg = expand(arg);
+        }
+        for check in project.bytecode_diff.iter_mut() {
+            check.address = expand(&check.address);
+            check.artifact = expand(&check.artifact);
+        }
+
+        for network in config.networks.values_mut() {
+            network.rpc_url = expand(&network.rpc_url);
+            network.explorer_url = expand_opt(&network.explorer_url);
+            network.verifier_url = expand_opt(&network.verifier_url);
+            network.proxy = expand_opt(&network.proxy);
+            network.approval_command = expand_opt(&network.approval_command);
+            if let Some(gas) = network.gas.as_mut() {
+                gas.gas_price = expand_opt(&gas.gas_price);
+                gas.priority_fee = expand_opt(&gas.priority_fee);
+            }
+            if let Some(extra_args) = network.extra_args.as_mut() {
+                for value in extra_args.values_mut() {
+                    *value = expand(value);
+                }
+            }
+        }
+
+        for address in config.expectations.values_mut() {
+            *address = expand(address);
+        }
+
+        config
+    }
+
+    /// Fail with a starting point when neither the config, `networks_file` nor
+    /// `networks_command` defined a single network
+    pub fn ensure_networks(&self) -> Result<()> {
+        if !self.networks.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!(
+            "no networks defined; add a [networks.<name>] section, for example:\n\n\
+             [networks.sepolia]\n\
+             chain_id = 11155111\n\
+             rpc_url = \"${{SEPOLIA_RPC_URL}}\"\n\
+             verify = true"
+        )
+    }
+
     pub fn get_network(&self, network_name: &str) -> Option<&NetworkConfig> {
         self.networks.get(network_name)
     }
 
+    /// Network names in stable (alphabetical) order
+    pub fn network_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.networks.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
     pub fn get_script_name(&self) -> String {
         format!("{}.s.sol", self.project.script)
     }
+
+    /// File names of `project.scripts` in order, or just the main script when unset
+    pub fn script_names(&self) -> Vec<String> {
+        if self.project.scripts.is_empty() {
+            return vec![self.get_script_name()];
+        }
+
+        self.project
+            .scripts
+            .iter()
+            .map(|script| format!("{}.s.sol", script))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -98,9 +855,7 @@ legacy = ""
         // Test args parsing from network config
         let network = config.get_network("sepolia").unwrap();
         let args = &network.extra_args;
-        if let None = args {
-            assert!(false, "args should be Some");
-        }
+        assert!(args.is_some(), "args should be Some");
         let args = args.as_ref().unwrap();
         let entry1 = args.get("gas-limit");
         assert_eq!(entry1, Some(&"1000000".to_string()));
@@ -111,4 +866,459 @@ legacy = ""
 
         // legacy has empty value, so only the flag should be present
     }
+
+    #[test]
+    fn test_json_schema_covers_config() {
+        let schema = DeploymentConfig::json_schema();
+        let properties = &schema["properties"];
+
+        for key in ["project", "env", "auth", "networks", "expectations"] {
+            assert!(properties.get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(schema["required"], serde_json::json!(["project", "env"]));
+
+        let network = &schema["$defs"]["NetworkConfig"]["properties"];
+        assert!(network.get("auto_confirm").is_some());
+        assert!(network.get("min_balance").is_some());
+    }
+
+    #[test]
+    fn test_platform_setup_command() {
+        let mut config: DeploymentConfig = toml::from_str(
+            r#"
+[project]
+name = "test-contract"
+script = "Deploy"
+setup_command = "npm ci"
+setup_command_windows = "cmd /C npm ci --no-audit"
+
+[env.vars]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.project.setup_command_for(true),
+            Some(("setup_command_windows", "cmd /C npm ci --no-audit"))
+        );
+        assert_eq!(
+            config.project.setup_command_for(false),
+            Some(("setup_command", "npm ci"))
+        );
+
+        config.project.setup_command_unix = Some("bun install".to_string());
+        assert_eq!(
+            config.project.setup_command_for(false),
+            Some(("setup_command_unix", "bun install"))
+        );
+
+        config.project.setup_command = None;
+        config.project.setup_command_windows = None;
+        assert_eq!(config.project.setup_command_for(true), None);
+    }
+
+    #[test]
+    fn test_artifact_path_in_custom_out_dir() {
+        let mut config: DeploymentConfig = toml::from_str(
+            r#"
+[project]
+name = "test-contract"
+script = "Deploy"
+
+[env.vars]
+"#,
+        )
+        .unwrap();
+        let project_dir = Path::new("/work/token");
+
+        assert_eq!(config.project.out_dir, "out");
+        assert_eq!(
+            config
+                .project
+                .artifact_path(project_dir, "Vault.sol/Vault.json"),
+            project_dir.join("out/Vault.sol/Vault.json")
+        );
+
+        config.project.out_dir = "build/artifacts".to_string();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let artifact_dir = temp_dir.path().join("build/artifacts/Vault.sol");
+        fs::create_dir_all(&artifact_dir).unwrap();
+        fs::write(
+            artifact_dir.join("Vault.json"),
+            r#"{"deployedBytecode": {"object": "0x6080"}}"#,
+        )
+        .unwrap();
+
+        for artifact in [
+            "Vault.sol/Vault.json",
+            "build/artifacts/Vault.sol/Vault.json",
+        ] {
+            let path = config.project.artifact_path(temp_dir.path(), artifact);
+            assert_eq!(
+                crate::bytecode::read_deployed_bytecode(&path).unwrap(),
+                "0x6080"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_with() {
+        let config_content = r#"
+[project]
+name = "token"
+script = "Deploy"
+setup_command = "forge install"
+repo = "https://${GIT_TOKEN}@github.com/org/token.git"
+path = "${HOME_DIR}/deploys"
+default_forge_args = ["--gas-estimate-multiplier", "${MULTIPLIER}"]
+gas_limit = "${GAS_LIMIT}"
+
+[[project.bytecode_diff]]
+address = "${PROXY}"
+artifact = "out/Token.sol/Token.json"
+
+[env]
+vars = { RPC_KEY = "${NOT_EXPANDED}" }
+
+[expectations]
+Token = "${PROXY}"
+
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "https://eth-sepolia.g.alchemy.com/v2/${RPC_KEY}"
+verify = true
+explorer_url = "https://${EXPLORER_HOST}"
+approval_command = "approve --ticket ${TICKET}"
+
+[networks.sepolia.extra_args]
+etherscan-api-key = "${ETHERSCAN_KEY}"
+slow = "$${LITERAL}"
+"#;
+        let config: DeploymentConfig = toml::from_str(config_content).unwrap();
+
+        let mut env = Environment::new();
+        for (key, value) in [
+            ("GIT_TOKEN", "ghp_x"),
+            ("HOME_DIR", "/home/ci"),
+            ("MULTIPLIER", "130"),
+            ("GAS_LIMIT", "3000000"),
+            ("PROXY", "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512"),
+            ("RPC_KEY", "abc"),
+            ("EXPLORER_HOST", "sepolia.etherscan.io"),
+            ("ETHERSCAN_KEY", "ek"),
+            ("TICKET", "CHG-42"),
+        ] {
+            env.set(key, value);
+        }
+
+        let expanded = config.expand_with(&env);
+        let project = &expanded.project;
+        assert_eq!(
+            project.repo.as_deref(),
+            Some("https://ghp_x@github.com/org/token.git")
+        );
+        assert_eq!(project.path.as_deref(), Some("/home/ci/deploys"));
+        assert_eq!(
+            project.default_forge_args,
+            vec!["--gas-estimate-multiplier", "130"]
+        );
+        assert_eq!(project.gas_limit.as_deref(), Some("3000000"));
+        assert_eq!(
+            project.bytecode_diff[0].address,
+            "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512"
+        );
+        assert_eq!(
+            expanded.expectations["Token"],
+            "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512"
+        );
+
+        let network = &expanded.networks["sepolia"];
+        assert_eq!(network.rpc_url, "https://eth-sepolia.g.alchemy.com/v2/abc");
+        assert_eq!(
+            network.explorer_url.as_deref(),
+            Some("https://sepolia.etherscan.io")
+        );
+        assert_eq!(
+            network.approval_command.as_deref(),
+            Some("approve --ticket CHG-42")
+        );
+        let extra_args = network.extra_args.as_ref().unwrap();
+        assert_eq!(extra_args["etherscan-api-key"], "ek");
+        assert_eq!(extra_args["slow"], "${LITERAL}");
+
+        // [env] and the original config are untouched
+        assert_eq!(expanded.env.vars["RPC_KEY"], "${NOT_EXPANDED}");
+        assert_eq!(
+            config.networks["sepolia"].rpc_url,
+            "https://eth-sepolia.g.alchemy.com/v2/${RPC_KEY}"
+        );
+    }
+
+    #[test]
+    fn test_rpc_template() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(
+            &path,
+            r#"
+[project]
+name = "test-contract"
+script = "Deploy"
+
+[env.vars]
+
+[rpc_template]
+base = "https://eth-{chain}.g.alchemy.com/v2/${ALCHEMY_KEY}"
+
+[networks.mainnet]
+chain = "mainnet"
+verify = true
+
+[networks.sepolia]
+chain_id = 11155111
+verify = true
+
+[networks.local]
+chain_id = 31337
+rpc_url = "http://localhost:8545"
+verify = false
+"#,
+        )
+        .unwrap();
+
+        let config = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            config.get_network("mainnet").unwrap().rpc_url,
+            "https://eth-mainnet.g.alchemy.com/v2/${ALCHEMY_KEY}"
+        );
+        assert_eq!(
+            config.get_network("sepolia").unwrap().rpc_url,
+            "https://eth-sepolia.g.alchemy.com/v2/${ALCHEMY_KEY}"
+        );
+        assert_eq!(
+            config.get_network("local").unwrap().rpc_url,
+            "http://localhost:8545"
+        );
+
+        let untemplated = fs::read_to_string(&path)
+            .unwrap()
+            .replace("[rpc_template]", "[unused]");
+        fs::write(&path, untemplated).unwrap();
+        let err = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("rpc_url is required"));
+    }
+
+    #[test]
+    fn test_min_balance_validation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        let config = |min_balance: &str| {
+            format!(
+                "[project]\nname = \"token\"\nscript = \"Deploy\"\n\n[env.vars]\n\n[networks.anvil]\nchain_id = 31337\nrpc_url = \"http://127.0.0.1:8545\"\nverify = false\nmin_balance = {}\n",
+                min_balance
+            )
+        };
+
+        fs::write(&path, config("0.05")).unwrap();
+        let loaded = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.get_network("anvil").unwrap().min_balance, Some(0.05));
+
+        fs::write(&path, config("-1.0")).unwrap();
+        let err = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("networks.anvil.min_balance must be a non-negative ETH amount")
+        );
+
+        fs::write(&path, config("0.0000000000000000001")).unwrap();
+        let err = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("more than 18 decimals"));
+    }
+
+    #[test]
+    fn test_empty_script_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        let config = |scripts: &str| {
+            format!(
+                "[project]\nname = \"token\"\nscripts = {}\n\n[env.vars]\n\n[networks.anvil]\nchain_id = 31337\nrpc_url = \"http://127.0.0.1:8545\"\nverify = false\n",
+                scripts
+            )
+        };
+
+        fs::write(&path, config(r#"["DeployCore", "ConfigureRoles"]"#)).unwrap();
+        let loaded = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            loaded.script_names(),
+            ["DeployCore.s.sol", "ConfigureRoles.s.sol"]
+        );
+
+        fs::write(&path, config(r#"["DeployCore", " "]"#)).unwrap();
+        let err = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "project.scripts[1] is empty");
+    }
+
+    #[test]
+    fn test_chain_defaults() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(
+            &path,
+            r#"
+[project]
+name = "test-contract"
+script = "Deploy"
+
+[env.vars]
+
+[networks.base-sepolia]
+chain = "base-sepolia"
+rpc_url = "https://sepolia.base.org"
+verify = true
+
+[networks.gnosis-custom]
+chain = "gnosis"
+chain_id = 10200
+explorer_url = "https://gnosis-chiado.blockscout.com"
+rpc_url = "https://rpc.chiadochain.net"
+verify = true
+"#,
+        )
+        .unwrap();
+
+        let config = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap();
+        let base = config.get_network("base-sepolia").unwrap();
+        assert_eq!(base.chain_id, 84532);
+        assert_eq!(
+            base.explorer_url.as_deref(),
+            Some("https://sepolia.basescan.org")
+        );
+        assert_eq!(base.verifier.as_deref(), Some("etherscan"));
+
+        // Another chain id: gnosis's explorer and verifier don't apply
+        let gnosis = config.get_network("gnosis-custom").unwrap();
+        assert_eq!(gnosis.chain_id, 10200);
+        assert_eq!(
+            gnosis.explorer_url.as_deref(),
+            Some("https://gnosis-chiado.blockscout.com")
+        );
+        assert_eq!(gnosis.verifier, None);
+        assert_eq!(gnosis.verifier_url, None);
+
+        fs::write(
+            &path,
+            fs::read_to_string(&path)
+                .unwrap()
+                .replace("chain = \"base-sepolia\"", "chain = \"base-goerli\""),
+        )
+        .unwrap();
+        let err = DeploymentConfig::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("unknown chain 'base-goerli'"));
+    }
+
+    #[test]
+    fn test_networks_file_merge() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shared_dir = temp_dir.path().join("shared");
+        fs::create_dir(&shared_dir).unwrap();
+
+        fs::write(
+            shared_dir.join("networks.toml"),
+            r#"
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "https://shared-sepolia.example"
+verify = true
+
+[networks.base]
+chain_id = 8453
+rpc_url = "https://shared-base.example"
+verify = true
+"#,
+        )
+        .unwrap();
+
+        let config_path = temp_dir.path().join("deploy.toml");
+        fs::write(
+            &config_path,
+            r#"
+networks_file = "shared/networks.toml"
+
+[project]
+name = "test-contract"
+script = "Deploy"
+network = "sepolia"
+setup_command = "bun install"
+
+[env.vars]
+
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "https://project-sepolia.example"
+verify = false
+"#,
+        )
+        .unwrap();
+
+        let config = DeploymentConfig::from_file(config_path.to_str().unwrap()).unwrap();
+
+        // The project config wins on conflicts, new networks are added
+        let sepolia = config.get_network("sepolia").unwrap();
+        assert_eq!(sepolia.rpc_url, "https://project-sepolia.example");
+        assert!(!sepolia.verify);
+        assert_eq!(config.get_network("base").unwrap().chain_id, 8453);
+    }
+
+    #[test]
+    fn test_networks_command_merge() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // The mock resolver reads the chain id from [env.vars] and prints one network
+        fs::write(
+            temp_dir.path().join("networks.sh"),
+            r#"printf '{"optimism": {"chain_id": %s, "rpc_url": "https://mainnet.optimism.io", "verify": false}}' "$OP_CHAIN_ID""#,
+        )
+        .unwrap();
+
+        let config_path = temp_dir.path().join("deploy.toml");
+        let write_config = |command: &str| {
+            fs::write(
+                &config_path,
+                format!(
+                    r#"
+networks_command = "{}"
+
+[project]
+name = "test-contract"
+script = "Deploy"
+network = "optimism"
+
+[env.vars]
+OP_CHAIN_ID = "10"
+"#,
+                    command
+                ),
+            )
+            .unwrap();
+        };
+
+        let mut env = Environment::new();
+        env.set("OP_CHAIN_ID", "10");
+
+        write_config("sh networks.sh");
+        let mut config = DeploymentConfig::from_file(config_path.to_str().unwrap()).unwrap();
+        // Parsing alone never runs the command
+        assert!(config.networks.is_empty());
+        config.merge_networks_command(&env).unwrap();
+        let optimism = config.get_network("optimism").unwrap();
+        assert_eq!(optimism.chain_id, 10);
+        assert_eq!(optimism.rpc_url, "https://mainnet.optimism.io");
+
+        write_config("exit 3");
+        let mut config = DeploymentConfig::from_file(config_path.to_str().unwrap()).unwrap();
+        let err = config.merge_networks_command(&env).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("networks_command failed (exit code 3)")
+        );
+    }
 }
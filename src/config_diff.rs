@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::config::DeploymentConfig;
+use crate::redact;
+
+/// One difference between two parsed configs, addressed by a dotted path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Added {
+        path: String,
+        value: String,
+    },
+    Removed {
+        path: String,
+        value: String,
+    },
+    Changed {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// Compare two configs field by field.
+///
+/// Tables present on one side only (e.g. a whole network) are reported as a
+/// single addition or removal. Values are redacted: `env.vars` values are
+/// always masked, secret-looking keys too, and credentials in URLs.
+pub fn diff(old: &DeploymentConfig, new: &DeploymentConfig) -> Result<Vec<Change>> {
+    let old = serde_json::to_value(old).context("Failed to serialize old config")?;
+    let new = serde_json::to_value(new).context("Failed to serialize new config")?;
+
+    let mut changes = Vec::new();
+    diff_values("", &old, &new, &mut changes);
+    Ok(changes)
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match (old_map.get(key), new_map.get(key)) {
+                    (Some(old), Some(new)) => diff_values(&child, old, new, changes),
+                    (None, Some(Value::Null)) | (Some(Value::Null), None) => {}
+                    (None, Some(new)) => changes.push(Change::Added {
+                        value: display(&child, new),
+                        path: child,
+                    }),
+                    (Some(old), None) => changes.push(Change::Removed {
+                        value: display(&child, old),
+                        path: child,
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if old == new => {}
+        (Value::Null, new) => changes.push(Change::Added {
+            path: path.to_string(),
+            value: display(path, new),
+        }),
+        (old, Value::Null) => changes.push(Change::Removed {
+            path: path.to_string(),
+            value: display(path, old),
+        }),
+        (old, new) => changes.push(Change::Changed {
+            path: path.to_string(),
+            old: display(path, old),
+            new: display(path, new),
+        }),
+    }
+}
+
+/// Render a value for the diff with secrets redacted
+fn display(path: &str, value: &Value) -> String {
+    redacted(path, value).to_string()
+}
+
+fn redacted(path: &str, value: &Value) -> Value {
+    let key = path.rsplit('.').next().unwrap_or(path);
+
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), redacted(&format!("{}.{}", path, k), v)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|v| redacted(path, v)).collect()),
+        Value::String(_) if path.starts_with("env.vars.") || redact::is_sensitive_key(key) => {
+            Value::String(redact::MASK.to_string())
+        }
+        Value::String(s) if key == "rpc_url" => Value::String(redact::redact_rpc_url(s)),
+        Value::String(s) => Value::String(redact::redact_url(s)),
+        other => other.clone(),
+    }
+}
+
+pub fn print_human(changes: &[Change]) {
+    if changes.is_empty() {
+        println!("{}", "Configs are equivalent".green());
+        return;
+    }
+
+    for change in changes {
+        match change {
+            Change::Added { path, value } => {
+                println!("{} {} = {}", "+".green(), path, value)
+            }
+            Change::Removed { path, value } => {
+                println!("{} {} = {}", "-".red(), path, value)
+            }
+            Change::Changed { path, old, new } => {
+                println!("{} {}: {} -> {}", "~".yellow(), path, old, new)
+            }
+        }
+    }
+    println!("\n{} difference(s)", changes.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+[project]
+name = "my-token"
+script = "Deploy"
+
+[env.vars]
+KEYSTORE_PASSWORD = "hunter2"
+
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "https://eth-sepolia.g.alchemy.com/v2/aB3dE5gH7jK9mN1pQ3sT5vX7"
+verify = true
+"#;
+
+    #[test]
+    fn test_diff_changed_network() {
+        let old: DeploymentConfig = toml::from_str(BASE).unwrap();
+        let new: DeploymentConfig = toml::from_str(
+            &(BASE
+                .replace("verify = true", "verify = false")
+                .replace("hunter2", "hunter3")
+                + r#"
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/test"
+verify = true
+"#),
+        )
+        .unwrap();
+
+        let changes = diff(&old, &new).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes[0],
+            Change::Changed {
+                path: "env.vars.KEYSTORE_PASSWORD".to_string(),
+                old: "\"********\"".to_string(),
+                new: "\"********\"".to_string(),
+            }
+        );
+        assert!(matches!(&changes[1], Change::Added { path, .. } if path == "networks.mainnet"));
+        assert_eq!(
+            changes[2],
+            Change::Changed {
+                path: "networks.sepolia.verify".to_string(),
+                old: "true".to_string(),
+                new: "false".to_string(),
+            }
+        );
+
+        assert!(diff(&old, &old).unwrap().is_empty());
+    }
+}
@@ -1,18 +1,165 @@
 use anyhow::{Context, Result};
 use colored::*;
 use git2::Repository;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::Command;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::config::{DeploymentConfig, NetworkConfig};
-use crate::environment::Environment;
+use crate::broadcast::{self, DeployedContract};
+use crate::bytecode::{self, BytecodeDiff};
+use crate::clone_cache;
+use crate::code_size::{self, EIP170_LIMIT};
+use crate::command_export::{CommandFormat, ExportedCommand};
+use crate::config::{
+    AuthMethod, DeploymentConfig, FlagPolicy, NetworkConfig, SenderConfig, TxType,
+};
+use crate::ens::{self, EnsResolver};
+use crate::environment::{self, Environment};
+use crate::exit_code::ForgeFailed;
+use crate::inventory;
+use crate::manifest_commit;
+use crate::notify;
+use crate::plan::DeploymentPlan;
+use crate::proxy;
+use crate::redact;
+use crate::repo_size;
+use crate::rpc::{self, RpcClient};
+use crate::safe::{SafeCheck, SafeMode};
+use crate::simulate;
+use crate::summary::{ContractSummary, DeploymentResult, DeploymentStatus};
+use crate::validate::{CheckStatus, ValidationReport};
+use crate::verify;
+use crate::watch;
 
 pub struct ContractDeployer {
+    /// Config with `${VAR}` references expanded once the environment is loaded
     config: DeploymentConfig,
+    /// Config as written, kept for reporting undefined variables and saving artifacts
+    raw_config: DeploymentConfig,
     env: Environment,
-    skip_confirmation: bool,
+    options: DeployOptions,
+    /// Plan being replayed (`--plan`)
+    plan: Option<DeploymentPlan>,
+    /// Repository size in bytes reported before cloning (`--estimate-clone`)
+    clone_estimate: Cell<Option<u64>>,
+    /// Sender derived from the keystore account, looked up once per run
+    keystore_sender: Option<String>,
+}
+
+/// Runtime options supplied from the command line
+#[derive(Debug, Default)]
+pub struct DeployOptions {
+    /// Skip confirmation prompt and auto-confirm deployment
+    pub skip_confirmation: bool,
+    /// Networks auto-confirmed as with `skip_confirmation` (`--assume-yes-for`)
+    pub assume_yes_for: Vec<String>,
+    /// Network to deploy to instead of `project.network`
+    pub network_override: Option<String>,
+    /// Script to run instead of `project.script`
+    pub script_override: Option<String>,
+    /// Re-run the network, script and commit of the latest manifest in `output_dir`
+    pub from_last: bool,
+    /// Skip the deploy when the commit matches the latest manifest in `output_dir`
+    pub only_if_changed: bool,
+    /// Query the deployer balance before confirming, even without `min_balance`
+    pub check_balance: bool,
+    /// Continue when the `require_synced` preflight fails
+    pub force: bool,
+    /// Deploy even when `project.guard_redeploy` finds a prior deployment
+    pub redeploy: bool,
+    /// Skip the extra confirmation for `project.dangerous_flags`
+    pub force_dangerous: bool,
+    /// Query the remote for the repository size before cloning
+    pub estimate_clone: bool,
+    /// Fetch into the `project.clone_cache_dir` clone even for an already cached tag or commit
+    pub refresh_cache: bool,
+    /// Fail on the first warning instead of printing it
+    pub abort_on_warning: bool,
+    /// Don't warn when a successful forge run left no new broadcast artifact
+    pub no_broadcast_check: bool,
+    /// Keep the cloned deployment directory even after a successful deploy
+    pub no_cleanup: bool,
+    /// Print what the clone step would do and exit without cloning
+    pub explain: bool,
+    /// Simulate `project.scripts` in order instead of deploying
+    pub simulate_all: bool,
+    /// Block the simulation forks from, overriding `fork_block` of the network
+    pub fork_block: Option<u64>,
+    /// Ring the bell / run `notify.on_complete_command` when the deploy finishes
+    pub notify_local: bool,
+    /// Derive `BROADCAST_ACCOUNT` from the keystore account with `cast wallet address`
+    pub sender_from_keystore: bool,
+    /// Entry of `project.senders` this run deploys with
+    pub sender: Option<SenderConfig>,
+    /// List the variables set on the forge process before the confirmation prompt
+    pub dump_forge_env: bool,
+    /// Write the deployment plan here and stop before broadcasting
+    pub plan_out: Option<String>,
+    /// Print the forge command in this format instead of running it
+    pub emit_command: Option<CommandFormat>,
+    /// Extra env files loaded after `env.load_files`
+    pub env_files: Vec<String>,
+    /// Directory to collect broadcast files, manifest and resolved config into
+    pub output_dir: Option<String>,
+    /// Seconds to wait for an answer at the confirmation prompt before cancelling
+    pub confirm_timeout: Option<u64>,
+    /// Run `forge clean` in the project before setup
+    pub clean: bool,
+    /// Shell command replacing `project.setup_command`; empty skips setup
+    pub setup_command_override: Option<String>,
+    /// Skip `setup_project` entirely (`--no-setup`)
+    pub no_setup: bool,
+    /// Arguments passed to forge after everything else (`-- <args>`)
+    pub forge_args: Vec<String>,
+    /// Seconds the repository clone may take, overriding `project.clone_timeout_secs`
+    pub clone_timeout: Option<u64>,
+    /// Seconds each JSON-RPC call may take, overriding `project.rpc_timeout_secs`
+    pub rpc_timeout: Option<u64>,
+    /// Branch, tag or commit to deploy instead of `project.git_ref`/`git_branch`
+    pub git_ref_override: Option<String>,
+    /// Project directory replacing `project.cwd` when no repo is configured
+    pub cwd_override: Option<String>,
+    /// Base for relative paths in the config instead of the config file's directory
+    pub config_dir: Option<String>,
+    /// JSON network definition registered for this run only (`--network-override`)
+    pub network_json: Option<String>,
+    /// Overrides `project.gas_limit`
+    pub gas_limit: Option<String>,
+    /// Overrides `project.gas_price`
+    pub gas_price: Option<String>,
+    /// Overrides `project.priority_fee`
+    pub priority_fee: Option<String>,
+    /// Preflight checks enforced by `--safe`
+    pub safe: SafeMode,
+    /// Fail instead of prompting when no network is set (batch entries load on worker threads)
+    pub no_network_prompt: bool,
+}
+
+/// Latest block age tolerated by `require_synced` when `max_block_lag_secs` is unset
+const DEFAULT_MAX_BLOCK_LAG_SECS: u64 = 60;
+
+/// Answer to the confirmation prompt
+#[derive(Debug, PartialEq, Eq)]
+enum Confirmation {
+    Approved,
+    Declined,
+    TimedOut,
+}
+
+/// A project that is cloned and set up, waiting for `deploy_prepared`
+#[derive(Debug)]
+pub struct PreparedDeployment {
+    context: DeploymentContext,
+    started: Instant,
+    /// Commit `--only-if-changed` found already deployed before cloning
+    unchanged: Option<String>,
 }
 
 #[derive(Debug)]
@@ -21,76 +168,437 @@ struct DeploymentContext {
     working_directory: String,
     /// Optional path to cleanup after deployment (for temporary directories)
     cleanup_path: Option<String>,
+    /// Lock on the cached clone, held while the checkout is in use
+    _cache_lock: Option<fs::File>,
 }
 
 impl ContractDeployer {
-    /// Create a new ContractDeployer with custom skip_confirmation setting (useful for testing)
-    pub fn new(
-        config_path: &str,
-        skip_confirmation: bool,
-        network_override: Option<String>,
-        script_override: Option<String>,
-    ) -> Result<Self> {
-        let mut config = DeploymentConfig::from_file(config_path)?;
+    /// Create a new ContractDeployer from a config file and command line options
+    pub fn new(config_path: &str, options: DeployOptions) -> Result<Self> {
+        let config = DeploymentConfig::from_file_in(config_path, options.config_dir.as_deref())?;
+        Self::with_config(config, options)
+    }
+
+    /// Create a ContractDeployer that replays a plan written with `--plan-out`
+    pub fn from_plan(plan_path: &str, options: DeployOptions) -> Result<Self> {
+        let plan = DeploymentPlan::load(Path::new(plan_path))?;
+        println!(
+            "{}",
+            format!(
+                "Replaying plan {} ({} on {}, commit {})",
+                plan_path,
+                plan.script,
+                plan.network,
+                plan.git_sha.as_deref().unwrap_or("unknown")
+            )
+            .blue()
+        );
+
+        let mut config = plan.replay_config();
+        config.config_dir = match &options.config_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(plan_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+        };
+
+        let mut deployer = Self::with_config(config, options)?;
+        deployer.plan = Some(plan);
+        Ok(deployer)
+    }
+
+    fn with_config(mut config: DeploymentConfig, options: DeployOptions) -> Result<Self> {
         let env = Environment::new();
 
-        if let Some(network) = network_override {
-            config.project.network = network;
+        if let Some(definition) = &options.network_json {
+            let name = config.add_network_json(definition)?;
+            println!("Registered network '{}' from --network-override", name);
+        }
+        validate_senders(&config)?;
+
+        if options.from_last {
+            apply_last_run(&mut config, options.output_dir.as_deref())?;
+        }
+
+        if let Some(network) = &options.network_override {
+            config.project.network = network.clone();
+        }
+
+        if let Some(script) = &options.script_override {
+            config.project.script = script.clone();
         }
 
-        if let Some(script) = script_override {
-            config.project.script = script;
+        if let Some(git_ref) = &options.git_ref_override {
+            config.project.git_branch = None;
+            config.project.git_ref = Some(git_ref.clone());
+        }
+
+        if options.safe.requires(SafeCheck::EmptyRequired) {
+            config.env.reject_empty_required = true;
+        }
+        if options.safe.requires(SafeCheck::CodeSize) {
+            config.project.check_code_size = true;
+        }
+        apply_safe_networks(&mut config, &options.safe);
+
+        // --cwd is relative to where the tool runs, not to the config
+        if let Some(cwd) = &options.cwd_override {
+            let cwd = std::path::absolute(cwd).context("Failed to resolve --cwd")?;
+            config.project.cwd = Some(cwd.to_string_lossy().to_string());
         }
 
         Ok(ContractDeployer {
+            raw_config: config.clone(),
             config,
             env,
-            skip_confirmation,
+            options,
+            plan: None,
+            clone_estimate: Cell::new(None),
+            keystore_sender: None,
+        })
+    }
+
+    /// Run the deployment and report how it ended.
+    ///
+    /// A declined or timed-out confirmation is not an error: it returns a
+    /// result with the matching status. Errors are kept for hard failures.
+    pub fn deploy(&mut self) -> Result<DeploymentResult> {
+        if self.options.explain {
+            self.explain_clone();
+            return Ok(self.not_deployed(DeploymentStatus::Explained));
+        }
+
+        let outcome = self.run_deployment();
+
+        if self.options.notify_local || self.config.notify.local {
+            let status = match &outcome {
+                Ok(result) => serde_json::to_value(result.status)
+                    .ok()
+                    .and_then(|status| status.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+                Err(_) => "failed".to_string(),
+            };
+            if let Err(warning) = notify::on_complete(
+                &self.config.notify,
+                &status,
+                &self.config.project.name,
+                &self.config.project.network,
+            ) {
+                // A deployment error takes precedence over a strict-mode notification failure
+                let warned = self.warn(warning);
+                return outcome.and_then(|result| warned.map(|_| result));
+            }
+        }
+
+        outcome
+    }
+
+    fn run_deployment(&mut self) -> Result<DeploymentResult> {
+        let prepared = self.prepare()?;
+        self.deploy_prepared(prepared)
+    }
+
+    /// Load the environment, then clone and set up the project without deploying.
+    ///
+    /// Used by batch runs to prepare several projects concurrently before
+    /// deploying them one after the other with `deploy_prepared`.
+    pub fn prepare(&mut self) -> Result<PreparedDeployment> {
+        let started = Instant::now();
+
+        // Load the environment first so repository and path settings can use ${VAR}
+        self.load_and_validate_environment()?;
+        if !self.options.simulate_all
+            && let Some(block) = self
+                .config
+                .get_network(&self.config.project.network)
+                .and_then(|network| network.fork_block)
+        {
+            println!(
+                "{}",
+                format!(
+                    "Note: fork_block {} only applies with --simulate-all; the broadcast ignores it",
+                    block
+                )
+                .yellow()
+            );
+        }
+
+        // Skip the clone too when the commit can be known without it
+        let unchanged = self.unchanged_commit(self.commit_without_checkout().as_deref())?;
+        if unchanged.is_some() {
+            let context = DeploymentContext {
+                working_directory: String::new(),
+                cleanup_path: None,
+                _cache_lock: None,
+            };
+            return Ok(PreparedDeployment {
+                context,
+                started,
+                unchanged,
+            });
+        }
+
+        let context = self.prepare_deployment_context()?;
+        if let Err(e) = self.prepare_project(&context) {
+            self.release_checkout(&context)?;
+            return Err(e);
+        }
+
+        Ok(PreparedDeployment {
+            context,
+            started,
+            unchanged: None,
         })
     }
 
-    pub fn deploy(&mut self) -> Result<()> {
-        let deployment_context = self.prepare_deployment_context()?;
+    /// Deploy a project readied by `prepare` and clean up its checkout
+    pub fn deploy_prepared(&mut self, prepared: PreparedDeployment) -> Result<DeploymentResult> {
+        let PreparedDeployment {
+            context: deployment_context,
+            started,
+            unchanged,
+        } = prepared;
+        if let Some(sha) = unchanged {
+            return Ok(self.unchanged_result(&sha));
+        }
+
+        // Execute the deployment workflow; the checkout goes either way
+        let outcome = self.execute_deployment_workflow(&deployment_context);
+        let released = self.release_checkout(&deployment_context);
+        let mut result = outcome?;
+        released?;
+
+        result.duration = started.elapsed();
+
+        Ok(result)
+    }
+
+    /// Clean up the checkout of a project readied by `prepare` that won't be deployed
+    pub fn release(&self, prepared: PreparedDeployment) -> Result<()> {
+        self.release_checkout(&prepared.context)
+    }
+
+    /// RPC URL of the selected network as the deploy would pass it to forge, keys redacted
+    pub fn resolve_rpc_url(&mut self) -> Result<String> {
+        self.load_environment()?;
+
+        let network_config = self.resolved_network_config()?;
+        Ok(redact::redact_rpc_url(&network_config.rpc_url))
+    }
+
+    /// Resolve the environment like a deploy would (including the computed sender)
+    /// and write it to `path` as a sourceable file
+    pub fn export_env(&mut self, path: &Path, include_secrets: bool) -> Result<()> {
+        self.load_and_validate_environment()?;
+
+        environment::write_private(path, &self.env.export(include_secrets))?;
+        println!(
+            "{}",
+            format!(
+                "Exported the environment to {}, load it with `source {}`",
+                path.display(),
+                path.display()
+            )
+            .green()
+        );
+        Ok(())
+    }
+
+    /// Load the environment and print every variable with its source, secrets masked
+    pub fn print_env(&mut self) -> Result<()> {
+        self.load_environment()?;
 
-        // Execute the deployment workflow
-        self.execute_deployment_workflow(&deployment_context)?;
+        let rows = self.env.printable();
+        let name_width = rows
+            .iter()
+            .map(|(key, _, _)| key.len())
+            .max()
+            .unwrap_or_default()
+            .max("NAME".len());
+        let source_width = rows
+            .iter()
+            .map(|(_, source, _)| source.len())
+            .max()
+            .unwrap_or_default()
+            .max("SOURCE".len());
 
-        // Cleanup if needed
-        if let Some(cleanup_path) = &deployment_context.cleanup_path {
-            self.cleanup(cleanup_path)?;
+        let header = format!(
+            "{:<name_width$}  {:<source_width$}  VALUE",
+            "NAME", "SOURCE"
+        );
+        println!("{}", header.blue());
+        for (key, source, value) in &rows {
+            println!("{:<name_width$}  {:<source_width$}  {}", key, source, value);
         }
 
         Ok(())
     }
 
+    /// Run the validation checks without cloning or deploying
+    pub fn validate(&mut self) -> ValidationReport {
+        // Networks from networks_command and the network choice need the environment
+        let env_result = self.load_environment();
+        let env_loaded = env_result.is_ok();
+
+        // The raw config holds whatever networks loaded, even when loading failed later
+        let network = self.raw_config.project.network.clone();
+        let mut report =
+            ValidationReport::new(Some(network.clone()), Some(self.config.get_script_name()));
+
+        report.add(
+            "config",
+            CheckStatus::Pass,
+            "Configuration parsed successfully",
+        );
+
+        let network_result = self
+            .raw_config
+            .get_network(&network)
+            .map(|n| format!("chain_id {}", n.chain_id))
+            .context(format!("Network '{}' not found in configuration", network));
+        let network_found = network_result.is_ok();
+        report.add_result("network", network_result);
+
+        report.add_result(
+            "environment",
+            env_result.map(|_| "Environment loaded".to_string()),
+        );
+
+        if env_loaded {
+            // The same signer preparation as a deploy, which may derive the sender
+            report.add_result(
+                "auth",
+                self.prepare_auth()
+                    .map(|_| "Signer prerequisites are met".to_string()),
+            );
+            report.add_result(
+                "required_vars",
+                self.validate_environment()
+                    .map(|_| "All required variables are set".to_string()),
+            );
+        } else {
+            report.add("auth", CheckStatus::Skip, "Environment not loaded");
+            report.add("required_vars", CheckStatus::Skip, "Environment not loaded");
+        }
+
+        if network_found && env_loaded {
+            report.add_result("rpc_url", self.check_rpc_url());
+        } else {
+            report.add(
+                "rpc_url",
+                CheckStatus::Skip,
+                "Network or environment unavailable",
+            );
+        }
+
+        match &self.config.project.repo {
+            Some(_) => report.add(
+                "script",
+                CheckStatus::Skip,
+                "Script is checked after cloning the repository",
+            ),
+            None if self.config.project.script.is_empty() => {
+                match discover_script(&self.local_dir()) {
+                    Ok(script) => report.add(
+                        "script",
+                        CheckStatus::Pass,
+                        format!("Auto-selected script/{}.s.sol", script),
+                    ),
+                    Err(e) => report.add("script", CheckStatus::Fail, e.to_string()),
+                }
+            }
+            None => {
+                let script_path = format!("script/{}", self.config.get_script_name());
+                if self.local_dir().join(&script_path).exists() {
+                    report.add(
+                        "script",
+                        CheckStatus::Pass,
+                        format!("Found {}", script_path),
+                    );
+                } else {
+                    report.add(
+                        "script",
+                        CheckStatus::Fail,
+                        match &self.config.project.cwd {
+                            Some(cwd) => format!("{} not found in {}", script_path, cwd),
+                            None => format!("{} not found in the current directory", script_path),
+                        },
+                    );
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Check that the selected network's RPC URL fully resolves
+    fn check_rpc_url(&self) -> Result<String> {
+        let network_config = self.resolved_network_config()?;
+        let raw_url = &self.raw_config.networks[&self.config.project.network].rpc_url;
+
+        let undefined = self.env.undefined_variables(raw_url);
+        if !undefined.is_empty() {
+            anyhow::bail!(
+                "RPC URL references undefined variables: {}",
+                undefined.join(", ")
+            );
+        }
+
+        if network_config.rpc_url.trim().is_empty() {
+            anyhow::bail!("RPC URL is empty");
+        }
+
+        Ok("RPC URL resolved".to_string())
+    }
+
     /// Prepare the deployment context (clone repo if needed, determine working directory)
     fn prepare_deployment_context(&self) -> Result<DeploymentContext> {
         match &self.config.project.repo {
+            Some(repo_url) if self.config.project.clone_cache_dir.is_some() => {
+                // Cached clones persist by design, so there is nothing to clean up
+                let (working_directory, cache_lock) = self.prepare_cached_checkout(repo_url)?;
+                Ok(DeploymentContext {
+                    working_directory,
+                    cleanup_path: None,
+                    _cache_lock: Some(cache_lock),
+                })
+            }
             Some(repo_url) => {
                 let work_dir = self.prepare_repo_deployment(repo_url)?;
                 Ok(DeploymentContext {
                     working_directory: work_dir.clone(),
                     cleanup_path: Some(work_dir),
+                    _cache_lock: None,
                 })
             }
             None => {
-                let current_dir = std::env::current_dir()
-                    .context("Failed to get current directory")?
-                    .to_string_lossy()
-                    .to_string();
+                let working_directory = match &self.config.project.cwd {
+                    Some(cwd) => foundry_project_dir(&self.config.config_dir.join(cwd))?,
+                    None => std::env::current_dir().context("Failed to get current directory")?,
+                };
 
                 Ok(DeploymentContext {
-                    working_directory: current_dir,
+                    working_directory: working_directory.to_string_lossy().to_string(),
                     cleanup_path: None,
+                    _cache_lock: None,
                 })
             }
         }
     }
 
+    /// Directory a deployment without a repo runs in, for checks that don't require it to exist
+    fn local_dir(&self) -> PathBuf {
+        match &self.config.project.cwd {
+            Some(cwd) => self.config.config_dir.join(cwd),
+            None => PathBuf::from("."),
+        }
+    }
+
     /// Prepare deployment from repository (clone and setup directory)
     fn prepare_repo_deployment(&self, repo_url: &str) -> Result<String> {
         let base_path = self.get_deployment_base_path();
-        let temp_dir = format!("{}/{}", base_path, self.config.project.name);
+        let temp_dir = format!("{}/{}", base_path, self.get_deployment_dir_name());
 
         println!(
             "{}",
@@ -103,6 +611,52 @@ impl ContractDeployer {
         Ok(temp_dir)
     }
 
+    /// Check out the repository in `project.clone_cache_dir`, cloning it on a cache
+    /// miss; the returned lock keeps other deploys out of it
+    fn prepare_cached_checkout(&self, repo_url: &str) -> Result<(String, fs::File)> {
+        let project = &self.config.project;
+        let cache_dir = Path::new(project.clone_cache_dir.as_deref().unwrap_or_default());
+        let dir = self
+            .config
+            .config_dir
+            .join(cache_dir)
+            .join(clone_cache::cache_key(repo_url));
+        let dir_str = dir.to_string_lossy().to_string();
+        let remote = project.git_remote.as_deref().unwrap_or("origin");
+        let lock = clone_cache::lock(&dir)?;
+
+        let Some(repo) = clone_cache::open(&dir, repo_url, remote) else {
+            println!(
+                "{}",
+                format!("Clone cache miss, cloning into {}", dir.display()).blue()
+            );
+            self.clone_repo(repo_url, &dir_str)?;
+            return Ok((dir_str, lock));
+        };
+
+        if project.git_branch.is_some() && project.git_ref.is_some() {
+            anyhow::bail!("project.git_branch and project.git_ref are both set; use only one");
+        }
+        println!(
+            "{}",
+            format!("Using cached clone: {}", dir.display()).blue()
+        );
+        let sha = clone_cache::checkout(
+            &repo,
+            remote,
+            project.git_branch.as_deref(),
+            project.git_ref.as_deref(),
+            self.options.refresh_cache,
+            proxy::for_url(repo_url, project.git_proxy.as_deref()).as_deref(),
+        )?;
+        if self.options.refresh_cache {
+            println!("{}", "Cache refreshed from the remote".green());
+        }
+        println!("{}: {}", "HEAD".blue(), sha);
+
+        Ok((dir_str, lock))
+    }
+
     /// Get the base path for deployments
     fn get_deployment_base_path(&self) -> String {
         self.config
@@ -113,6 +667,27 @@ impl ContractDeployer {
             .unwrap_or_else(|| "/tmp".to_string())
     }
 
+    /// Get the clone directory name from `project.workdir_template` (defaults to the project name)
+    fn get_deployment_dir_name(&self) -> String {
+        let template = self
+            .config
+            .project
+            .workdir_template
+            .as_deref()
+            .unwrap_or("{name}");
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        template
+            .replace("{name}", &self.config.project.name)
+            .replace("{network}", &self.config.project.network)
+            .replace("{script}", &self.config.project.script)
+            .replace("{timestamp}", &timestamp.to_string())
+    }
+
     /// Sanitize path by removing quotes and trimming
     fn sanitize_path(&self, path: &str) -> String {
         path.trim()
@@ -122,35 +697,269 @@ impl ContractDeployer {
             .to_string()
     }
 
-    /// Execute the main deployment workflow
-    fn execute_deployment_workflow(&mut self, context: &DeploymentContext) -> Result<()> {
+    /// Remove the temporary checkout, unless `--no-cleanup`
+    fn release_checkout(&self, context: &DeploymentContext) -> Result<()> {
+        if let Some(cleanup_path) = &context.cleanup_path {
+            // An emitted command is run later, in this checkout
+            if self.options.no_cleanup || self.options.emit_command.is_some() {
+                println!(
+                    "{}",
+                    format!(
+                        "Skipping cleanup, deployment directory retained at: {}",
+                        cleanup_path
+                    )
+                    .yellow()
+                );
+            } else {
+                self.cleanup(cleanup_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify one already deployed contract, independent of any broadcast or manifest.
+    ///
+    /// Uses the network's verifier settings and the loaded environment; no
+    /// signer is needed. A configured repo is cloned and set up first.
+    pub fn verify_address(&mut self, address: &str, contract: &str) -> Result<()> {
+        if !ens::is_address(address) {
+            anyhow::bail!(
+                "--address is not a 0x-prefixed 20-byte address: {}",
+                address
+            );
+        }
+
+        self.load_environment()?;
+        let network_config = self.resolved_network_config()?;
+
+        let context = self.prepare_deployment_context()?;
+        self.setup_project(&context.working_directory)?;
+
+        let target = DeployedContract {
+            name: contract.to_string(),
+            address: address.to_string(),
+        };
+        println!(
+            "{}",
+            format!(
+                "Verifying {} at {} on {}...",
+                contract, address, self.config.project.network
+            )
+            .blue()
+        );
+        let result = verify::verify_contract(
+            Path::new(&context.working_directory),
+            &target,
+            &network_config,
+            self.env.get_vars(),
+        );
+
+        self.release_checkout(&context)?;
+
+        match result {
+            Ok(()) => {
+                println!("{} {} verified", "✓".green(), contract);
+                Ok(())
+            }
+            Err(e) => Err(e.context(format!("Verification of {} failed", contract))),
+        }
+    }
+
+    /// Deploy, then redeploy whenever `src/` or `script/` change, until Ctrl-C.
+    ///
+    /// Setup happens once; later rounds only re-run forge. Only local projects
+    /// on local networks are accepted and the confirmation prompt is skipped.
+    pub fn watch(&mut self) -> Result<()> {
+        if self.config.project.repo.is_some() {
+            anyhow::bail!(
+                "--watch needs a local project: edits in your tree never reach the temporary clone of project.repo"
+            );
+        }
+        let network_config = self
+            .config
+            .get_network(&self.config.project.network)
+            .context(format!(
+                "Network '{}' not found in configuration",
+                self.config.project.network
+            ))?;
+        if !watch::is_local_network(network_config) {
+            anyhow::bail!(
+                "--watch only runs against local networks (an RPC URL on localhost), not '{}' ({})",
+                self.config.project.network,
+                redact::redact_rpc_url(&network_config.rpc_url)
+            );
+        }
+
+        let stop = watch::ctrl_c_flag()?;
+        self.options.skip_confirmation = true;
+
+        let prepared = self.prepare()?;
+        let project_dir = PathBuf::from(&prepared.context.working_directory);
+        let dirs: Vec<PathBuf> = watch::WATCHED_DIRS
+            .iter()
+            .map(|dir| project_dir.join(dir))
+            .collect();
+
+        loop {
+            match self.execute_deployment_workflow(&prepared.context) {
+                Ok(result) => {
+                    // Under --safe the network name is typed once, not on every change
+                    if !matches!(
+                        result.status,
+                        DeploymentStatus::Cancelled | DeploymentStatus::TimedOut
+                    ) {
+                        self.options.safe.skip(SafeCheck::TypedConfirm);
+                    }
+                    result.print_human();
+                }
+                Err(e) => println!("{}", format!("Deployment failed: {:#}", e).red()),
+            }
+
+            println!(
+                "\n{}",
+                format!(
+                    "Watching {} for changes (Ctrl-C to stop)...",
+                    watch::WATCHED_DIRS.join(", ")
+                )
+                .blue()
+            );
+            if !watch::wait_for_change(
+                &dirs,
+                Duration::from_millis(500),
+                Duration::from_millis(300),
+                &stop,
+            ) {
+                break;
+            }
+            println!("{}", "Change detected, redeploying".blue());
+        }
+
+        println!("\nStopping watch mode");
+        self.release_checkout(&prepared.context)
+    }
+
+    /// Get the checkout ready: clean, check the planned commit, run the setup
+    /// command and pick the script
+    fn prepare_project(&mut self, context: &DeploymentContext) -> Result<()> {
         println!(
             "{}",
             format!("Starting deployment in: {}", context.working_directory).green()
         );
 
-        // Load environment configuration
-        self.load_and_validate_environment()?;
+        if self.options.clean {
+            self.clean_build_artifacts(&context.working_directory)?;
+        }
+
+        if let Some(planned) = self.plan.as_ref().and_then(|plan| plan.git_sha.as_ref()) {
+            let actual = head_sha(Path::new(&context.working_directory));
+            if actual.as_ref() != Some(planned) {
+                anyhow::bail!(
+                    "Project is at commit {}, but the plan was made at {}",
+                    actual.as_deref().unwrap_or("unknown"),
+                    planned
+                );
+            }
+        }
+
+        // --from-last can only check out the recorded commit in a clone
+        if self.options.from_last
+            && self.config.project.repo.is_none()
+            && let Some(recorded) = &self.config.project.git_ref
+        {
+            let actual = head_sha(Path::new(&context.working_directory));
+            if !actual
+                .as_deref()
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(recorded))
+            {
+                anyhow::bail!(
+                    "Project is at commit {}, but the last run deployed {}; check it out first",
+                    actual.as_deref().unwrap_or("unknown"),
+                    recorded
+                );
+            }
+        }
 
         // Setup project (install dependencies)
         self.setup_project(&context.working_directory)?;
 
-        // Deploy contract
-        self.deploy_contract(&context.working_directory)?;
+        if self.config.project.script.is_empty() && self.config.project.scripts.is_empty() {
+            let script = discover_script(Path::new(&context.working_directory))?;
+            println!(
+                "Auto-selected script: {}",
+                format!("{}.s.sol", script).green()
+            );
+            self.config.project.script = script.clone();
+            self.raw_config.project.script = script;
+        }
 
         Ok(())
     }
 
+    /// Execute the main deployment workflow
+    fn execute_deployment_workflow(
+        &mut self,
+        context: &DeploymentContext,
+    ) -> Result<DeploymentResult> {
+        if self.options.simulate_all {
+            return self.simulate_all(Path::new(&context.working_directory));
+        }
+
+        // Deploy contract
+        self.deploy_contract(&context.working_directory)
+    }
+
     /// Load environment configuration and validate required variables
     fn load_and_validate_environment(&mut self) -> Result<()> {
         println!("{}", "Loading environment configuration...".blue());
+        self.load_environment()?;
+        self.validate_loaded_environment()
+    }
+
+    /// Load the environment, add the networks of `networks_command`, pick the
+    /// network and expand the config. Every entry point that reads the config
+    /// beyond the project settings goes through here.
+    fn load_environment(&mut self) -> Result<()> {
+        self.load_env()?;
 
-        // Load environment configuration
-        self.env.load_from_config(&self.config.env)?;
+        if self.raw_config.networks_command.is_some() {
+            self.raw_config.merge_networks_command(&self.env)?;
+            apply_safe_networks(&mut self.raw_config, &self.options.safe);
+        }
+        self.raw_config.ensure_networks()?;
+
+        if self.raw_config.project.network.is_empty() {
+            // Only prompt when someone can answer; -y implies an unattended run
+            let interactive = io::stdout().is_terminal()
+                && !self.options.skip_confirmation
+                && !self.options.no_network_prompt;
+            self.raw_config.project.network =
+                select_network(&self.raw_config, interactive, &mut io::stdin().lock())?;
+        }
+
+        self.config = self.raw_config.expand_with(&self.env);
+        Ok(())
+    }
+
+    /// Check that the deploy can sign with the loaded environment
+    fn validate_loaded_environment(&mut self) -> Result<()> {
+        // Make sure the signer can be used before validating its variables
+        self.prepare_auth()?;
+        if let Some(sender) = self.options.sender.clone() {
+            self.apply_sender(&sender, "cast")?;
+        } else if self.options.sender_from_keystore {
+            self.derive_keystore_sender("cast")?;
+        }
 
         // Validate required environment variables
         self.validate_environment()?;
 
+        // Resolve an ENS sender once so every later step sees the address
+        self.resolve_sender_name()?;
+
+        // The sender may only be known now (KMS, keystore, --sender)
+        self.config = self.raw_config.expand_with(&self.env);
+        self.check_strict_expansion()?;
+
         println!(
             "{}",
             "Environment validation completed successfully!".green()
@@ -158,322 +967,5763 @@ impl ContractDeployer {
         Ok(())
     }
 
-    /// Clean up temporary files and directories
-    fn cleanup(&self, cleanup_path: &str) -> Result<()> {
-        println!("{}", format!("Cleaning up: {}", cleanup_path).yellow());
-
-        fs::remove_dir_all(cleanup_path).context("Failed to cleanup temporary directory")?;
+    /// In safe mode, fail when the selected network's config references undefined variables
+    fn check_strict_expansion(&self) -> Result<()> {
+        if !self.options.safe.requires(SafeCheck::StrictExpansion) {
+            return Ok(());
+        }
 
-        println!("{}", "Cleanup completed successfully!".green());
+        let undefined = self.undefined_network_references()?;
+        if !undefined.is_empty() {
+            anyhow::bail!(
+                "Safe mode: config references undefined variables: {}",
+                undefined.join(", ")
+            );
+        }
         Ok(())
     }
 
-    fn clone_repo(&self, repo_url: &str, target_dir: &str) -> Result<()> {
-        println!("{}", "Cloning repository...".blue());
+    /// Check signer prerequisites, deriving the sender for AWS KMS if it isn't set
+    #[cfg(feature = "aws")]
+    fn prepare_auth(&mut self) -> Result<()> {
+        if self.config.auth.method == AuthMethod::AwsKms {
+            crate::aws::ensure_forge_supports_kms()?;
 
-        if Path::new(target_dir).exists() {
-            fs::remove_dir_all(target_dir).context("Failed to remove existing directory")?;
+            if self.env.get("BROADCAST_ACCOUNT").is_none() {
+                let address = crate::aws::kms_sender_address()?;
+                println!(
+                    "{}",
+                    format!("Derived AWS KMS sender address: {}", address).green()
+                );
+                self.env.set("BROADCAST_ACCOUNT", &address);
+            }
         }
 
-        Repository::clone(repo_url, target_dir).context("Failed to clone repository")?;
-
-        println!("{}", "Repository cloned successfully!".green());
         Ok(())
     }
 
-    fn setup_project(&self, project_dir: &str) -> Result<()> {
-        println!("{}", "Setting up project...".blue());
-
-        let setup_parts: Vec<&str> = self
-            .config
-            .project
-            .setup_command
-            .split_whitespace()
-            .collect();
-        if setup_parts.is_empty() {
-            return Ok(());
-        }
-
-        let mut child = Command::new(setup_parts[0])
-            .args(&setup_parts[1..])
-            .current_dir(project_dir)
-            .stdout(std::process::Stdio::inherit()) // Show stdout in real-time
-            .stderr(std::process::Stdio::inherit()) // Show stderr in real-time
-            .spawn()
-            .context("Failed to run setup command")?;
-
-        let status = child
-            .wait()
-            .context("Failed to wait for setup command completion")?;
-
-        if status.success() {
-            println!("\n{}", "Setup command executed successfully!".green());
-        } else {
-            println!("\n{}", "Setup command execution failed!".red());
-            if let Some(code) = status.code() {
-                println!("Exit code: {}", code);
-            }
-            anyhow::bail!("Setup command execution failed with status: {}", status);
+    #[cfg(not(feature = "aws"))]
+    fn prepare_auth(&mut self) -> Result<()> {
+        if self.config.auth.method == AuthMethod::AwsKms {
+            anyhow::bail!(
+                "auth.method = \"aws_kms\" requires contract-deployer to be built with the `aws` feature"
+            );
         }
 
-        println!("{}", "Project setup completed successfully!".green());
         Ok(())
     }
 
-    fn deploy_contract(&self, project_dir: &str) -> Result<()> {
-        // Get network configuration
-        let network_config = self
-            .config
-            .get_network(&self.config.project.network)
-            .context(format!(
-                "Network '{}' not found in configuration",
-                self.config.project.network
-            ))?;
+    /// Set `BROADCAST_ACCOUNT` to the address of the keystore account, once per run.
+    ///
+    /// Does nothing for other auth methods or when the account variables are
+    /// missing, which `validate_environment` then reports.
+    fn derive_keystore_sender(&mut self, cast: &str) -> Result<()> {
+        if self.config.auth.method != AuthMethod::Keystore {
+            return Ok(());
+        }
+        if let Some(address) = &self.keystore_sender {
+            self.env.set("BROADCAST_ACCOUNT", address);
+            return Ok(());
+        }
 
-        // Expand variables in RPC URL
-        let rpc_url = self.env.expand_variables(&network_config.rpc_url);
-        let expanded_network_config = NetworkConfig {
-            chain_id: network_config.chain_id,
-            rpc_url,
-            verify: network_config.verify,
-            extra_args: network_config.extra_args.clone(),
+        let auth = &self.config.auth;
+        let (Some(account), Some(password)) = (
+            self.env.get(&auth.account_var),
+            self.env.get(&auth.password_var),
+        ) else {
+            return Ok(());
         };
 
-        self.display_deployment_info(&expanded_network_config);
+        let address = keystore_address(cast, account, password)?;
+        if let Some(configured) = self.env.get("BROADCAST_ACCOUNT")
+            && !configured.eq_ignore_ascii_case(&address)
+        {
+            self.warn(format!(
+                "Ignoring {} {}, keystore account {} is {}",
+                self.env.describe("BROADCAST_ACCOUNT"),
+                configured,
+                account,
+                address
+            ))?;
+        }
 
-        let script_name = self.config.get_script_name();
         println!(
             "{}",
-            format!("Running Forge script: {}", script_name).green()
+            format!("Derived keystore sender address: {}", address).green()
         );
+        self.env.set("BROADCAST_ACCOUNT", &address);
+        self.keystore_sender = Some(address);
 
-        // Build forge command
-        let mut forge_cmd = self.build_forge_command(&expanded_network_config, &script_name)?;
+        Ok(())
+    }
 
-        forge_cmd.current_dir(project_dir);
+    /// `project.senders`, each deployed in its own run
+    pub fn senders(&self) -> &[SenderConfig] {
+        &self.config.project.senders
+    }
 
-        // Set environment variables for the forge process
-        for (key, value) in self.env.get_vars() {
-            forge_cmd.env(key, value);
-        }
+    /// Network this deployer targets
+    pub fn network(&self) -> &str {
+        &self.config.project.network
+    }
 
-        self.display_command_info(&forge_cmd);
+    /// Resolve every `project.senders` account before the first run, so a wrong
+    /// password or address stops the rollout before anything is broadcast
+    pub fn check_senders(&mut self) -> Result<()> {
+        self.load_environment()?;
 
-        // Ask for confirmation
-        if !self.confirm_execution()? {
-            println!("Script execution cancelled");
-            return Ok(());
+        for sender in self.config.project.senders.clone() {
+            let address = self.sender_address(&sender, "cast")?;
+            println!(
+                "{}",
+                format!("Sender {}: {}", sender.account, address).green()
+            );
         }
+        Ok(())
+    }
 
-        // Execute the command
-        self.execute_forge_command(forge_cmd)?;
+    /// Sign with `sender`'s keystore account and broadcast from its address
+    fn apply_sender(&mut self, sender: &SenderConfig, cast: &str) -> Result<()> {
+        let auth = self.config.auth.clone();
+        if let Some(var) = &sender.password_var {
+            let password = self.env.get(var).cloned().context(format!(
+                "{} (password of keystore account {}) is not set",
+                var, sender.account
+            ))?;
+            self.env.set(&auth.password_var, &password);
+        }
 
+        let address = self.sender_address(sender, cast)?;
+        self.env.set(&auth.account_var, &sender.account);
+        self.env.set("BROADCAST_ACCOUNT", &address);
+        println!(
+            "{}",
+            format!("Deploying as {} ({})", sender.account, address).green()
+        );
         Ok(())
     }
 
-    fn build_forge_command(
-        &self,
-        network_config: &NetworkConfig,
-        script_name: &str,
-    ) -> Result<Command> {
-        let mut forge_cmd = Command::new("forge");
-
-        forge_cmd
-            .arg("script")
-            .arg(format!("script/{}", script_name))
-            .arg("--chain-id")
-            .arg(network_config.chain_id.to_string())
-            .arg("--rpc-url")
-            .arg(&network_config.rpc_url)
-            .arg("--broadcast");
+    /// Address of `sender`'s keystore account, checked against its configured `address`
+    fn sender_address(&self, sender: &SenderConfig, cast: &str) -> Result<String> {
+        let password_var = sender
+            .password_var
+            .as_deref()
+            .unwrap_or(&self.config.auth.password_var);
+        let password = self.env.get(password_var).context(format!(
+            "{} is not set; it is needed to unlock keystore account {}",
+            password_var, sender.account
+        ))?;
 
-        // Add verification if enabled
-        if network_config.verify {
-            forge_cmd.arg("--verify");
+        let address = keystore_address(cast, &sender.account, password)?;
+        if let Some(expected) = &sender.address
+            && !expected.eq_ignore_ascii_case(&address)
+        {
+            anyhow::bail!(
+                "project.senders: keystore account {} is {}, not the configured {}",
+                sender.account,
+                address,
+                expected
+            );
         }
+        Ok(address)
+    }
 
-        // Add account and authentication
-        if let Some(keystore_account) = self.env.get("KEYSTORE_ACCOUNT") {
-            forge_cmd.arg("--account").arg(keystore_account);
+    /// Replace an ENS name in `BROADCAST_ACCOUNT` with the address it resolves to
+    fn resolve_sender_name(&mut self) -> Result<()> {
+        let Some(sender) = self.env.get("BROADCAST_ACCOUNT").cloned() else {
+            return Ok(());
+        };
+
+        if ens::is_address(&sender) {
+            return Ok(());
         }
 
-        if let Some(keystore_password) = self.env.get("KEYSTORE_PASSWORD") {
-            forge_cmd.arg("--password").arg(keystore_password);
+        let network_config = self.resolved_network_config()?;
+        let resolver = EnsResolver::new(self.rpc_client(&network_config)?);
+        let address =
+            ens::resolve_sender(&sender, network_config.chain_id, &resolver).context(format!(
+                "{} is not a usable sender",
+                self.env.describe("BROADCAST_ACCOUNT")
+            ))?;
+
+        println!(
+            "{}",
+            format!("Resolved sender {} to {}", sender, address).green()
+        );
+        self.env.set("BROADCAST_ACCOUNT", &address);
+
+        Ok(())
+    }
+
+    /// Load the environment, sending its warnings (e.g. a missing env file) through `warn`
+    fn load_env(&mut self) -> Result<()> {
+        self.env.load_from_config(
+            &self.config.env,
+            &self.config.config_dir,
+            &self.options.env_files,
+        )?;
+        for warning in self.env.take_warnings() {
+            self.warn(warning)?;
         }
+        Ok(())
+    }
 
-        if let Some(broadcast_account) = self.env.get("BROADCAST_ACCOUNT") {
-            forge_cmd.arg("--sender").arg(broadcast_account);
+    /// `--abort-on-warning` or `project.strict`
+    fn strict_warnings(&self) -> bool {
+        self.options.abort_on_warning || self.config.project.strict
+    }
+
+    /// Print a warning, or fail with it in strict mode.
+    ///
+    /// Every warning goes through here so strict mode can't miss one.
+    fn warn(&self, message: impl std::fmt::Display) -> Result<()> {
+        if self.strict_warnings() {
+            anyhow::bail!(
+                "{} (warnings are errors with --abort-on-warning / project.strict)",
+                message
+            );
         }
+        println!("{}", format!("Warning: {}", message).yellow());
+        Ok(())
+    }
 
-        // Add extra arguments from network config
-        if let Some(args) = &network_config.extra_args {
-            args.iter().for_each(|(key, value)| {
-                forge_cmd.arg(format!("--{}", key)).arg(value);
-            });
+    /// RPC client for the network with `--rpc-timeout` / `project.rpc_timeout_secs` applied
+    fn rpc_client(&self, network_config: &NetworkConfig) -> Result<RpcClient> {
+        let timeout = self
+            .options
+            .rpc_timeout
+            .or(self.config.project.rpc_timeout_secs)
+            .map_or(rpc::DEFAULT_TIMEOUT, Duration::from_secs);
+        let proxy = proxy::for_url(&network_config.rpc_url, network_config.proxy.as_deref());
+        RpcClient::new(&network_config.rpc_url, timeout, proxy.as_deref())
+    }
+
+    /// Get the selected network configuration (expanded once the environment is loaded)
+    fn resolved_network_config(&self) -> Result<NetworkConfig> {
+        self.config
+            .get_network(&self.config.project.network)
+            .cloned()
+            .context(format!(
+                "Network '{}' not found in configuration",
+                self.config.project.network
+            ))
+    }
+
+    /// Clean up temporary files and directories.
+    ///
+    /// A failed removal only warns unless `project.strict_cleanup` is set, so a
+    /// successful deploy is not reported as failed because of a locked file.
+    fn cleanup(&self, cleanup_path: &str) -> Result<()> {
+        println!("{}", format!("Cleaning up: {}", cleanup_path).yellow());
+
+        if let Err(e) = fs::remove_dir_all(cleanup_path) {
+            if self.config.project.strict_cleanup {
+                return Err(e).context("Failed to cleanup temporary directory");
+            }
+
+            return self.warn(format!(
+                "Failed to cleanup temporary directory ({}), remove it manually: {}",
+                e, cleanup_path
+            ));
         }
 
-        Ok(forge_cmd)
+        println!("{}", "Cleanup completed successfully!".green());
+        Ok(())
     }
 
-    fn display_deployment_info(&self, network_config: &NetworkConfig) {
-        println!("\n{}", "════════════════════════════════════ DEPLOYMENT CONFIG ════════════════════════════════════".green());
-        println!("{}: {}", "PROJECT".blue(), self.config.project.name);
-        println!("{}: {}", "SCRIPT".blue(), self.config.get_script_name());
-        println!("{}: {}", "NETWORK".blue(), self.config.project.network);
-        println!("{}: {}", "CHAIN_ID".blue(), network_config.chain_id);
-        println!("{}: {}", "RPC_URL".blue(), network_config.rpc_url);
-        println!("{}: {}", "VERIFY".blue(), network_config.verify);
+    /// Print what `clone_repo` would do without touching the network or disk
+    fn explain_clone(&self) {
+        println!("\n{}", "═══════════════════════════════════════ CLONE PLAN ═══════════════════════════════════════".green());
 
-        for (key, value) in self.env.get_vars() {
-            if key.contains("API_KEY") {
-                println!("{}: {}", key.blue(), "********".yellow());
-            } else if key.contains("RPC_URL") {
-                println!("{}: {}", key.blue(), value);
+        match &self.config.project.repo {
+            Some(repo_url) => {
+                let target_dir = match &self.config.project.clone_cache_dir {
+                    Some(cache_dir) => self
+                        .config
+                        .config_dir
+                        .join(cache_dir)
+                        .join(clone_cache::cache_key(repo_url))
+                        .to_string_lossy()
+                        .to_string(),
+                    None => format!(
+                        "{}/{}",
+                        self.get_deployment_base_path(),
+                        self.get_deployment_dir_name()
+                    ),
+                };
+
+                println!("{}: {}", "REPO".blue(), redact::redact_url(repo_url));
+                println!("{}: {}", "TARGET_DIR".blue(), target_dir);
+                match (
+                    &self.config.project.git_branch,
+                    &self.config.project.git_ref,
+                ) {
+                    (Some(branch), _) => println!("{}: branch {}", "REF".blue(), branch),
+                    (None, Some(git_ref)) => println!("{}: {}", "REF".blue(), git_ref),
+                    (None, None) => println!("{}: default branch (remote HEAD)", "REF".blue()),
+                }
+                println!("{}: full", "DEPTH".blue());
+                if self.options.estimate_clone {
+                    if let Err(e) = self.estimate_clone_size(repo_url) {
+                        println!("{}", format!("{:#}", e).red());
+                    }
+                    if let Some(bytes) = self.clone_estimate.get() {
+                        println!("{}: ~{}", "CLONE_SIZE".blue(), format_mb(bytes));
+                    }
+                }
+
+                if Path::new(&target_dir).exists() {
+                    println!(
+                        "{}",
+                        "Note: The existing target directory would be removed before cloning"
+                            .yellow()
+                    );
+                }
             }
+            None => match &self.config.project.cwd {
+                Some(cwd) => println!("No repository configured, deployment would run in {}", cwd),
+                None => println!(
+                    "No repository configured, deployment would run in the current directory"
+                ),
+            },
         }
 
         println!("{}", "═══════════════════════════════════════════════════════════════════════════════════════".green());
-        println!();
+        println!("Clone skipped (explain mode)");
     }
 
-    fn display_command_info(&self, forge_cmd: &Command) {
-        // Display the full command (with sensitive info masked)
-        let masked_cmd = format!(
-            "forge {}",
-            forge_cmd
-                .get_args()
-                .map(|arg| arg.to_string_lossy())
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
+    /// Look up the repository size and warn when it is over `max_clone_mb`.
+    /// Best effort: remotes that can't be queried are skipped silently.
+    fn estimate_clone_size(&self, repo_url: &str) -> Result<()> {
+        let Some(bytes) = repo_size::estimate(repo_url) else {
+            return Ok(());
+        };
+        self.clone_estimate.set(Some(bytes));
+
+        println!("Estimated clone size: ~{}", format_mb(bytes));
+        if let Some(max_mb) = self.config.project.max_clone_mb
+            && bytes > max_mb.saturating_mul(1024 * 1024)
+        {
+            self.warn(format!(
+                "The repository is likely larger than project.max_clone_mb ({} MB); the clone may be aborted",
+                max_mb
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn clone_repo(&self, repo_url: &str, target_dir: &str) -> Result<()> {
+        if self.options.estimate_clone {
+            self.estimate_clone_size(repo_url)?;
+        }
 
-        if let Some(keystore_account) = self.env.get("KEYSTORE_ACCOUNT") {
-            let _ = masked_cmd.replace(keystore_account, "********");
+        println!("{}", "Cloning repository...".blue());
+
+        if Path::new(target_dir).exists() {
+            fs::remove_dir_all(target_dir).context("Failed to remove existing directory")?;
         }
 
-        if let Some(keystore_password) = self.env.get("KEYSTORE_PASSWORD") {
-            let _ = masked_cmd.replace(keystore_password, "********");
+        let project = &self.config.project;
+        if project.git_branch.is_some() && project.git_ref.is_some() {
+            anyhow::bail!("project.git_branch and project.git_ref are both set; use only one");
         }
 
-        println!("\n{}", masked_cmd);
+        let proxy = proxy::for_url(repo_url, project.git_proxy.as_deref());
+        let clone_options = CloneOptions {
+            branch: project.git_branch.as_deref(),
+            git_ref: project.git_ref.as_deref(),
+            remote: project.git_remote.as_deref(),
+            timeout: self
+                .options
+                .clone_timeout
+                .or(project.clone_timeout_secs)
+                .map(Duration::from_secs),
+            max_bytes: project
+                .max_clone_mb
+                .map(|mb| usize::try_from(mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX)),
+            proxy: proxy.as_deref(),
+        };
+        let repo = clone_repository(repo_url, Path::new(target_dir), &clone_options)?;
+
+        let head = repo.head().context("Cloned repository has no HEAD")?;
+        let sha = head
+            .peel_to_commit()
+            .context("Cloned HEAD is not a commit")?
+            .id();
+        let branch = if head.is_branch() {
+            head.shorthand().unwrap_or("HEAD").to_string()
+        } else {
+            "detached".to_string()
+        };
+
+        println!("{}", "Repository cloned successfully!".green());
+        if let Some(git_ref) = &project.git_ref {
+            println!("{}: {} -> {}", "REF".blue(), git_ref, sha);
+        }
+        println!("{}: {}", "BRANCH".blue(), branch);
+        println!("{}: {}", "HEAD".blue(), sha);
+        Ok(())
     }
 
-    fn confirm_execution(&self) -> Result<bool> {
-        if self.skip_confirmation {
-            println!("Skipping confirmation (auto-confirm enabled)");
-            return Ok(true);
+    /// Run `forge clean` so the deploy starts from a fresh compile.
+    ///
+    /// Only warns when forge is missing or `forge clean` fails, since a stale
+    /// cache is not a reason to abort the deployment (except in strict mode).
+    fn clean_build_artifacts(&self, project_dir: &str) -> Result<()> {
+        println!("{}", "Cleaning forge build artifacts...".blue());
+
+        let existing: Vec<&str> = [self.config.project.out_dir.as_str(), "cache"]
+            .into_iter()
+            .filter(|dir| Path::new(project_dir).join(dir).exists())
+            .collect();
+
+        match Command::new("forge")
+            .arg("clean")
+            .current_dir(project_dir)
+            .status()
+        {
+            Ok(status) if status.success() => {
+                if existing.is_empty() {
+                    println!("Nothing to clean");
+                } else {
+                    println!("Removed: {}", existing.join(", "));
+                }
+            }
+            Ok(status) => self.warn(format!("forge clean failed ({}), skipping", status))?,
+            Err(e) => self.warn(format!("could not run forge clean ({}), skipping", e))?,
         }
 
-        print!("Continue with script execution? (y/n): ");
-        io::stdout().flush()?;
+        Ok(())
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+    /// Build the setup command, or `None` when setup should be skipped.
+    ///
+    /// `--setup-command` replaces `project.setup_command` and runs through
+    /// `sh -c` so `&&` chains work; an empty override skips setup. Without
+    /// either, the command is detected from the files in `project_dir`.
+    fn setup_command(&self, project_dir: &Path) -> Option<Command> {
+        if let Some(setup_override) = &self.options.setup_command_override {
+            if setup_override.trim().is_empty() {
+                return None;
+            }
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(setup_override);
+            return Some(cmd);
+        }
+
+        let setup_command = match self.config.project.setup_command_for(cfg!(windows)) {
+            Some((key, setup_command)) => {
+                println!("Using {}: {}", key, setup_command.green());
+                setup_command.to_string()
+            }
+            None => {
+                let (marker, detected) = detect_setup_command(project_dir)?;
+                println!(
+                    "Detected setup command from {}: {}",
+                    marker,
+                    detected.green()
+                );
+                detected.to_string()
+            }
+        };
+
+        let setup_parts: Vec<&str> = setup_command.split_whitespace().collect();
+        let (program, args) = setup_parts.split_first()?;
 
-        Ok(input == "y" || input == "yes")
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        Some(cmd)
     }
 
-    fn execute_forge_command(&self, mut forge_cmd: Command) -> Result<()> {
-        println!("{}", "Executing forge script...".blue());
+    fn setup_project(&self, project_dir: &str) -> Result<()> {
+        if self.options.no_setup {
+            println!("{}", "Skipping project setup (--no-setup)".yellow());
+            return Ok(());
+        }
 
-        // Use spawn + wait instead of output() to see real-time logs
-        let mut child = forge_cmd
+        println!("{}", "Setting up project...".blue());
+
+        let Some(mut setup_cmd) = self.setup_command(Path::new(project_dir)) else {
+            println!("No setup command, skipping setup");
+            return Ok(());
+        };
+
+        let display = std::iter::once(setup_cmd.get_program())
+            .chain(setup_cmd.get_args())
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("{}: {}", "Setup command".blue(), display);
+
+        let mut child = setup_cmd
+            .current_dir(project_dir)
             .stdout(std::process::Stdio::inherit()) // Show stdout in real-time
             .stderr(std::process::Stdio::inherit()) // Show stderr in real-time
             .spawn()
-            .context("Failed to start forge script")?;
+            .context("Failed to run setup command")?;
 
         let status = child
             .wait()
-            .context("Failed to wait for forge script completion")?;
+            .context("Failed to wait for setup command completion")?;
 
         if status.success() {
-            println!("\n{}", "Script executed successfully!".green());
+            println!("\n{}", "Setup command executed successfully!".green());
         } else {
-            println!("\n{}", "Script execution failed!".red());
+            println!("\n{}", "Setup command execution failed!".red());
             if let Some(code) = status.code() {
                 println!("Exit code: {}", code);
             }
-            anyhow::bail!("Script execution failed with status: {}", status);
+            anyhow::bail!("Setup command execution failed with status: {}", status);
         }
 
+        println!("{}", "Project setup completed successfully!".green());
         Ok(())
     }
 
-    fn validate_environment(&self) -> Result<()> {
-        let required_vars = vec!["KEYSTORE_ACCOUNT", "KEYSTORE_PASSWORD", "BROADCAST_ACCOUNT"];
+    fn deploy_contract(&self, project_dir: &str) -> Result<DeploymentResult> {
+        if let Some(sha) = self.unchanged_commit(head_sha(Path::new(project_dir)).as_deref())? {
+            return Ok(self.unchanged_result(&sha));
+        }
+        if self.config.project.commit_manifest {
+            self.check_commit_manifest(Path::new(project_dir))?;
+        }
 
-        self.env.validate_required(&required_vars)
-    }
-}
+        // Get network configuration
+        let expanded_network_config = self.resolved_network_config()?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+        self.check_chain_id(&expanded_network_config)?;
+        self.check_node_synced(&expanded_network_config)?;
+        self.check_redeploy(Path::new(project_dir), &expanded_network_config)?;
 
-    const CONFIG_CONTENT: &str = r#"
-[project]
-name = "test-contract"
-script = "Deploy"
-network = "sepolia"
-setup_command = "echo 'test'"
+        let balance = self.fetch_deployer_balance(&expanded_network_config)?;
+        self.display_deployment_info(&expanded_network_config, balance);
+        self.warn_on_low_balance(&expanded_network_config, balance)?;
+        self.check_safe_balance(&expanded_network_config, balance)?;
 
-[env]
-load_files = [".env"]
+        let script_name = self.config.get_script_name();
+        println!(
+            "{}",
+            format!("Running Forge script: {}", script_name).green()
+        );
 
-[env.vars]
-KEYSTORE_ACCOUNT = "deployer"
-KEYSTORE_PASSWORD = "****"
-BROADCAST_ACCOUNT = "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A"
+        let forge_cmd = self.deploy_command(
+            &expanded_network_config,
+            &script_name,
+            Path::new(project_dir),
+        )?;
 
-[networks.sepolia]
-chain_id = 11155111
-rpc_url = "https://eth-sepolia.g.alchemy.com/v2/test"
-verify = true
+        if let Some(format) = self.options.emit_command {
+            let exported = self.export_command(&forge_cmd, &expanded_network_config);
+            println!("{}", exported.render(format)?);
 
-[networks.sepolia.extra_args]
-gas-limit = "1000000"
-priority-gas-price = "1000000000"
-"#;
+            let mut result = self.not_deployed(DeploymentStatus::Emitted);
+            result.chain_id = Some(expanded_network_config.chain_id);
+            result.deployer = self.env.get("BROADCAST_ACCOUNT").cloned();
+            return Ok(result);
+        }
 
-    const FOUNDRY_CONFIG_CONTENT: &str = r#"
-# Full reference https://github.com/foundry-rs/foundry/tree/master/crates/config
+        self.display_command_info(&forge_cmd);
+        if self.options.dump_forge_env {
+            dump_forge_env(&forge_cmd);
+        }
+        self.report_bytecode_diff(Path::new(project_dir), &expanded_network_config)?;
+        if self.config.project.check_code_size {
+            self.check_code_size(Path::new(project_dir))?;
+        }
 
-[profile.default]
+        if let Some(plan_out) = &self.options.plan_out {
+            let plan = DeploymentPlan::new(
+                &self.raw_config,
+                head_sha(Path::new(project_dir)),
+                self.plan_command(&forge_cmd, &expanded_network_config),
+            );
+            plan.write(Path::new(plan_out))?;
+            println!(
+                "{}",
+                format!("Plan written to {}, nothing was broadcast", plan_out).green()
+            );
 
-[dependencies]
+            let mut result = self.not_deployed(DeploymentStatus::Planned);
+            result.chain_id = Some(expanded_network_config.chain_id);
+            result.git_sha = plan.git_sha;
+            result.deployer = self.env.get("BROADCAST_ACCOUNT").cloned();
+            return Ok(result);
+        }
 
-[etherscan]
-sepolia = { key = "${API_KEY_ETHERSCAN}", url = "https://api.etherscan.io/v2/api?chainid=11155111" }
+        // Ask for confirmation
+        let status = match self.confirm_execution(&expanded_network_config)? {
+            Confirmation::Approved => None,
+            Confirmation::Declined => Some(DeploymentStatus::Cancelled),
+            Confirmation::TimedOut => Some(DeploymentStatus::TimedOut),
+        };
+        let status = match status {
+            None => match self.confirm_dangerous_flags(
+                &forge_cmd,
+                &expanded_network_config,
+                read_stdin_line,
+            )? {
+                Confirmation::Approved => None,
+                Confirmation::Declined => Some(DeploymentStatus::Cancelled),
+                Confirmation::TimedOut => Some(DeploymentStatus::TimedOut),
+            },
+            status => status,
+        };
+        if let Some(status) = status {
+            println!("Script execution cancelled");
+            let mut result = self.not_deployed(status);
+            result.chain_id = Some(expanded_network_config.chain_id);
+            result.deployer = self.env.get("BROADCAST_ACCOUNT").cloned();
+            return Ok(result);
+        }
 
-[rpc_endpoints]
-sepolia = "https://ethereum-sepolia-rpc.publicnode.com"
-"#;
+        // Execute the command
+        let broadcast_before =
+            self.latest_broadcast_time(Path::new(project_dir), &expanded_network_config);
+        let started = SystemTime::now();
+        let forge_run = self.execute_forge_command(forge_cmd, self.options.output_dir.is_some())?;
+        if !self.options.no_broadcast_check
+            && let Some(problem) = self.wait_for_broadcast(
+                Path::new(project_dir),
+                &expanded_network_config,
+                broadcast_before,
+            )
+        {
+            self.warn_nothing_broadcast(&problem)?;
+        }
 
-    #[test]
-    fn test_deployer_creation() {
-        let temp_dir = tempdir().unwrap();
-        let config_path = temp_dir.path().join("test_config.toml");
+        let run_output_dir = match &self.options.output_dir {
+            Some(output_dir) => Some(self.collect_artifacts(
+                Path::new(project_dir),
+                Path::new(output_dir),
+                &expanded_network_config,
+                started,
+                forge_run.log.as_deref(),
+            )?),
+            None => None,
+        };
 
-        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+        if !self.config.expectations.is_empty() {
+            self.check_expected_addresses(Path::new(project_dir), &expanded_network_config)?;
+        }
 
-        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), true, None, None);
-        assert!(deployer.is_ok());
+        if self.config.project.commit_manifest
+            && let Some(run_output_dir) = &run_output_dir
+        {
+            // The deploy went through; a failed commit is left for the user to redo
+            if let Err(e) = self.commit_manifest(Path::new(project_dir), run_output_dir) {
+                self.warn(format!("Failed to commit the manifest: {:#}", e))?;
+            }
+        }
+
+        self.deployment_result(
+            Path::new(project_dir),
+            &expanded_network_config,
+            forge_run.exit_code,
+        )
     }
 
-    #[test]
-    fn test_deployer_execution() {
-        let temp_dir = tempdir().unwrap();
-        let config_path = temp_dir.path().join("test_config.toml");
-        let foundry_config_path = temp_dir.path().join("foundry.toml");
+    /// `current`, when `--only-if-changed` applies and it is the commit of the
+    /// latest manifest for this network and script
+    fn unchanged_commit(&self, current: Option<&str>) -> Result<Option<String>> {
+        if !self.options.only_if_changed || self.options.force {
+            return Ok(None);
+        }
+        let Some(current) = current else {
+            return Ok(None);
+        };
+        let output_dir = self
+            .options
+            .output_dir
+            .as_deref()
+            .context("--only-if-changed reads the last deploy from --output-dir")?;
 
-        fs::write(&config_path, CONFIG_CONTENT).unwrap();
-        fs::write(&foundry_config_path, FOUNDRY_CONFIG_CONTENT).unwrap();
+        let last = inventory::last_deployed_commit(
+            Path::new(output_dir),
+            &self.config.project.network,
+            &self.config.get_script_name(),
+        )?;
+        Ok(last.filter(|sha| sha.eq_ignore_ascii_case(current)))
+    }
 
-        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), true, None, None);
-        assert!(deployer.is_ok());
+    /// The commit a deploy would use, when known without cloning: HEAD of a local
+    /// project, a full commit in `git_ref`, or the remote's branch or tag
+    fn commit_without_checkout(&self) -> Option<String> {
+        let project = &self.config.project;
+        let Some(repo_url) = &project.repo else {
+            return head_sha(&self.local_dir());
+        };
+        if let Some(git_ref) = &project.git_ref
+            && git_ref.len() == 40
+            && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Some(git_ref.to_lowercase());
+        }
+
+        let proxy = proxy::for_url(repo_url, project.git_proxy.as_deref());
+        remote_commit(
+            repo_url,
+            project.git_branch.as_deref(),
+            project.git_ref.as_deref(),
+            proxy.as_deref(),
+        )
+    }
+
+    fn unchanged_result(&self, sha: &str) -> DeploymentResult {
+        println!(
+            "{}",
+            format!("No changes since last deploy ({:.7})", sha).green()
+        );
+        let mut result = self.not_deployed(DeploymentStatus::Unchanged);
+        result.git_sha = Some(sha.to_string());
+        result
+    }
+
+    /// Fail before broadcasting when `project.commit_manifest` can't commit afterwards
+    fn check_commit_manifest(&self, project_dir: &Path) -> Result<()> {
+        if self.config.project.repo.is_some() {
+            anyhow::bail!(
+                "project.commit_manifest only applies to local projects, not to a cloned project.repo"
+            );
+        }
+        let Some(output_dir) = &self.options.output_dir else {
+            anyhow::bail!("project.commit_manifest commits the manifest written by --output-dir");
+        };
+        manifest_commit::check(project_dir, Path::new(output_dir))
+    }
+
+    /// Commit the manifest and the broadcast addresses collected in `run_output_dir`
+    fn commit_manifest(&self, project_dir: &Path, run_output_dir: &Path) -> Result<()> {
+        let files: Vec<PathBuf> = [
+            run_output_dir.join("manifest.json"),
+            run_output_dir.join("broadcast").join("run-latest.json"),
+        ]
+        .into_iter()
+        .filter(|file| file.is_file())
+        .collect();
+
+        let message = manifest_commit::message(
+            self.config
+                .project
+                .commit_message
+                .as_deref()
+                .unwrap_or(manifest_commit::DEFAULT_MESSAGE),
+            &self.config.project.name,
+            &self.config.project.network,
+            &self.config.get_script_name(),
+            &head_sha(project_dir).unwrap_or_default(),
+        );
+        let oid = manifest_commit::commit_files(project_dir, &files, &message)?;
+        println!(
+            "{}",
+            format!("Committed the manifest as {:.7}: {}", oid, message).green()
+        );
+        Ok(())
+    }
+
+    fn not_deployed(&self, status: DeploymentStatus) -> DeploymentResult {
+        DeploymentResult::not_deployed(
+            status,
+            &self.config.project.name,
+            &self.config.project.network,
+            &self.config.get_script_name(),
+        )
+    }
+
+    /// Gather the metadata of a successful run for the summary
+    fn deployment_result(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+        forge_exit_code: Option<i32>,
+    ) -> Result<DeploymentResult> {
+        let run_dir = self.broadcast_run_dir(project_dir, network_config);
+        let contracts = match broadcast::latest_run_path(&run_dir) {
+            Some(latest) => self.recorded_contracts(&latest)?,
+            None => Vec::new(),
+        };
+
+        let git_sha = head_sha(project_dir);
+
+        let verified = if self.deferred_verify(network_config) && !contracts.is_empty() {
+            let policy = verify::RetryPolicy {
+                delay: Duration::from_secs(self.config.project.verify_delay_secs),
+                retries: self.config.project.verify_retries,
+            };
+            let results = verify::verify_all(
+                project_dir,
+                &project_dir.join(&self.config.project.out_dir),
+                &contracts,
+                network_config,
+                self.env.get_vars(),
+                policy,
+            );
+            let mut verified = Vec::with_capacity(results.len());
+            for (contract, result) in contracts.iter().zip(results) {
+                if let Err(e) = &result {
+                    self.warn(format!("{} not verified: {:#}", contract.name, e))?;
+                }
+                verified.push(Some(result.is_ok()));
+            }
+            verified
+        } else {
+            vec![None; contracts.len()]
+        };
+
+        Ok(DeploymentResult {
+            status: DeploymentStatus::Succeeded,
+            project: self.config.project.name.clone(),
+            network: self.config.project.network.clone(),
+            chain_id: Some(network_config.chain_id),
+            git_sha,
+            script: self.config.get_script_name(),
+            deployer: self.env.get("BROADCAST_ACCOUNT").cloned(),
+            duration: Duration::ZERO,
+            forge_exit_code,
+            contracts: contracts
+                .into_iter()
+                .zip(verified)
+                .map(|(c, verified)| ContractSummary {
+                    verified,
+                    ..ContractSummary::new(c, network_config.explorer_url.as_deref())
+                })
+                .collect(),
+        })
+    }
+
+    /// The contracts of a broadcast file, read again up to `project.broadcast_read_retries`
+    /// times while forge may still be writing it; a file that still can't be parsed is a
+    /// warning, not a failure
+    fn recorded_contracts(&self, path: &Path) -> Result<Vec<DeployedContract>> {
+        let project = &self.config.project;
+        let mut parsed = broadcast::parse_deployed_contracts(path);
+        for _ in 0..project.broadcast_read_retries {
+            if parsed.is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(project.broadcast_read_delay_ms));
+            parsed = broadcast::parse_deployed_contracts(path);
+        }
+
+        match parsed {
+            Ok(contracts) => Ok(contracts),
+            Err(e) => {
+                self.warn(format!("Deployed addresses not recorded: {:#}", e))?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Verification runs after the broadcast instead of through `forge script --verify`
+    fn deferred_verify(&self, network_config: &NetworkConfig) -> bool {
+        network_config.verify && self.config.project.deferred_verify
+    }
+
+    /// Build the script and report the contracts it compiles that are over the
+    /// EIP-170 limit: a warning, or an error under `--safe`
+    fn check_code_size(&self, project_dir: &Path) -> Result<()> {
+        let script_path = format!("script/{}", self.config.get_script_name());
+        println!("{}", "Checking contract sizes...".blue());
+
+        // forge script compiles with the same flags, so it reuses this build
+        let build = Command::new("forge")
+            .arg("build")
+            .arg(&script_path)
+            .args(self.optimizer_flags()?)
+            .args(self.skip_flags()?)
+            .envs(self.forge_env())
+            .current_dir(project_dir)
+            .stdin(Stdio::null())
+            .output();
+        let required = self.options.safe.requires(SafeCheck::CodeSize);
+        // Under --safe a check that couldn't run must not pass silently
+        let not_checked = |message: String| {
+            if required {
+                anyhow::bail!("{} (--safe)", message);
+            }
+            self.warn(message)
+        };
+        match build {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => {
+                return not_checked(format!(
+                    "Contract sizes not checked, forge build failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Err(e) => return not_checked(format!("Contract sizes not checked: {}", e)),
+        }
+
+        let out_dir = project_dir.join(&self.config.project.out_dir);
+        let oversized = match code_size::oversized(&out_dir, &script_path, EIP170_LIMIT) {
+            Ok(oversized) => oversized,
+            Err(e) => return not_checked(format!("Contract sizes not checked: {:#}", e)),
+        };
+        if oversized.is_empty() {
+            println!("{}", "All contracts fit the EIP-170 size limit".green());
+            return Ok(());
+        }
+
+        let contracts: Vec<String> = oversized
+            .iter()
+            .map(|contract| format!("{} ({} bytes)", contract.contract, contract.size))
+            .collect();
+        let message = format!(
+            "Runtime bytecode over the EIP-170 limit of {} bytes, deploying would fail: {}",
+            EIP170_LIMIT,
+            contracts.join(", ")
+        );
+        if required {
+            anyhow::bail!("{} (--safe)", message);
+        }
+        self.warn(message)
+    }
+
+    /// Show how the code at each `project.bytecode_diff` address compares to
+    /// its local artifact. Advisory only: problems are printed, never fatal.
+    fn report_bytecode_diff(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+    ) -> Result<()> {
+        if self.config.project.bytecode_diff.is_empty() {
+            return Ok(());
+        }
+
+        println!("{}", "Comparing on-chain bytecode...".blue());
+        let client = self.rpc_client(network_config)?;
+
+        for check in &self.config.project.bytecode_diff {
+            let artifact = self
+                .config
+                .project
+                .artifact_path(project_dir, &check.artifact);
+            match bytecode::diff(&check.address, &artifact, &client) {
+                Ok(diff) => {
+                    let line = format!(
+                        "{} ({}): {}",
+                        check.address,
+                        check.artifact,
+                        diff.describe()
+                    );
+                    match diff {
+                        BytecodeDiff::Changed => println!("{}", line.yellow()),
+                        BytecodeDiff::Identical => println!(
+                            "{}",
+                            format!("{} - identical code would be deployed again", line).yellow()
+                        ),
+                        _ => println!("{}", line),
+                    }
+                }
+                Err(e) => self.warn(format!(
+                    "Bytecode check for {} skipped: {:#}",
+                    check.address, e
+                ))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Broadcast directory of this script and network inside the project
+    fn broadcast_run_dir(&self, project_dir: &Path, network_config: &NetworkConfig) -> PathBuf {
+        broadcast::run_dir(
+            project_dir,
+            &self.config.project.broadcast_dir,
+            &self.config.get_script_name(),
+            network_config.chain_id,
+        )
+    }
+
+    /// When this script's latest broadcast artifact was last written, if there is one
+    fn latest_broadcast_time(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+    ) -> Option<SystemTime> {
+        let latest =
+            broadcast::latest_run_path(&self.broadcast_run_dir(project_dir, network_config))?;
+        fs::metadata(latest).and_then(|m| m.modified()).ok()
+    }
+
+    /// Why a forge run that exited 0 looks like it sent nothing, if it does:
+    /// no broadcast artifact, one older than the run, or one without transactions
+    fn missing_broadcast(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+        before: Option<SystemTime>,
+    ) -> Option<String> {
+        let run_dir = self.broadcast_run_dir(project_dir, network_config);
+        let Some(latest) = broadcast::latest_run_path(&run_dir) else {
+            return Some(format!(
+                "no broadcast artifact was written to {}",
+                run_dir.display()
+            ));
+        };
+
+        let modified = fs::metadata(&latest).and_then(|m| m.modified()).ok();
+        if before.is_some() && modified <= before {
+            return Some(format!("{} was not updated by this run", latest.display()));
+        }
+
+        match broadcast::transaction_count(&latest) {
+            Ok(0) => Some(format!("{} contains no transactions", latest.display())),
+            Ok(_) => None,
+            Err(e) => Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Give forge's broadcast artifact time to appear on slow filesystems.
+    ///
+    /// Checks `missing_broadcast` up to `project.broadcast_read_retries` more
+    /// times, `broadcast_read_delay_ms` apart, and returns the last problem if
+    /// the artifact never became readable.
+    fn wait_for_broadcast(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+        before: Option<SystemTime>,
+    ) -> Option<String> {
+        let project = &self.config.project;
+        let delay = Duration::from_millis(project.broadcast_read_delay_ms);
+        let mut problem = self.missing_broadcast(project_dir, network_config, before);
+        for _ in 0..project.broadcast_read_retries {
+            if problem.is_none() {
+                break;
+            }
+            std::thread::sleep(delay);
+            problem = self.missing_broadcast(project_dir, network_config, before);
+        }
+        problem
+    }
+
+    fn warn_nothing_broadcast(&self, problem: &str) -> Result<()> {
+        if self.strict_warnings() {
+            return self.warn(format!("forge exited successfully, but {}", problem));
+        }
+
+        println!(
+            "\n{}",
+            "═══════════════════════════════ NOTHING BROADCAST ═══════════════════════════════"
+                .red()
+        );
+        println!(
+            "{}",
+            format!(
+                "Warning: forge exited successfully, but {}. The script may only have been simulated.",
+                problem
+            )
+            .red()
+            .bold()
+        );
+        println!("{}", "Check the forge output and extra arguments before assuming anything was deployed (--no-broadcast-check silences this)".red());
+        println!(
+            "{}",
+            "═════════════════════════════════════════════════════════════════════════════════"
+                .red()
+        );
+        Ok(())
+    }
+
+    /// Compare the addresses in the latest broadcast against `[expectations]`
+    fn check_expected_addresses(
+        &self,
+        project_dir: &Path,
+        network_config: &NetworkConfig,
+    ) -> Result<()> {
+        let run_dir = self.broadcast_run_dir(project_dir, network_config);
+        let Some(latest) = broadcast::latest_run_path(&run_dir) else {
+            anyhow::bail!(
+                "Cannot check [expectations]: no broadcast file in {}",
+                run_dir.display()
+            );
+        };
+
+        let contracts = broadcast::parse_deployed_contracts(&latest)?;
+        broadcast::check_expectations(&contracts, &self.config.expectations)?;
+
+        println!(
+            "{}",
+            format!(
+                "✓ {} deployed address(es) match [expectations]",
+                self.config.expectations.len()
+            )
+            .green()
+        );
+
+        Ok(())
+    }
+
+    /// Gather what the run produced into `<output_dir>/<network>-<timestamp>/`.
+    ///
+    /// Copies the broadcast files written since `started`, saves the forge
+    /// output as `forge.log`, writes a `manifest.json` with the deployed
+    /// contracts and saves the resolved config with secret values masked.
+    fn collect_artifacts(
+        &self,
+        project_dir: &Path,
+        output_dir: &Path,
+        network_config: &NetworkConfig,
+        started: SystemTime,
+        forge_log: Option<&[u8]>,
+    ) -> Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let run_output_dir =
+            output_dir.join(format!("{}-{}", self.config.project.network, timestamp));
+        fs::create_dir_all(&run_output_dir).context(format!(
+            "Failed to create output directory: {}",
+            run_output_dir.display()
+        ))?;
+
+        let broadcast_dir = self.broadcast_run_dir(project_dir, network_config);
+        let mut contracts = Vec::new();
+
+        if broadcast_dir.is_dir() {
+            let target = run_output_dir.join("broadcast");
+            fs::create_dir_all(&target)?;
+            for entry in fs::read_dir(&broadcast_dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                // Earlier runs' files stay in the project's broadcast history
+                let this_run = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified >= started);
+                if path.is_file() && this_run {
+                    fs::copy(&path, target.join(path.file_name().unwrap_or_default()))
+                        .context(format!("Failed to copy {}", path.display()))?;
+                }
+            }
+
+            if let Some(latest) = broadcast::latest_run_path(&broadcast_dir) {
+                contracts = self.recorded_contracts(&latest)?;
+            }
+        } else {
+            self.warn(format!(
+                "No broadcast files found in {}",
+                broadcast_dir.display()
+            ))?;
+        }
+
+        let manifest = serde_json::json!({
+            "project": self.config.project.name,
+            "network": self.config.project.network,
+            "chain_id": network_config.chain_id,
+            "script": self.config.get_script_name(),
+            "timestamp": timestamp,
+            "git_sha": head_sha(project_dir),
+            "contracts": contracts,
+        });
+        fs::write(
+            run_output_dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+
+        if let Some(forge_log) = forge_log {
+            fs::write(run_output_dir.join("forge.log"), forge_log)?;
+        }
+
+        fs::write(
+            run_output_dir.join("config.toml"),
+            toml::to_string_pretty(&self.archived_config())
+                .context("Failed to serialize config")?,
+        )?;
+
+        println!(
+            "{}",
+            format!("Artifacts collected in: {}", run_output_dir.display()).green()
+        );
+        Ok(run_output_dir)
+    }
+
+    /// The expanded config as saved by `--output-dir`: secret-looking `env.vars`
+    /// and forge arguments masked, keys and credentials in URLs redacted
+    fn archived_config(&self) -> DeploymentConfig {
+        let mut config = self.config.clone();
+        for (key, value) in config.env.vars.iter_mut() {
+            if redact::is_sensitive_key(key) {
+                *value = redact::MASK.to_string();
+            } else if value.contains("://") {
+                *value = redact::redact_rpc_url(value);
+            }
+        }
+        if let Some(repo) = &mut config.project.repo {
+            *repo = redact::redact_url(repo);
+        }
+        config.project.default_forge_args = redact::redact_args(&config.project.default_forge_args);
+
+        for network in config.networks.values_mut() {
+            network.rpc_url = redact::redact_rpc_url(&network.rpc_url);
+            for url in [
+                &mut network.verifier_url,
+                &mut network.explorer_url,
+                &mut network.proxy,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *url = redact::redact_rpc_url(url);
+            }
+            for (flag, value) in network.extra_args.iter_mut().flatten() {
+                if redact::is_sensitive_key(&flag.replace('-', "_")) {
+                    *value = redact::MASK.to_string();
+                } else if value.contains("://") {
+                    *value = redact::redact_rpc_url(value);
+                }
+            }
+        }
+        config
+    }
+
+    /// The deploy's forge command, run in `project_dir` with the merged environment
+    /// and the build metadata variables
+    fn deploy_command(
+        &self,
+        network_config: &NetworkConfig,
+        script_name: &str,
+        project_dir: &Path,
+    ) -> Result<Command> {
+        let mut forge_cmd = self.build_forge_command(network_config, script_name)?;
+        forge_cmd.current_dir(project_dir);
+
+        // Set environment variables for the forge process
+        forge_cmd.envs(self.forge_env());
+        self.set_metadata_env(&mut forge_cmd, project_dir)?;
+
+        Ok(forge_cmd)
+    }
+
+    /// Variables set on forge processes: the merged environment, plus the
+    /// `env.forge_prefix` variables of it and of the process under their plain names
+    fn forge_env(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self
+            .env
+            .get_vars()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        if let Some(prefix) = self
+            .config
+            .env
+            .forge_prefix
+            .as_deref()
+            .filter(|prefix| !prefix.is_empty())
+        {
+            // Later entries win, so loaded values override the process environment
+            let prefixed = std::env::vars().chain(vars.clone());
+            vars.extend(unprefixed(prefix, prefixed));
+        }
+        vars
+    }
+
+    /// Expose the commit, start time and network to the script (`vm.envString`).
+    /// The git SHA is left out when the project is not a git checkout, and a
+    /// variable the user already set keeps its value, with a warning.
+    fn set_metadata_env(&self, forge_cmd: &mut Command, project_dir: &Path) -> Result<()> {
+        let project = &self.config.project;
+        if !project.metadata_env {
+            return Ok(());
+        }
+
+        let prefix = &project.metadata_env_prefix;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut metadata = vec![
+            ("TIMESTAMP", timestamp.to_string()),
+            ("NETWORK", project.network.clone()),
+        ];
+        if let Some(sha) = head_sha(project_dir) {
+            metadata.insert(0, ("GIT_SHA", sha));
+        }
+
+        for (suffix, value) in metadata {
+            let name = format!("{}{}", prefix, suffix);
+            if self.env.get(&name).is_some() || std::env::var_os(&name).is_some() {
+                self.warn(format!(
+                    "{} is already set; keeping it instead of the build metadata (change project.metadata_env_prefix to avoid the clash)",
+                    name
+                ))?;
+                continue;
+            }
+            forge_cmd.env(name, value);
+        }
+        Ok(())
+    }
+
+    fn build_forge_command(
+        &self,
+        network_config: &NetworkConfig,
+        script_name: &str,
+    ) -> Result<Command> {
+        self.forge_script_command(network_config, script_name, true)
+    }
+
+    /// `forge script` with all configured flags; `broadcast = false` only simulates
+    fn forge_script_command(
+        &self,
+        network_config: &NetworkConfig,
+        script_name: &str,
+        broadcast: bool,
+    ) -> Result<Command> {
+        let wrapper: Vec<&str> = self
+            .config
+            .project
+            .command_wrapper
+            .as_deref()
+            .map(|wrapper| wrapper.split_whitespace().collect())
+            .unwrap_or_default();
+        let mut forge_cmd = match wrapper.split_first() {
+            Some((program, args)) => {
+                let mut cmd = Command::new(program);
+                cmd.args(args).arg("forge");
+                cmd
+            }
+            None => Command::new("forge"),
+        };
+
+        forge_cmd
+            .arg("script")
+            .arg(format!("script/{}", script_name))
+            .arg("--chain-id")
+            .arg(network_config.chain_id.to_string())
+            .arg("--rpc-url")
+            .arg(&network_config.rpc_url);
+
+        // A broadcast can't be pinned to a past block; fork_block is simulation-only
+        if !broadcast && let Some(block) = self.fork_block(network_config) {
+            forge_cmd.arg("--fork-block-number").arg(block.to_string());
+        }
+
+        if broadcast {
+            forge_cmd.arg("--broadcast");
+
+            // Add verification if enabled and not deferred until after the broadcast
+            if network_config.verify && !self.deferred_verify(network_config) {
+                forge_cmd.arg("--verify");
+                if let Some(verifier) = &network_config.verifier {
+                    forge_cmd.arg("--verifier").arg(verifier);
+                }
+                if let Some(verifier_url) = &network_config.verifier_url {
+                    forge_cmd.arg("--verifier-url").arg(verifier_url);
+                }
+            }
+        }
+
+        // Add account and authentication
+        match self.config.auth.method {
+            AuthMethod::Keystore => {
+                if let Some(keystore_account) = self.env.get(&self.config.auth.account_var) {
+                    forge_cmd.arg("--account").arg(keystore_account);
+                }
+
+                if let Some(keystore_password) = self.env.get(&self.config.auth.password_var) {
+                    forge_cmd.arg("--password").arg(keystore_password);
+                }
+            }
+            AuthMethod::AwsKms => {
+                // forge reads the key id from AWS_KMS_KEY_ID
+                forge_cmd.arg("--aws");
+            }
+            AuthMethod::Unlocked => {
+                // The node signs for the sender, no local key material is involved
+                forge_cmd.arg("--unlocked");
+            }
+        }
+
+        if let Some(broadcast_account) = self.env.get("BROADCAST_ACCOUNT") {
+            forge_cmd.arg("--sender").arg(broadcast_account);
+        }
+
+        let gas_flags = self.gas_flags(network_config)?;
+        for (flag, value) in &gas_flags {
+            forge_cmd.arg(flag).arg(value);
+        }
+
+        let tx_type = self.tx_type(network_config);
+        if tx_type == Some(TxType::Legacy) {
+            forge_cmd.arg("--legacy");
+        }
+
+        forge_cmd.args(self.optimizer_flags()?);
+        forge_cmd.args(self.skip_flags()?);
+
+        let extra_args = self.extra_forge_args(network_config);
+
+        if tx_type == Some(TxType::Eip1559) && extra_args.iter().any(|arg| arg == "--legacy") {
+            anyhow::bail!(
+                "{} is \"eip1559\" but the extra forge arguments contain --legacy",
+                self.tx_type_source(network_config)
+            );
+        }
+
+        let set_gas_flags: Vec<&str> = gas_flags.iter().map(|(flag, _)| *flag).collect();
+        let duplicates = duplicate_flags(&extra_args, &set_gas_flags);
+        if !duplicates.is_empty() {
+            let message = format!(
+                "Extra forge arguments repeat flags the tool already sets: {}",
+                duplicates.join(", ")
+            );
+            match self.config.project.duplicate_flags {
+                FlagPolicy::Warn => self.warn(message)?,
+                FlagPolicy::Error => anyhow::bail!(
+                    "{} (remove them, or set project.duplicate_flags = \"warn\")",
+                    message
+                ),
+            }
+        }
+
+        forge_cmd.args(extra_args);
+
+        Ok(forge_cmd)
+    }
+
+    /// Extra forge arguments in precedence order: project defaults, network, CLI
+    fn extra_forge_args(&self, network_config: &NetworkConfig) -> Vec<String> {
+        let mut extra_args = self.config.project.default_forge_args.clone();
+
+        if let Some(args) = &network_config.extra_args {
+            let mut keys: Vec<&String> = args.keys().collect();
+            keys.sort();
+            for key in keys {
+                extra_args.push(format!("--{}", key));
+                extra_args.push(args[key].clone());
+            }
+        }
+
+        extra_args.extend(self.options.forge_args.iter().cloned());
+        extra_args
+    }
+
+    /// The merged extra arguments as shown in the deployment banner, secrets masked
+    fn extra_args_summary(&self, network_config: &NetworkConfig) -> String {
+        let extra_args = self.extra_forge_args(network_config);
+        if extra_args.is_empty() {
+            return "none".to_string();
+        }
+        redact::redact_args(&extra_args).join(" ")
+    }
+
+    /// Transaction type for a network: `networks.<name>.gas` wins over `project.tx_type`
+    fn tx_type(&self, network_config: &NetworkConfig) -> Option<TxType> {
+        network_config
+            .gas
+            .as_ref()
+            .map(|gas| gas.tx_type)
+            .or(self.config.project.tx_type)
+    }
+
+    /// The transaction type forge will use as shown in the deployment banner;
+    /// `--legacy` among the extra forge arguments counts too
+    fn tx_type_summary(&self, network_config: &NetworkConfig) -> &'static str {
+        let legacy_arg = self
+            .extra_forge_args(network_config)
+            .iter()
+            .any(|arg| arg == "--legacy");
+        match self.tx_type(network_config) {
+            Some(TxType::Legacy) => "legacy",
+            Some(TxType::Eip1559) => "eip1559",
+            None if legacy_arg => "legacy (extra forge arguments)",
+            None => "eip1559 (forge default)",
+        }
+    }
+
+    /// Config key the transaction type comes from, for error messages
+    fn tx_type_source(&self, network_config: &NetworkConfig) -> String {
+        if network_config.gas.is_some() {
+            format!("networks.{}.gas.type", self.config.project.network)
+        } else {
+            "project.tx_type".to_string()
+        }
+    }
+
+    /// forge flags for the typed gas settings; CLI values win over
+    /// `networks.<name>.gas`, which wins over `[project]`.
+    ///
+    /// Values must be plain integers (wei for prices).
+    fn gas_flags(&self, network_config: &NetworkConfig) -> Result<Vec<(&'static str, String)>> {
+        let project = &self.config.project;
+        let network_gas = network_config.gas.as_ref();
+        let settings = [
+            (
+                "gas_limit",
+                "--gas-limit",
+                self.options
+                    .gas_limit
+                    .as_ref()
+                    .or(project.gas_limit.as_ref()),
+            ),
+            (
+                "gas_price",
+                "--with-gas-price",
+                self.options
+                    .gas_price
+                    .as_ref()
+                    .or(network_gas.and_then(|gas| gas.gas_price.as_ref()))
+                    .or(project.gas_price.as_ref()),
+            ),
+            (
+                "priority_fee",
+                "--priority-gas-price",
+                self.options
+                    .priority_fee
+                    .as_ref()
+                    .or(network_gas.and_then(|gas| gas.priority_fee.as_ref()))
+                    .or(project.priority_fee.as_ref()),
+            ),
+        ];
+
+        let mut flags = Vec::new();
+        for (name, flag, value) in settings {
+            let Some(value) = value else {
+                continue;
+            };
+
+            let value = value.trim();
+            if value.parse::<u128>().is_err() {
+                anyhow::bail!("{} must be a whole number, got '{}'", name, value);
+            }
+            if name == "priority_fee" && self.tx_type(network_config) == Some(TxType::Legacy) {
+                anyhow::bail!(
+                    "priority_fee only applies to EIP-1559 transactions, but {} is \"legacy\"",
+                    self.tx_type_source(network_config)
+                );
+            }
+            flags.push((flag, value.to_string()));
+        }
+
+        Ok(flags)
+    }
+
+    /// forge flags for `project.optimize` and `project.optimizer_runs`, overriding foundry.toml
+    fn optimizer_flags(&self) -> Result<Vec<String>> {
+        let project = &self.config.project;
+        let mut flags = Vec::new();
+
+        match project.optimize {
+            Some(true) => flags.push("--optimize".to_string()),
+            Some(false) => flags.push("--optimize=false".to_string()),
+            None => {}
+        }
+
+        if let Some(runs) = &project.optimizer_runs {
+            let runs = runs.trim();
+            if runs.parse::<u64>().is_err() {
+                anyhow::bail!("optimizer_runs must be a whole number, got '{}'", runs);
+            }
+            if project.optimize == Some(false) {
+                anyhow::bail!("optimizer_runs has no effect with project.optimize = false");
+            }
+            flags.push("--optimizer-runs".to_string());
+            flags.push(runs.to_string());
+        }
+
+        Ok(flags)
+    }
+
+    /// `--fork-block`, else the network's `fork_block`
+    fn fork_block(&self, network_config: &NetworkConfig) -> Option<u64> {
+        self.options.fork_block.or(network_config.fork_block)
+    }
+
+    /// One `--skip <pattern>` per `project.skip` entry
+    fn skip_flags(&self) -> Result<Vec<String>> {
+        let mut flags = Vec::new();
+        for pattern in &self.config.project.skip {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                anyhow::bail!("project.skip contains an empty pattern");
+            }
+            flags.push("--skip".to_string());
+            flags.push(pattern.to_string());
+        }
+        Ok(flags)
+    }
+
+    /// Simulate every script of `project.scripts` in order and report the estimated gas.
+    ///
+    /// Each script runs without `--broadcast`; the contracts of its dry run are
+    /// passed to the following scripts as `<CONTRACT>_ADDRESS` variables.
+    fn simulate_all(&self, project_dir: &Path) -> Result<DeploymentResult> {
+        let network_config = self.resolved_network_config()?;
+        let scripts = self.config.script_names();
+        println!(
+            "{}",
+            format!(
+                "Simulating {} script(s) without broadcasting",
+                scripts.len()
+            )
+            .blue()
+        );
+        if let Some(block) = self.fork_block(&network_config) {
+            println!("{}: {}", "FORK_BLOCK".blue(), block);
+        }
+
+        let estimates = simulate::simulate_in_order(&scripts, |script, addresses| {
+            println!("{}", format!("Simulating {}", script).green());
+            let mut forge_cmd = self.forge_script_command(&network_config, script, false)?;
+            forge_cmd
+                .current_dir(project_dir)
+                .envs(self.forge_env())
+                .envs(addresses);
+            self.set_metadata_env(&mut forge_cmd, project_dir)?;
+
+            let status = forge_cmd.status().context("Failed to start forge script")?;
+            if !status.success() {
+                anyhow::bail!("Simulation of {} failed ({})", script, status);
+            }
+
+            let dry_run = broadcast::run_dir(
+                project_dir,
+                &self.config.project.broadcast_dir,
+                script,
+                network_config.chain_id,
+            )
+            .join("dry-run");
+            let Some(latest) = broadcast::latest_run_path(&dry_run) else {
+                anyhow::bail!("No dry-run artifact found in {}", dry_run.display());
+            };
+
+            Ok(simulate::ScriptEstimate {
+                script: script.to_string(),
+                gas: broadcast::estimated_gas(&latest)?,
+                contracts: broadcast::parse_deployed_contracts(&latest)?,
+            })
+        })?;
+
+        simulate::print_estimates(&estimates);
+
+        let mut result = self.not_deployed(DeploymentStatus::Simulated);
+        result.chain_id = Some(network_config.chain_id);
+        result.script = scripts.join(", ");
+        result.deployer = self.env.get("BROADCAST_ACCOUNT").cloned();
+        result.contracts = estimates
+            .into_iter()
+            .flat_map(|estimate| estimate.contracts)
+            .map(|contract| ContractSummary::new(contract, None))
+            .collect();
+        Ok(result)
+    }
+
+    /// Abort when `guard_redeploy` is set and the script already deployed on this
+    /// network, unless `--redeploy` or `--force`.
+    ///
+    /// The project's broadcast history is checked first, then the manifests
+    /// collected in `--output-dir`.
+    fn check_redeploy(&self, project_dir: &Path, network_config: &NetworkConfig) -> Result<()> {
+        if !self.config.project.guard_redeploy {
+            return Ok(());
+        }
+
+        let script_name = self.config.get_script_name();
+        let from_broadcast =
+            broadcast::latest_run_path(&self.broadcast_run_dir(project_dir, network_config))
+                .and_then(|path| {
+                    let contracts = broadcast::parse_deployed_contracts(&path).ok()?;
+                    Some((
+                        path.display().to_string(),
+                        broadcast::run_commit(&path),
+                        contracts,
+                    ))
+                })
+                .filter(|(_, _, contracts)| !contracts.is_empty());
+
+        let prior = match (from_broadcast, &self.options.output_dir) {
+            (Some(prior), _) => Some(prior),
+            (None, Some(output_dir)) => inventory::latest_run(
+                Path::new(output_dir),
+                &self.config.project.network,
+                &script_name,
+            )?
+            .map(|run| {
+                (
+                    format!("{}/*/manifest.json", output_dir),
+                    run.git_sha,
+                    run.contracts,
+                )
+            }),
+            (None, None) => None,
+        };
+
+        let Some((source, git_sha, contracts)) = prior else {
+            return Ok(());
+        };
+
+        let deployed = contracts
+            .iter()
+            .map(|c| format!("{} at {}", c.name, c.address))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!(
+            "{} already deployed on {} ({}, commit {}) according to {}",
+            script_name,
+            self.config.project.network,
+            deployed,
+            git_sha.as_deref().unwrap_or("unknown"),
+            source
+        );
+
+        if self.options.redeploy || self.options.force {
+            return self.warn(format!("{}, deploying again", message));
+        }
+
+        anyhow::bail!("{}. Use --redeploy to deploy it again", message)
+    }
+
+    /// Abort when `require_synced` is set and the RPC node looks behind, unless `--force`.
+    ///
+    /// A node that does not support `eth_syncing` only produces a warning; the
+    /// latest block age is still checked.
+    fn check_node_synced(&self, network_config: &NetworkConfig) -> Result<()> {
+        if !network_config.require_synced {
+            return Ok(());
+        }
+
+        let client = self.rpc_client(network_config)?;
+        let syncing = match client.is_syncing() {
+            Ok(syncing) => syncing,
+            Err(e) => {
+                self.warn(format!("Could not check eth_syncing: {}", e))?;
+                false
+            }
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("System clock is before the unix epoch")?
+            .as_secs();
+        let problem = match client.latest_block_timestamp() {
+            Ok(timestamp) => sync_problem(
+                syncing,
+                now.saturating_sub(timestamp),
+                network_config
+                    .max_block_lag_secs
+                    .unwrap_or(DEFAULT_MAX_BLOCK_LAG_SECS),
+            ),
+            Err(e) => Some(format!("could not read the latest block: {}", e)),
+        };
+
+        let Some(problem) = problem else {
+            println!("{}", "RPC node is synced".green());
+            return Ok(());
+        };
+
+        if self.options.force {
+            return self.warn(format!(
+                "RPC node {}, continuing because of --force",
+                problem
+            ));
+        }
+
+        anyhow::bail!(
+            "RPC node for '{}' {}. Use --force to deploy anyway",
+            self.config.project.network,
+            problem
+        )
+    }
+
+    /// In safe mode, fail unless the RPC reports the network's configured chain id
+    fn check_chain_id(&self, network_config: &NetworkConfig) -> Result<()> {
+        if !self.options.safe.requires(SafeCheck::ChainId) {
+            return Ok(());
+        }
+
+        let reported = self
+            .rpc_client(network_config)?
+            .chain_id()
+            .context("Safe mode: could not verify the RPC chain id")?;
+        if reported != network_config.chain_id {
+            anyhow::bail!(
+                "Safe mode: RPC for '{}' is on chain {}, but chain_id = {} is configured",
+                self.config.project.network,
+                reported,
+                network_config.chain_id
+            );
+        }
+
+        println!("{}", format!("RPC chain id is {}", reported).green());
+        Ok(())
+    }
+
+    /// Query the deployer balance when the balance preflight is enabled.
+    ///
+    /// Returns `None` when the check is disabled or cannot be performed, so a
+    /// missing sender or unreachable RPC never blocks the deployment.
+    fn fetch_deployer_balance(&self, network_config: &NetworkConfig) -> Result<Option<u128>> {
+        if !self.options.check_balance
+            && !self.options.safe.requires(SafeCheck::Balance)
+            && network_config.min_balance.is_none()
+        {
+            return Ok(None);
+        }
+
+        let Some(sender) = self.env.get("BROADCAST_ACCOUNT") else {
+            self.warn("Skipping balance check, BROADCAST_ACCOUNT is not set")?;
+            return Ok(None);
+        };
+
+        match self.rpc_client(network_config)?.get_balance(sender) {
+            Ok(balance) => Ok(Some(balance)),
+            Err(e) => {
+                self.warn(format!("Skipping balance check: {}", e))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Warn when the deployer balance is below the network's `min_balance`
+    fn warn_on_low_balance(
+        &self,
+        network_config: &NetworkConfig,
+        balance: Option<u128>,
+    ) -> Result<()> {
+        let (Some(balance), Some(min_balance)) = (balance, network_config.min_balance) else {
+            return Ok(());
+        };
+
+        if is_below_min_balance(balance, min_balance) {
+            self.warn(format!(
+                "Deployer balance {} ETH is below the configured minimum of {} ETH",
+                rpc::format_ether(balance),
+                min_balance
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// In safe mode, fail unless the balance was read and is non-zero and above `min_balance`
+    fn check_safe_balance(
+        &self,
+        network_config: &NetworkConfig,
+        balance: Option<u128>,
+    ) -> Result<()> {
+        if !self.options.safe.requires(SafeCheck::Balance) {
+            return Ok(());
+        }
+
+        let Some(balance) = balance else {
+            anyhow::bail!("Safe mode: the deployer balance could not be checked");
+        };
+        if balance == 0 {
+            anyhow::bail!("Safe mode: the deployer has no funds");
+        }
+        if let Some(min_balance) = network_config.min_balance
+            && is_below_min_balance(balance, min_balance)
+        {
+            anyhow::bail!(
+                "Safe mode: deployer balance {} ETH is below the configured minimum of {} ETH",
+                rpc::format_ether(balance),
+                min_balance
+            );
+        }
+
+        Ok(())
+    }
+
+    fn display_deployment_info(&self, network_config: &NetworkConfig, balance: Option<u128>) {
+        println!("\n{}", "════════════════════════════════════ DEPLOYMENT CONFIG ════════════════════════════════════".green());
+        println!("{}: {}", "PROJECT".blue(), self.config.project.name);
+        println!("{}: {}", "SCRIPT".blue(), self.config.get_script_name());
+        println!("{}: {}", "NETWORK".blue(), self.config.project.network);
+        println!("{}: {}", "CHAIN_ID".blue(), network_config.chain_id);
+        println!(
+            "{}: {}",
+            "RPC_URL".blue(),
+            redact::redact_rpc_url(&network_config.rpc_url)
+        );
+        println!("{}: {}", "VERIFY".blue(), network_config.verify);
+        println!(
+            "{}: {}",
+            "TX_TYPE".blue(),
+            self.tx_type_summary(network_config)
+        );
+        println!(
+            "{}: {}",
+            "EXTRA_ARGS".blue(),
+            self.extra_args_summary(network_config)
+        );
+        let project = &self.config.project;
+        if project.optimize.is_some() || project.optimizer_runs.is_some() {
+            let runs = project
+                .optimizer_runs
+                .as_ref()
+                .map(|runs| format!(" ({} runs)", runs.trim()))
+                .unwrap_or_default();
+            let state = match project.optimize {
+                Some(false) => "off",
+                Some(true) => "on",
+                None => "foundry.toml",
+            };
+            println!("{}: {}{}", "OPTIMIZER".blue(), state, runs);
+        }
+        if !project.skip.is_empty() {
+            println!("{}: {}", "SKIP".blue(), project.skip.join(", "));
+        }
+        if let Some(balance) = balance {
+            println!("{}: {} ETH", "BALANCE".blue(), rpc::format_ether(balance));
+        }
+        if let Some(bytes) = self.clone_estimate.get() {
+            println!("{}: ~{} (estimate)", "CLONE_SIZE".blue(), format_mb(bytes));
+        }
+        if self.options.safe.is_enabled() {
+            println!("{}: {}", "SAFE_MODE".blue(), "on".green());
+        }
+
+        for (key, value) in self.env.get_vars() {
+            if key.contains("API_KEY") {
+                println!("{}: {}", key.blue(), redact::MASK.yellow());
+            } else if key.contains("RPC_URL") {
+                println!("{}: {}", key.blue(), redact::redact_rpc_url(value));
+            }
+        }
+
+        println!("{}", "═══════════════════════════════════════════════════════════════════════════════════════".green());
+        println!();
+    }
+
+    /// The forge invocation as recorded in a plan: secret values become `${VAR}`
+    /// and keys in the RPC URL are redacted
+    fn plan_command(&self, forge_cmd: &Command, network_config: &NetworkConfig) -> Vec<String> {
+        let mut secrets: Vec<(&String, &String)> = self
+            .env
+            .get_vars()
+            .iter()
+            .filter(|(key, value)| {
+                !value.is_empty()
+                    && (redact::is_sensitive_key(key) || **key == self.config.auth.password_var)
+            })
+            .collect();
+        // Longest first, so a secret containing another is replaced whole
+        secrets.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+
+        std::iter::once(forge_cmd.get_program())
+            .chain(forge_cmd.get_args())
+            .map(|arg| {
+                let arg = arg.to_string_lossy().to_string();
+                if let Some((key, _)) = secrets.iter().find(|(_, value)| **value == arg) {
+                    return format!("${{{}}}", key);
+                }
+                if !arg.contains("://") && arg != network_config.rpc_url {
+                    return arg;
+                }
+
+                // URLs may embed a secret, or a literal API key
+                let arg = secrets.iter().fold(arg, |arg, (key, value)| {
+                    arg.replace(value.as_str(), &format!("${{{}}}", key))
+                });
+                redact::redact_rpc_url(&arg)
+            })
+            .collect()
+    }
+
+    /// `forge_cmd` as `--emit-command` prints it, with the same redaction as a plan
+    fn export_command(
+        &self,
+        forge_cmd: &Command,
+        network_config: &NetworkConfig,
+    ) -> ExportedCommand {
+        let mut command = self.plan_command(forge_cmd, network_config).into_iter();
+        ExportedCommand {
+            program: command.next().unwrap_or_default(),
+            args: command.collect(),
+            working_dir: forge_cmd
+                .get_current_dir()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            env: forge_env_names(forge_cmd),
+        }
+    }
+
+    fn display_command_info(&self, forge_cmd: &Command) {
+        println!("\n{}", self.echoed_command(forge_cmd));
+    }
+
+    /// The forge command as echoed before running it: sensitive flag values,
+    /// URL credentials and the keystore account and password are masked
+    fn echoed_command(&self, forge_cmd: &Command) -> String {
+        let args: Vec<String> = std::iter::once(forge_cmd.get_program())
+            .chain(forge_cmd.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let keystore_values: Vec<&String> = [
+            &self.config.auth.account_var,
+            &self.config.auth.password_var,
+        ]
+        .into_iter()
+        .filter_map(|var| self.env.get(var))
+        .filter(|value| !value.is_empty())
+        .collect();
+
+        redact::redact_args(&args)
+            .into_iter()
+            .map(|arg| {
+                if keystore_values.contains(&&arg) {
+                    redact::MASK.to_string()
+                } else {
+                    arg
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn confirm_execution(&self, network_config: &NetworkConfig) -> Result<Confirmation> {
+        write_confirm_message(network_config, &mut io::stdout())?;
+
+        if let Some(approval_command) = &network_config.approval_command {
+            return self.run_approval_command(approval_command, network_config);
+        }
+
+        let typed = self.requires_typed_confirm();
+        if !typed && !should_prompt(network_config.auto_confirm, self.assume_yes()) {
+            println!("Skipping confirmation (auto-confirm enabled)");
+            return Ok(Confirmation::Approved);
+        }
+
+        let network = &self.config.project.network;
+        if typed {
+            print!("Type the network name ({}) to continue: ", network);
+        } else {
+            print!("Continue with script execution? (y/n): ");
+        }
+        io::stdout().flush()?;
+
+        let answer = match self.options.confirm_timeout {
+            Some(secs) => read_line_with_timeout(read_stdin_line, Duration::from_secs(secs))?,
+            None => Some(read_stdin_line()?),
+        };
+
+        let Some(input) = answer else {
+            println!(
+                "\nNo answer within {}s",
+                self.options.confirm_timeout.unwrap_or_default()
+            );
+            return Ok(Confirmation::TimedOut);
+        };
+        if typed {
+            return Ok(typed_confirmation(&input, network));
+        }
+        let input = input.trim().to_lowercase();
+
+        if input == "y" || input == "yes" {
+            Ok(Confirmation::Approved)
+        } else {
+            Ok(Confirmation::Declined)
+        }
+    }
+
+    /// `project.dangerous_flags` in `forge_cmd` with why each is risky, sorted;
+    /// none on local networks
+    fn dangerous_flags_in<'a>(
+        &'a self,
+        forge_cmd: &Command,
+        network_config: &NetworkConfig,
+    ) -> Vec<(&'a str, &'a str)> {
+        if watch::is_local_network(network_config) {
+            return Vec::new();
+        }
+        let args: Vec<String> = forge_cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        dangerous_flags(&args, &self.config.project.dangerous_flags)
+    }
+
+    /// Ask again, whatever `-y` says, before running forge with a dangerous flag,
+    /// unless `--force-dangerous`; `read` reads the answer
+    fn confirm_dangerous_flags(
+        &self,
+        forge_cmd: &Command,
+        network_config: &NetworkConfig,
+        read: fn() -> io::Result<String>,
+    ) -> Result<Confirmation> {
+        let found = self.dangerous_flags_in(forge_cmd, network_config);
+        if found.is_empty() || self.options.force_dangerous {
+            return Ok(Confirmation::Approved);
+        }
+
+        println!(
+            "\n{}",
+            format!(
+                "The forge command for '{}' uses risky flags:",
+                self.config.project.network
+            )
+            .red()
+        );
+        for (flag, reason) in &found {
+            println!("  {} {}", flag.red(), reason);
+        }
+        print!("Run it anyway? Type 'yes' (or pass --force-dangerous): ");
+        io::stdout().flush()?;
+
+        let answer = match self.options.confirm_timeout {
+            Some(secs) => read_line_with_timeout(read, Duration::from_secs(secs))?,
+            None => Some(read()?),
+        };
+        Ok(match answer {
+            None => Confirmation::TimedOut,
+            Some(input) if input.trim().eq_ignore_ascii_case("yes") => Confirmation::Approved,
+            Some(_) => Confirmation::Declined,
+        })
+    }
+
+    /// Whether the network name must be typed to confirm: in safe mode, unless
+    /// `--assume-yes-for` names the selected network
+    fn requires_typed_confirm(&self) -> bool {
+        self.options.safe.requires(SafeCheck::TypedConfirm)
+            && !self
+                .options
+                .assume_yes_for
+                .contains(&self.config.project.network)
+    }
+
+    /// Whether `-y` or `--assume-yes-for` covers the selected network
+    fn assume_yes(&self) -> bool {
+        self.options.skip_confirmation
+            || self
+                .options
+                .assume_yes_for
+                .contains(&self.config.project.network)
+    }
+
+    /// Ask an external approval command, run through `sh -c`, whether to proceed.
+    ///
+    /// The deploy metadata is passed as `DEPLOY_*` environment variables and
+    /// exit code 0 approves. This replaces the prompt and ignores `-y`.
+    fn run_approval_command(
+        &self,
+        approval_command: &str,
+        network_config: &NetworkConfig,
+    ) -> Result<Confirmation> {
+        println!("{}: {}", "Approval command".blue(), approval_command);
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(approval_command)
+            .env("DEPLOY_PROJECT", &self.config.project.name)
+            .env("DEPLOY_NETWORK", &self.config.project.network)
+            .env("DEPLOY_CHAIN_ID", network_config.chain_id.to_string())
+            .env("DEPLOY_SCRIPT", self.config.get_script_name());
+        if let Some(sender) = self.env.get("BROADCAST_ACCOUNT") {
+            cmd.env("DEPLOY_SENDER", sender);
+        }
+
+        let status = cmd.status().context("Failed to run approval command")?;
+
+        if status.success() {
+            println!("{}", "Deployment approved".green());
+            Ok(Confirmation::Approved)
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "Deployment rejected by approval command ({})",
+                    status
+                        .code()
+                        .map(|code| format!("exit code {}", code))
+                        .unwrap_or_else(|| "terminated by signal".to_string())
+                )
+                .red()
+            );
+            Ok(Confirmation::Declined)
+        }
+    }
+
+    /// Run forge with its output shown as it comes; with `capture_log` the
+    /// output is also returned, stdout and stderr interleaved by line
+    fn execute_forge_command(&self, mut forge_cmd: Command, capture_log: bool) -> Result<ForgeRun> {
+        println!("{}", "Executing forge script...".blue());
+
+        // Use spawn + wait instead of output() to see real-time logs
+        let output = if capture_log {
+            Stdio::piped
+        } else {
+            Stdio::inherit
+        };
+        let mut child = forge_cmd
+            .stdout(output())
+            .stderr(output())
+            .spawn()
+            .context("Failed to start forge script")?;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut copies = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            copies.push(tee_lines(stdout, io::stdout(), log.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            copies.push(tee_lines(stderr, io::stderr(), log.clone()));
+        }
+
+        let status = child
+            .wait()
+            .context("Failed to wait for forge script completion")?;
+        for copy in copies {
+            let _ = copy.join();
+        }
+
+        if status.success() {
+            println!("\n{}", "Script executed successfully!".green());
+        } else {
+            println!("\n{}", "Script execution failed!".red());
+            if let Some(code) = status.code() {
+                println!("Exit code: {}", code);
+            }
+            return Err(ForgeFailed {
+                code: status.code(),
+            }
+            .into());
+        }
+
+        let log = std::mem::take(&mut *log.lock().unwrap_or_else(|e| e.into_inner()));
+        Ok(ForgeRun {
+            exit_code: status.code(),
+            log: capture_log.then_some(log),
+        })
+    }
+
+    /// Variables the signer (and the plan's secrets) need
+    fn required_vars(&self) -> Vec<&str> {
+        let auth = &self.config.auth;
+        let mut required_vars = match auth.method {
+            AuthMethod::Keystore => vec![
+                auth.account_var.as_str(),
+                auth.password_var.as_str(),
+                "BROADCAST_ACCOUNT",
+            ],
+            AuthMethod::AwsKms => vec!["AWS_KMS_KEY_ID", "BROADCAST_ACCOUNT"],
+            AuthMethod::Unlocked => vec!["BROADCAST_ACCOUNT"],
+        };
+
+        if let Some(plan) = &self.plan {
+            required_vars.extend(plan.secrets.iter().map(String::as_str));
+        }
+
+        required_vars
+    }
+
+    fn validate_environment(&self) -> Result<()> {
+        self.env.validate_required(&self.required_vars())
+    }
+
+    /// `path: VAR` for the undefined `${VAR}` references of the config, ignoring
+    /// other networks, which may rely on variables this run doesn't need
+    fn undefined_network_references(&self) -> Result<Vec<String>> {
+        let mut selected = self.raw_config.clone();
+        let network = &self.config.project.network;
+        selected.networks.retain(|name, _| name == network);
+
+        undefined_references(&selected, &self.env)
+    }
+
+    /// Load the environment and list the required or referenced variables it
+    /// lacks, for `setup-env`
+    pub fn missing_env_vars(&mut self) -> Result<Vec<String>> {
+        self.load_environment()?;
+
+        let mut missing: Vec<String> = self
+            .required_vars()
+            .into_iter()
+            .filter(|var| self.env.is_missing(var))
+            .map(String::from)
+            .collect();
+        for reference in self.undefined_network_references()? {
+            let var = reference.rsplit(": ").next().unwrap_or(&reference);
+            if !missing.iter().any(|known| known == var) {
+                missing.push(var.to_string());
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// The env file `setup-env` writes: the first of `env.load_files`, else `.env`
+    pub fn default_env_file(&self) -> PathBuf {
+        let file = self
+            .config
+            .env
+            .load_files
+            .as_ref()
+            .and_then(|files| files.first())
+            .map_or(".env", String::as_str);
+        self.config.config_dir.join(file)
+    }
+}
+
+/// What to check out when cloning
+#[derive(Debug, Default)]
+struct CloneOptions<'a> {
+    /// Branch to clone and track instead of the remote's default
+    branch: Option<&'a str>,
+    /// Tag or commit to check out (detached) after cloning
+    git_ref: Option<&'a str>,
+    /// Name for the remote, `origin` when unset
+    remote: Option<&'a str>,
+    /// Abort the clone once this much time has passed
+    timeout: Option<Duration>,
+    /// Abort the clone once more than this many bytes have been received
+    max_bytes: Option<usize>,
+    /// HTTP(S) proxy the clone connects through
+    proxy: Option<&'a str>,
+}
+
+/// Network settings `--safe` forces on every network
+fn apply_safe_networks(config: &mut DeploymentConfig, safe: &SafeMode) {
+    if safe.requires(SafeCheck::Synced) {
+        for network in config.networks.values_mut() {
+            network.require_synced = true;
+        }
+    }
+}
+
+/// `project.senders` needs keystore auth and distinct, non-empty account names
+fn validate_senders(config: &DeploymentConfig) -> Result<()> {
+    let senders = &config.project.senders;
+    if senders.is_empty() {
+        return Ok(());
+    }
+    if config.auth.method != AuthMethod::Keystore {
+        anyhow::bail!("project.senders requires auth.method = \"keystore\"");
+    }
+
+    let mut seen = HashSet::new();
+    for sender in senders {
+        if sender.account.trim().is_empty() {
+            anyhow::bail!("project.senders contains an entry without an account");
+        }
+        if !seen.insert(sender.account.as_str()) {
+            anyhow::bail!("project.senders lists account {} twice", sender.account);
+        }
+    }
+    Ok(())
+}
+
+/// `vars` starting with `prefix`, renamed without it; the bare prefix is skipped
+fn unprefixed(
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<(String, String)> {
+    vars.into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(prefix)
+                .filter(|name| !name.is_empty())
+                .map(|name| (name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Names of the variables explicitly set on `command`, sorted
+fn forge_env_names(command: &Command) -> Vec<String> {
+    let mut names: Vec<String> = command
+        .get_envs()
+        .filter(|(_, value)| value.is_some())
+        .map(|(key, _)| key.to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Print the variables forge receives on top of the inherited process environment (`--dump-forge-env`)
+fn dump_forge_env(command: &Command) {
+    let names = forge_env_names(command);
+    println!(
+        "{}",
+        format!(
+            "Environment passed to forge ({} variables, plus the inherited process environment):",
+            names.len()
+        )
+        .blue()
+    );
+    for name in names {
+        println!("  {}={}", name, redact::MASK);
+    }
+}
+
+/// How a successful forge run ended
+#[derive(Debug)]
+struct ForgeRun {
+    /// Exit code of the forge process, `None` if the platform reports none
+    exit_code: Option<i32>,
+    /// The output, when it was captured
+    log: Option<Vec<u8>>,
+}
+
+/// Copy `source` line by line to `out` and to `log`, on a thread of its own
+fn tee_lines(
+    source: impl io::Read + Send + 'static,
+    mut out: impl Write + Send + 'static,
+    log: Arc<Mutex<Vec<u8>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = io::BufReader::new(source);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            let _ = out.write_all(&line).and_then(|_| out.flush());
+            if let Ok(mut log) = log.lock() {
+                log.extend_from_slice(&line);
+            }
+            line.clear();
+        }
+    })
+}
+
+/// Point `config` at the network, script and commit of the project's latest run
+/// recorded under `output_dir` (`--from-last`)
+fn apply_last_run(config: &mut DeploymentConfig, output_dir: Option<&str>) -> Result<()> {
+    let dir = output_dir.context("--from-last reads the run history from --output-dir")?;
+    let last = inventory::last_run(Path::new(dir), &config.project.name)?.context(format!(
+        "No previous run of '{}' recorded in {}; nothing to re-deploy",
+        config.project.name, dir
+    ))?;
+
+    println!(
+        "{}",
+        format!(
+            "Re-deploying the last run: {} on {} (commit {})",
+            last.script,
+            last.network,
+            last.git_sha.as_deref().unwrap_or("unknown")
+        )
+        .blue()
+    );
+    config.project.network = last.network;
+    config.project.script = last.script;
+    if let Some(sha) = last.git_sha {
+        config.project.git_branch = None;
+        config.project.git_ref = Some(sha);
+    }
+    Ok(())
+}
+
+/// Commit of `git_ref` (a branch or tag), `branch` or the default branch on the
+/// remote, listed without fetching. `None` when the remote can't be reached.
+fn remote_commit(
+    repo_url: &str,
+    branch: Option<&str>,
+    git_ref: Option<&str>,
+    proxy: Option<&str>,
+) -> Option<String> {
+    let mut remote = git2::Remote::create_detached(repo_url).ok()?;
+    let connection = remote
+        .connect_auth(
+            git2::Direction::Fetch,
+            None,
+            Some(proxy::git_options(proxy)),
+        )
+        .ok()?;
+    let heads = connection.list().ok()?;
+
+    let candidates = match (git_ref, branch) {
+        // An annotated tag is listed twice, the peeled `^{}` entry names the commit
+        (Some(git_ref), _) => vec![
+            format!("refs/tags/{}^{{}}", git_ref),
+            format!("refs/tags/{}", git_ref),
+            format!("refs/heads/{}", git_ref),
+        ],
+        (None, Some(branch)) => vec![format!("refs/heads/{}", branch)],
+        (None, None) => vec!["HEAD".to_string()],
+    };
+    candidates.iter().find_map(|name| {
+        heads
+            .iter()
+            .find(|head| head.name() == name)
+            .map(|head| head.oid().to_string())
+    })
+}
+
+/// Clone `repo_url` into `target_dir` and check out the requested branch or ref.
+///
+/// With a timeout the clone runs on a worker thread that is given up on once
+/// the timeout passes, so a connection that stalls without reporting progress
+/// is caught too; the transfer progress callback then stops the transfer. The
+/// size limit is enforced from the same callback. A clone aborted by either
+/// removes the partial directory.
+fn clone_repository(
+    repo_url: &str,
+    target_dir: &Path,
+    options: &CloneOptions,
+) -> Result<Repository> {
+    let Some(timeout) = options.timeout else {
+        return clone_and_checkout(repo_url, target_dir, options, &AtomicBool::new(false));
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    {
+        let (repo_url, target_dir) = (repo_url.to_string(), target_dir.to_path_buf());
+        let branch = options.branch.map(String::from);
+        let git_ref = options.git_ref.map(String::from);
+        let remote = options.remote.map(String::from);
+        let proxy = options.proxy.map(String::from);
+        let max_bytes = options.max_bytes;
+        let cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let options = CloneOptions {
+                branch: branch.as_deref(),
+                git_ref: git_ref.as_deref(),
+                remote: remote.as_deref(),
+                timeout: None,
+                max_bytes,
+                proxy: proxy.as_deref(),
+            };
+            let result = clone_and_checkout(&repo_url, &target_dir, &options, &cancelled);
+            if cancelled.load(Ordering::SeqCst) && target_dir.exists() {
+                let _ = fs::remove_dir_all(&target_dir);
+            }
+            let _ = sender.send(result);
+        });
+    }
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => return result,
+        Err(mpsc::RecvTimeoutError::Disconnected) => anyhow::bail!("Clone thread panicked"),
+        Err(mpsc::RecvTimeoutError::Timeout) => {}
+    }
+
+    // Stop the transfer and give the worker a moment to let go of the directory;
+    // a worker stuck on the connection removes it itself once it gets unstuck
+    cancelled.store(true, Ordering::SeqCst);
+    let _ = receiver.recv_timeout(CLONE_CANCEL_GRACE);
+    if target_dir.exists() {
+        let _ = fs::remove_dir_all(target_dir);
+    }
+    anyhow::bail!("Clone timed out after {}s", timeout.as_secs())
+}
+
+/// How long a timed out clone may take to stop before its directory is removed
+const CLONE_CANCEL_GRACE: Duration = Duration::from_secs(1);
+
+/// The clone itself, stopped from the transfer progress callback once
+/// `cancelled` is set or the size limit is crossed
+fn clone_and_checkout(
+    repo_url: &str,
+    target_dir: &Path,
+    options: &CloneOptions,
+    cancelled: &AtomicBool,
+) -> Result<Repository> {
+    let received = Cell::new(0);
+    let too_large = || {
+        options
+            .max_bytes
+            .is_some_and(|max_bytes| received.get() > max_bytes)
+    };
+    let aborted = Cell::new(false);
+
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.transfer_progress(|progress| {
+        received.set(progress.received_bytes());
+        let stop = cancelled.load(Ordering::SeqCst) || too_large();
+        aborted.set(stop);
+        !stop
+    });
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    fetch_options.proxy_options(proxy::git_options(options.proxy));
+
+    let mut builder = git2::build::RepoBuilder::new();
+    builder.fetch_options(fetch_options);
+    if let Some(branch) = options.branch {
+        builder.branch(branch);
+    }
+    if let Some(remote) = options.remote {
+        let remote = remote.to_string();
+        builder.remote_create(move |repo, _name, url| repo.remote(&remote, url));
+    }
+
+    let remove_partial_clone = || {
+        if target_dir.exists() {
+            let _ = fs::remove_dir_all(target_dir);
+        }
+    };
+
+    let repo = match builder.clone(repo_url, target_dir) {
+        Ok(repo) => repo,
+        Err(_) if aborted.get() && too_large() => {
+            remove_partial_clone();
+            anyhow::bail!(
+                "Clone aborted after receiving {} bytes, over the limit of {} bytes (project.max_clone_mb)",
+                received.get(),
+                options.max_bytes.unwrap_or_default()
+            )
+        }
+        Err(_) if aborted.get() => {
+            remove_partial_clone();
+            anyhow::bail!("Clone cancelled")
+        }
+        Err(e) => {
+            let context = match options.branch {
+                Some(branch) => format!("Failed to clone branch '{}'", branch),
+                None => "Failed to clone repository".to_string(),
+            };
+            return Err(anyhow::Error::new(e).context(context));
+        }
+    };
+
+    if let Some(git_ref) = options.git_ref {
+        // Only the default branch exists locally, other branches are remote-tracking
+        let remote_branch = format!("{}/{}", options.remote.unwrap_or("origin"), git_ref);
+        let object = repo
+            .revparse_single(git_ref)
+            .or_else(|_| repo.revparse_single(&remote_branch))
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Ref '{}' not found in repository (not a branch, tag or commit)",
+                    git_ref
+                )
+            })?;
+        repo.checkout_tree(&object, None)
+            .context(format!("Failed to check out '{}'", git_ref))?;
+        repo.set_head_detached(object.peel_to_commit()?.id())?;
+    }
+
+    Ok(repo)
+}
+
+/// Flags `forge_script_command` controls itself; repeating them in extra
+/// arguments makes forge fail with confusing errors or changes the broadcast
+const MANAGED_FLAGS: &[&str] = &[
+    "--broadcast",
+    "--resume",
+    "--rpc-url",
+    "--chain-id",
+    "--chain",
+    "--gas-limit",
+    "--with-gas-price",
+    "--priority-gas-price",
+];
+
+/// Managed flags only set when the typed gas settings ask for them
+const GAS_FLAGS: &[&str] = &["--gas-limit", "--with-gas-price", "--priority-gas-price"];
+
+/// The managed flags found in `args`, as `--flag` or `--flag=value`, in order and
+/// deduplicated; a gas flag only counts when it is in `set_gas_flags`
+fn duplicate_flags(args: &[String], set_gas_flags: &[&str]) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    for arg in args {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        if let Some(managed) = MANAGED_FLAGS.iter().find(|managed| **managed == flag)
+            && (!GAS_FLAGS.contains(managed) || set_gas_flags.contains(managed))
+            && !found.contains(managed)
+        {
+            found.push(*managed);
+        }
+    }
+    found
+}
+
+/// The `dangerous` flags found in `args`, as `--flag` or `--flag=value`, with their reasons
+fn dangerous_flags<'a>(
+    args: &[String],
+    dangerous: &'a HashMap<String, String>,
+) -> Vec<(&'a str, &'a str)> {
+    let mut found: Vec<(&str, &str)> = dangerous
+        .iter()
+        .filter(|(flag, _)| {
+            args.iter()
+                .any(|arg| arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag) == *flag)
+        })
+        .map(|(flag, reason)| (flag.as_str(), reason.as_str()))
+        .collect();
+    found.sort();
+    found
+}
+
+/// Print the network's `confirm_message` as a banner, if it has one
+fn write_confirm_message(network_config: &NetworkConfig, out: &mut impl Write) -> io::Result<()> {
+    if let Some(message) = &network_config.confirm_message {
+        let rule = "=".repeat(message.chars().count().clamp(20, 80));
+        writeln!(
+            out,
+            "\n{}\n{}\n{}",
+            rule.red(),
+            message.red().bold(),
+            rule.red()
+        )?;
+    }
+    Ok(())
+}
+
+/// Address of a foundry keystore account, via `<cast> wallet address`.
+///
+/// The password goes through an owner-only `--password-file`, so it never
+/// shows up in the process list, and never appears in the returned error.
+fn keystore_address(cast: &str, account: &str, password: &str) -> Result<String> {
+    let password_file = PasswordFile::new(password)?;
+    let output = Command::new(cast)
+        .args(["wallet", "address", "--account", account, "--password-file"])
+        .arg(&password_file.0)
+        .stdin(Stdio::null())
+        .output()
+        .context(format!("Failed to run {} (is foundry installed?)", cast))?;
+    drop(password_file);
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).replace(password, redact::MASK);
+        anyhow::bail!(
+            "Failed to derive the sender from keystore account '{}': {}",
+            account,
+            stderr.trim()
+        );
+    }
+
+    let address = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !ens::is_address(&address) {
+        anyhow::bail!(
+            "cast returned '{}' for keystore account '{}', expected an address",
+            address,
+            account
+        );
+    }
+
+    Ok(address)
+}
+
+/// Temporary owner-only file holding a keystore password, removed when dropped
+struct PasswordFile(PathBuf);
+
+impl PasswordFile {
+    fn new(password: &str) -> Result<Self> {
+        static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "contract-deployer-{}-{}.password",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        environment::write_private(&path, password)?;
+        Ok(PasswordFile(path))
+    }
+}
+
+impl Drop for PasswordFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Names (without `.s.sol`) of the forge scripts in `<project_dir>/script/`, sorted
+fn list_scripts(project_dir: &Path) -> Result<Vec<String>> {
+    let script_dir = project_dir.join("script");
+    let entries =
+        fs::read_dir(&script_dir).context(format!("Failed to read {}", script_dir.display()))?;
+
+    let mut scripts: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            file_name.strip_suffix(".s.sol").map(str::to_string)
+        })
+        .collect();
+    scripts.sort();
+
+    Ok(scripts)
+}
+
+/// The only script in `script/`, used when `project.script` is not configured
+fn discover_script(project_dir: &Path) -> Result<String> {
+    let mut scripts = list_scripts(project_dir)?;
+
+    match scripts.len() {
+        1 => Ok(scripts.remove(0)),
+        0 => anyhow::bail!(
+            "No project.script configured and no *.s.sol found in {}",
+            project_dir.join("script").display()
+        ),
+        _ => anyhow::bail!(
+            "No project.script configured and script/ has several scripts; pick one with --script or project.script: {}",
+            scripts.join(", ")
+        ),
+    }
+}
+
+/// Pick a setup command from the lockfiles and config present in `project_dir`.
+///
+/// Returns the file that decided it along with the command, or `None` if
+/// nothing recognizable is found.
+fn detect_setup_command(project_dir: &Path) -> Option<(&'static str, &'static str)> {
+    const CANDIDATES: &[(&[&str], &str)] = &[
+        (&["bun.lockb"], "bun install"),
+        (&["bun.lock"], "bun install"),
+        (&["package-lock.json"], "npm ci"),
+        (&["yarn.lock"], "yarn"),
+        (&["foundry.toml", ".gitmodules"], "forge install"),
+    ];
+
+    CANDIDATES
+        .iter()
+        .find(|(files, _)| files.iter().all(|f| project_dir.join(f).exists()))
+        .map(|(files, command)| (files[0], *command))
+}
+
+/// Resolve `project.cwd` to an absolute path and make sure it holds a foundry project
+fn foundry_project_dir(cwd: &Path) -> Result<PathBuf> {
+    let dir =
+        fs::canonicalize(cwd).context(format!("project.cwd does not exist: {}", cwd.display()))?;
+
+    if !dir.join("foundry.toml").is_file() {
+        anyhow::bail!(
+            "project.cwd {} is not a foundry project (no foundry.toml)",
+            dir.display()
+        );
+    }
+
+    Ok(dir)
+}
+
+/// Bytes as megabytes with one decimal, e.g. `12.5 MB`
+fn format_mb(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// A typed confirmation approves only when the exact network name was entered
+fn typed_confirmation(input: &str, network: &str) -> Confirmation {
+    if input.trim() == network {
+        Confirmation::Approved
+    } else {
+        Confirmation::Declined
+    }
+}
+
+/// `path: VAR` for every `${VAR}` in the config that the environment doesn't define
+fn undefined_references(config: &DeploymentConfig, env: &Environment) -> Result<Vec<String>> {
+    fn visit(path: &str, value: &serde_json::Value, env: &Environment, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(text) => out.extend(
+                env.undefined_variables(text)
+                    .into_iter()
+                    .map(|var| format!("{}: {}", path, var)),
+            ),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    visit(path, item, env, out);
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (key, child) in map {
+                    let child_path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    visit(&child_path, child, env, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let value = serde_json::to_value(config).context("Failed to serialize config")?;
+    let mut undefined = Vec::new();
+    visit("", &value, env, &mut undefined);
+    Ok(undefined)
+}
+
+/// Whether to ask before running the script.
+///
+/// A network's `auto_confirm` wins over the CLI: `true` never prompts and
+/// `false` always prompts, even with `-y`. Unset follows `-y`.
+fn should_prompt(auto_confirm: Option<bool>, skip_confirmation: bool) -> bool {
+    match auto_confirm {
+        Some(auto_confirm) => !auto_confirm,
+        None => !skip_confirmation,
+    }
+}
+
+fn read_stdin_line() -> io::Result<String> {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input)
+}
+
+/// Run `read` on a helper thread and wait at most `timeout` for its line.
+///
+/// Returns `None` if nothing arrived in time; the helper thread is left
+/// blocked on its read and goes away with the process.
+fn read_line_with_timeout<F>(read: F, timeout: Duration) -> Result<Option<String>>
+where
+    F: FnOnce() -> io::Result<String> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(read());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(line) => Ok(Some(line?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Pick the network when neither the command line nor the config names one.
+///
+/// A single configured network is used directly. With several, a numbered
+/// list is shown when `interactive`, otherwise this is an error.
+fn select_network(
+    config: &DeploymentConfig,
+    interactive: bool,
+    input: &mut dyn BufRead,
+) -> Result<String> {
+    let names = config.network_names();
+
+    match names.len() {
+        0 => anyhow::bail!("No networks defined in configuration"),
+        1 => return Ok(names[0].clone()),
+        _ => {}
+    }
+
+    if !interactive {
+        anyhow::bail!(
+            "No network specified, pass --network or set project.network (available: {})",
+            names.join(", ")
+        );
+    }
+
+    println!("{}", "Select a network:".blue());
+    for (i, name) in names.iter().enumerate() {
+        println!(
+            "  {}) {} (chain_id {})",
+            i + 1,
+            name,
+            config.networks[name].chain_id
+        );
+    }
+    print!("Network [1-{}]: ", names.len());
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|choice| names.get(choice.checked_sub(1)?))
+        .cloned()
+        .context(format!("Invalid network selection: {}", answer.trim()))
+}
+
+/// Commit checked out in the repository containing `project_dir`, if any
+pub fn head_sha(project_dir: &Path) -> Option<String> {
+    let repo = Repository::discover(project_dir).ok()?;
+    Some(repo.head().ok()?.peel_to_commit().ok()?.id().to_string())
+}
+
+/// Describe why a node looks out of sync, or `None` when it looks healthy
+fn sync_problem(syncing: bool, block_age_secs: u64, max_lag_secs: u64) -> Option<String> {
+    if syncing {
+        Some("reports it is still syncing".to_string())
+    } else if block_age_secs > max_lag_secs {
+        Some(format!(
+            "is {}s behind (latest block is older than {}s)",
+            block_age_secs, max_lag_secs
+        ))
+    } else {
+        None
+    }
+}
+
+/// Check whether a wei balance is below a minimum expressed in ETH, compared in wei.
+/// The minimum was checked to be a valid ETH amount when the config loaded.
+fn is_below_min_balance(balance: u128, min_balance: f64) -> bool {
+    rpc::parse_ether(&min_balance.to_string()).is_ok_and(|min_wei| balance < min_wei)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const CONFIG_CONTENT: &str = r#"
+[project]
+name = "test-contract"
+script = "Deploy"
+network = "sepolia"
+setup_command = "echo 'test'"
+
+[env]
+load_files = [".env"]
+
+[env.vars]
+KEYSTORE_ACCOUNT = "deployer"
+KEYSTORE_PASSWORD = "****"
+BROADCAST_ACCOUNT = "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A"
+
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "https://eth-sepolia.g.alchemy.com/v2/test"
+verify = true
+
+[networks.sepolia.extra_args]
+gas-limit = "1000000"
+priority-gas-price = "1000000000"
+"#;
+
+    const FOUNDRY_CONFIG_CONTENT: &str = r#"
+# Full reference https://github.com/foundry-rs/foundry/tree/master/crates/config
+
+[profile.default]
+
+[dependencies]
+
+[etherscan]
+sepolia = { key = "${API_KEY_ETHERSCAN}", url = "https://api.etherscan.io/v2/api?chainid=11155111" }
+
+[rpc_endpoints]
+sepolia = "https://ethereum-sepolia-rpc.publicnode.com"
+"#;
+
+    /// The arguments of a command as strings, without the program
+    fn forge_args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// A git repository at `dir` whose `main` branch has one commit adding `foundry.toml`
+    fn init_repo_with_commit(dir: &Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        repo.branch(
+            "main",
+            &commit_file(&repo, "foundry.toml", FOUNDRY_CONFIG_CONTENT, &[]),
+            true,
+        )
+        .unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        repo
+    }
+
+    /// Commit `name` with `content` on top of `parents`, without moving any branch
+    fn commit_file<'r>(
+        repo: &'r Repository,
+        name: &str,
+        content: &str,
+        parents: &[&git2::Commit],
+    ) -> git2::Commit<'r> {
+        let workdir = repo.workdir().unwrap();
+        fs::write(workdir.join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let oid = repo
+            .commit(None, &signature, &signature, name, &tree, parents)
+            .unwrap();
+        repo.find_commit(oid).unwrap()
+    }
+
+    #[test]
+    fn test_deployer_creation() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let deployer = ContractDeployer::new(
+            config_path.to_str().unwrap(),
+            DeployOptions {
+                skip_confirmation: true,
+                ..Default::default()
+            },
+        );
+        assert!(deployer.is_ok());
+    }
+
+    #[test]
+    fn test_deployer_execution() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let foundry_config_path = temp_dir.path().join("foundry.toml");
+
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+        fs::write(&foundry_config_path, FOUNDRY_CONFIG_CONTENT).unwrap();
+
+        let deployer = ContractDeployer::new(
+            config_path.to_str().unwrap(),
+            DeployOptions {
+                skip_confirmation: true,
+                ..Default::default()
+            },
+        );
+        assert!(deployer.is_ok());
+    }
+
+    #[test]
+    fn test_workdir_template_per_network() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\nworkdir_template = \"{name}-{network}\"",
+        );
+
+        fs::write(&config_path, config).unwrap();
+
+        let dir_for = |network: &str| {
+            ContractDeployer::new(
+                config_path.to_str().unwrap(),
+                DeployOptions {
+                    network_override: Some(network.to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .get_deployment_dir_name()
+        };
+
+        assert_eq!(dir_for("sepolia"), "test-contract-sepolia");
+        assert_eq!(dir_for("mainnet"), "test-contract-mainnet");
+    }
+
+    #[test]
+    fn test_validate_unknown_network() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer = ContractDeployer::new(
+            config_path.to_str().unwrap(),
+            DeployOptions {
+                network_override: Some("unknown".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let report = deployer.validate();
+        assert!(!report.passed);
+
+        let network_check = report.checks.iter().find(|c| c.name == "network").unwrap();
+        assert_eq!(network_check.status, CheckStatus::Fail);
+        let rpc_check = report.checks.iter().find(|c| c.name == "rpc_url").unwrap();
+        assert_eq!(rpc_check.status, CheckStatus::Skip);
+    }
+
+    #[test]
+    fn test_cleanup_failure_handling() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let missing_dir = temp_dir.path().join("already-removed");
+
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+
+        // Removing a directory that doesn't exist fails; by default that's only a warning
+        assert!(deployer.cleanup(missing_dir.to_str().unwrap()).is_ok());
+
+        deployer.config.project.strict_cleanup = true;
+        assert!(deployer.cleanup(missing_dir.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_aws_kms_forge_flags() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "[networks.sepolia]",
+            "[auth]\nmethod = \"aws_kms\"\n\n[networks.sepolia]",
+        );
+
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A",
+        );
+
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+
+        assert!(args.contains(&"--aws".to_string()));
+        assert!(args.contains(&"--sender".to_string()));
+        assert!(!args.contains(&"--account".to_string()));
+
+        // validate prepares the signer as a deploy would
+        #[cfg(not(feature = "aws"))]
+        {
+            let report = deployer.validate();
+            let auth = report.checks.iter().find(|c| c.name == "auth").unwrap();
+            assert_eq!(auth.status, CheckStatus::Fail);
+            assert!(auth.message.contains("the `aws` feature"));
+            assert!(!report.passed);
+        }
+    }
+
+    #[test]
+    fn test_unlocked_forge_flags() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "BROADCAST_ACCOUNT = \"0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A\"\n",
+                "",
+            )
+            .replace(
+                "[networks.sepolia]",
+                "[auth]\nmethod = \"unlocked\"\n\n[networks.sepolia]",
+            );
+
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set("KEYSTORE_PASSWORD", "****");
+
+        // The sender is required since the node signs for it
+        let err = deployer.validate_environment().unwrap_err().to_string();
+        assert!(err.contains("BROADCAST_ACCOUNT"));
+
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        );
+        assert!(deployer.validate_environment().is_ok());
+
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+
+        assert!(args.contains(&"--unlocked".to_string()));
+        let sender = args.iter().position(|a| a == "--sender").unwrap();
+        assert_eq!(
+            args[sender + 1],
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        );
+        for flag in ["--account", "--password", "--private-key", "--aws"] {
+            assert!(!args.contains(&flag.to_string()), "unexpected {}", flag);
+        }
+    }
+
+    #[test]
+    fn test_custom_keystore_var_names() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "[networks.sepolia]",
+            "[auth]\naccount_var = \"DEPLOYER_ACCOUNT\"\npassword_var = \"DEPLOYER_PASSWORD\"\n\n[networks.sepolia]",
+        );
+
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A",
+        );
+        deployer.env.set("KEYSTORE_ACCOUNT", "ignored");
+        deployer.env.set("DEPLOYER_ACCOUNT", "release");
+
+        // The configured password var is required, not KEYSTORE_PASSWORD
+        let err = deployer.validate_environment().unwrap_err().to_string();
+        assert!(err.contains("DEPLOYER_PASSWORD"));
+
+        deployer.env.set("DEPLOYER_PASSWORD", "hunter2");
+        assert!(deployer.validate_environment().is_ok());
+
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+
+        let account = args.iter().position(|a| a == "--account").unwrap();
+        assert_eq!(args[account + 1], "release");
+        let password = args.iter().position(|a| a == "--password").unwrap();
+        assert_eq!(args[password + 1], "hunter2");
+    }
+
+    #[test]
+    fn test_only_if_changed() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let repo = init_repo_with_commit(&source);
+        let head = repo.head().unwrap().target().unwrap().to_string();
+
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                &format!(
+                    "setup_command = \"echo 'test'\"\nrepo = \"file://{}\"",
+                    source.display()
+                ),
+            ),
+        )
+        .unwrap();
+        let output_dir = temp_dir.path().join("runs");
+        let options = DeployOptions {
+            only_if_changed: true,
+            output_dir: Some(output_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+
+        // Read from the remote, without cloning
+        let current = deployer.commit_without_checkout();
+        assert_eq!(current.as_deref(), Some(head.as_str()));
+
+        let record = |sha: &str| {
+            fs::create_dir_all(output_dir.join("sepolia-100")).unwrap();
+            fs::write(
+                output_dir.join("sepolia-100").join("manifest.json"),
+                serde_json::json!({
+                    "network": "sepolia",
+                    "script": "Deploy.s.sol",
+                    "timestamp": 100,
+                    "git_sha": sha,
+                })
+                .to_string(),
+            )
+            .unwrap();
+        };
+
+        record(&head);
+        assert_eq!(
+            deployer.unchanged_commit(current.as_deref()).unwrap(),
+            Some(head.clone())
+        );
+        deployer.options.force = true;
+        assert_eq!(deployer.unchanged_commit(current.as_deref()).unwrap(), None);
+        deployer.options.force = false;
+
+        record(&"0".repeat(40));
+        assert_eq!(deployer.unchanged_commit(current.as_deref()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_clone_timeout_removes_partial_clone() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        init_repo_with_commit(&source);
+        let url = format!("file://{}", source.display());
+
+        // A deadline that has already passed stands in for a slow clone
+        let target = temp_dir.path().join("slow");
+        let slow = CloneOptions {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let err = clone_repository(&url, &target, &slow).err().unwrap();
+        assert!(err.to_string().contains("timed out"));
+        assert!(!target.exists());
+
+        // A server that accepts the connection and never answers reports no progress
+        let stalled_url = format!("{}/repo.git", test_http::stalled());
+        let stalled = CloneOptions {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let err = clone_repository(&stalled_url, &target, &stalled)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Other failures keep their own error
+        let fast = CloneOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let missing = format!("file://{}", temp_dir.path().join("missing").display());
+        let err = clone_repository(&missing, &target, &fast).err().unwrap();
+        assert!(!err.to_string().contains("timed out"));
+
+        let target = temp_dir.path().join("fast");
+        clone_repository(&url, &target, &fast).unwrap();
+        assert!(target.join("foundry.toml").exists());
+    }
+
+    #[test]
+    fn test_max_clone_size_aborts_large_clone() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let repo = init_repo_with_commit(&source);
+        // Pseudo-random content (xorshift) so the pack does not compress away
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let blob: String = (0..16 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                format!("{:016x}", state)
+            })
+            .collect();
+        let initial = repo.head().unwrap().peel_to_commit().unwrap();
+        let large = commit_file(&repo, "blob.txt", &blob, &[&initial]);
+        repo.reference("refs/heads/main", large.id(), true, "add blob")
+            .unwrap();
+        let url = format!("file://{}", source.display());
+
+        let target = temp_dir.path().join("tiny-limit");
+        let tiny = CloneOptions {
+            max_bytes: Some(1024),
+            ..Default::default()
+        };
+        let err = clone_repository(&url, &target, &tiny).err().unwrap();
+        assert!(err.to_string().contains("over the limit of 1024 bytes"));
+        assert!(!target.exists());
+
+        let target = temp_dir.path().join("roomy-limit");
+        let roomy = CloneOptions {
+            max_bytes: Some(10 * 1024 * 1024),
+            ..Default::default()
+        };
+        clone_repository(&url, &target, &roomy).unwrap();
+        assert!(target.join("blob.txt").exists());
+    }
+
+    #[test]
+    fn test_clone_uses_proxy() {
+        // Stand-in proxy that records the first request line and refuses it
+        let (proxy_url, request) = test_http::refuse_once();
+
+        let temp_dir = tempdir().unwrap();
+        let options = CloneOptions {
+            proxy: Some(&proxy_url),
+            timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let result = clone_repository(
+            "https://git.example.invalid/token.git",
+            &temp_dir.path().join("clone"),
+            &options,
+        );
+        assert!(result.is_err());
+
+        let line = request.join().unwrap();
+        assert!(
+            line.starts_with("CONNECT git.example.invalid:443"),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_from_last_run() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "[networks.sepolia]",
+                "[networks.anvil]\nchain_id = 31337\nrpc_url = \"http://127.0.0.1:8545\"\nverify = false\n\n[networks.sepolia]",
+            ),
+        )
+        .unwrap();
+        let output_dir = temp_dir.path().join("runs");
+        let options = || DeployOptions {
+            from_last: true,
+            output_dir: Some(output_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let err = ContractDeployer::new(config_path.to_str().unwrap(), options())
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .contains("No previous run of 'test-contract'")
+        );
+
+        for (run, project, network, script, timestamp) in [
+            (
+                "anvil-100",
+                "test-contract",
+                "anvil",
+                "DeployVault.s.sol",
+                100,
+            ),
+            ("sepolia-50", "test-contract", "sepolia", "Deploy.s.sol", 50),
+            (
+                "sepolia-200",
+                "other-project",
+                "sepolia",
+                "Deploy.s.sol",
+                200,
+            ),
+        ] {
+            fs::create_dir_all(output_dir.join(run)).unwrap();
+            fs::write(
+                output_dir.join(run).join("manifest.json"),
+                serde_json::json!({
+                    "project": project,
+                    "network": network,
+                    "script": script,
+                    "timestamp": timestamp,
+                    "git_sha": format!("{:040x}", timestamp),
+                    "contracts": [],
+                })
+                .to_string(),
+            )
+            .unwrap();
+        }
+
+        let mut deployer = ContractDeployer::new(config_path.to_str().unwrap(), options()).unwrap();
+        let project = &deployer.config.project;
+        assert_eq!(project.network, "anvil");
+        assert_eq!(project.script, "DeployVault");
+        assert_eq!(
+            project.git_ref.as_deref(),
+            Some(format!("{:040x}", 100).as_str())
+        );
+
+        // A local project isn't checked out at the recorded commit, so it must be there already
+        let context = DeploymentContext {
+            working_directory: temp_dir.path().to_string_lossy().to_string(),
+            cleanup_path: None,
+            _cache_lock: None,
+        };
+        let err = deployer.prepare_project(&context).unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "Project is at commit unknown, but the last run deployed {:040x}",
+            100
+        )));
+    }
+
+    #[test]
+    fn test_validate_networks_command_network() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("networks.sh"),
+            r#"printf '{"optimism": {"chain_id": %s, "rpc_url": "http://localhost:8545", "verify": false}}' "$OP_CHAIN_ID""#,
+        )
+        .unwrap();
+        fs::create_dir_all(temp_dir.path().join("script")).unwrap();
+        fs::write(temp_dir.path().join("script/Deploy.s.sol"), "").unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = format!(
+            "networks_command = \"sh networks.sh\"\n{}",
+            CONFIG_CONTENT
+                .split("[networks.sepolia]")
+                .next()
+                .unwrap()
+                .replace("network = \"sepolia\"", "network = \"optimism\"")
+                .replace("[env.vars]", "[env.vars]\nOP_CHAIN_ID = \"10\"")
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let options = DeployOptions {
+            cwd_override: Some(temp_dir.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let report = deployer.validate();
+        let status = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.name == name)
+                .map(|check| check.status)
+        };
+        assert_eq!(status("network"), Some(CheckStatus::Pass));
+        assert_eq!(status("environment"), Some(CheckStatus::Pass));
+        assert_eq!(status("rpc_url"), Some(CheckStatus::Pass));
+        assert!(report.passed, "{:?}", report.checks);
+        assert_eq!(deployer.resolved_network_config().unwrap().chain_id, 10);
+    }
+
+    #[test]
+    fn test_config_without_networks() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let without_networks = CONFIG_CONTENT.split("[networks.sepolia]").next().unwrap();
+        fs::write(&config_path, without_networks).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let err = deployer.load_environment().unwrap_err().to_string();
+        assert!(err.starts_with("no networks defined; add a [networks.<name>] section"));
+        assert!(err.contains("[networks.sepolia]\nchain_id = 11155111"));
+
+        // A network from the command line is enough
+        let options = DeployOptions {
+            network_override: Some("local".to_string()),
+            network_json: Some(
+                r#"{"name":"local","chain_id":31337,"rpc_url":"http://localhost:8545","verify":false}"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let network_config = deployer.resolved_network_config().unwrap();
+        assert_eq!(network_config.chain_id, 31337);
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        assert!(forge_args(&forge_cmd).contains(&"http://localhost:8545".to_string()));
+    }
+
+    #[test]
+    fn test_network_override_json() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let options = DeployOptions {
+            network_override: Some("custom".to_string()),
+            network_json: Some(
+                r#"{"name":"custom","chain_id":31337,"rpc_url":"http://localhost:8545","verify":false}"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let network_config = deployer.resolved_network_config().unwrap();
+        assert_eq!(network_config.chain_id, 31337);
+
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+        assert!(args.contains(&"http://localhost:8545".to_string()));
+        assert!(!args.contains(&"--verify".to_string()));
+
+        // The config file itself is not modified
+        assert!(!fs::read_to_string(&config_path).unwrap().contains("custom"));
+
+        let malformed = DeployOptions {
+            network_json: Some(r#"{"name":"custom","chain_id":"x"}"#.to_string()),
+            ..Default::default()
+        };
+        let err = ContractDeployer::new(config_path.to_str().unwrap(), malformed)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Invalid --network-override JSON"));
+    }
+
+    #[test]
+    fn test_clone_non_default_branch() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let repo = init_repo_with_commit(&source);
+        let initial = repo.head().unwrap().peel_to_commit().unwrap();
+        let release = commit_file(&repo, "RELEASE", "", &[&initial]);
+        repo.branch("release", &release, true).unwrap();
+
+        let url = format!("file://{}", source.display());
+        let options = CloneOptions {
+            branch: Some("release"),
+            remote: Some("upstream"),
+            ..Default::default()
+        };
+        let target = temp_dir.path().join("clone");
+        let cloned = clone_repository(&url, &target, &options).unwrap();
+
+        assert_eq!(cloned.head().unwrap().shorthand(), Some("release"));
+        assert_eq!(
+            cloned.head().unwrap().peel_to_commit().unwrap().id(),
+            release.id()
+        );
+        assert!(target.join("RELEASE").exists());
+        assert!(cloned.find_remote("upstream").is_ok());
+    }
+
+    #[test]
+    fn test_clone_cache() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let repo = init_repo_with_commit(&source);
+        let initial = repo.head().unwrap().peel_to_commit().unwrap();
+
+        let url = format!("file://{}", source.display());
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            &format!(
+                "setup_command = \"echo 'test'\"\nrepo = \"{}\"\nclone_cache_dir = \"cache\"",
+                url
+            ),
+        );
+        fs::write(&config_path, config).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let checkout = |refresh_cache: bool| {
+            let options = DeployOptions {
+                refresh_cache,
+                ..Default::default()
+            };
+            let deployer = ContractDeployer::new(config_path, options).unwrap();
+            let context = deployer.prepare_deployment_context().unwrap();
+            assert!(context.cleanup_path.is_none());
+            let dir = PathBuf::from(&context.working_directory);
+            (dir.clone(), head_sha(&dir).unwrap())
+        };
+
+        // Miss: cloned into a directory keyed by the repository URL
+        let (dir, sha) = checkout(false);
+        assert_eq!(
+            dir,
+            temp_dir
+                .path()
+                .join("cache")
+                .join(clone_cache::cache_key(&url))
+        );
+        assert_eq!(sha, initial.id().to_string());
+
+        // Hit: the cache is reused and fetched, so the branch is at its new commit
+        let next = commit_file(&repo, "Token.sol", "", &[&initial]);
+        repo.reference("refs/heads/main", next.id(), true, "advance main")
+            .unwrap();
+        fs::write(dir.join("marker"), "kept").unwrap();
+        let (cached_dir, sha) = checkout(false);
+        assert_eq!(cached_dir, dir);
+        assert_eq!(sha, next.id().to_string());
+        assert!(dir.join("marker").exists());
+        assert!(dir.join("Token.sol").exists());
+
+        // A deploy holding the cache keeps a second one out
+        let deployer = ContractDeployer::new(config_path, DeployOptions::default()).unwrap();
+        let held = deployer.prepare_deployment_context().unwrap();
+        let err = deployer.prepare_deployment_context().err().unwrap();
+        assert!(err.to_string().contains("in use by another deploy"));
+        drop(held);
+
+        // A commit already in the cache is checked out without fetching
+        fs::rename(&source, temp_dir.path().join("moved")).unwrap();
+        let pinned = DeployOptions {
+            git_ref_override: Some(initial.id().to_string()),
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path, pinned).unwrap();
+        let context = deployer.prepare_deployment_context().unwrap();
+        assert_eq!(
+            head_sha(Path::new(&context.working_directory)),
+            Some(initial.id().to_string())
+        );
+        drop(context);
+
+        // --refresh-cache fetches anyway
+        let refresh = DeployOptions {
+            refresh_cache: true,
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path, refresh).unwrap();
+        assert!(deployer.prepare_deployment_context().is_err());
+    }
+
+    #[test]
+    fn test_git_ref_override() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let repo = init_repo_with_commit(&source);
+        let initial = repo.head().unwrap().peel_to_commit().unwrap();
+        let feature = commit_file(&repo, "FEATURE", "", &[&initial]);
+        repo.branch("feature/new-token", &feature, true).unwrap();
+
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\ngit_branch = \"main\"",
+        );
+        fs::write(&config_path, config).unwrap();
+        let options = DeployOptions {
+            git_ref_override: Some("feature/new-token".to_string()),
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        assert_eq!(deployer.config.project.git_branch, None);
+
+        // A remote branch resolves through its remote-tracking ref
+        let url = format!("file://{}", source.display());
+        let target = temp_dir.path().join("clone");
+        let clone_options = CloneOptions {
+            git_ref: deployer.config.project.git_ref.as_deref(),
+            ..Default::default()
+        };
+        let cloned = clone_repository(&url, &target, &clone_options).unwrap();
+        assert_eq!(
+            cloned.head().unwrap().peel_to_commit().unwrap().id(),
+            feature.id()
+        );
+        assert!(target.join("FEATURE").exists());
+
+        let missing = CloneOptions {
+            git_ref: Some("no-such-branch"),
+            ..Default::default()
+        };
+        let err = clone_repository(&url, &temp_dir.path().join("missing"), &missing)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Ref 'no-such-branch' not found"));
+    }
+
+    #[test]
+    fn test_sender_error_names_source() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "BROADCAST_ACCOUNT = \"0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A\"",
+                "BROADCAST_ACCOUNT = \"deployer.eth\"",
+            )
+            .replace("chain_id = 11155111", "chain_id = 31337");
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.load_env().unwrap();
+        let err = deployer.resolve_sender_name().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "BROADCAST_ACCOUNT (from config [env.vars]) is not a usable sender"
+        );
+        assert!(format!("{:#}", err).contains("ENS is not available on chain 31337"));
+    }
+
+    #[test]
+    fn test_resolve_rpc_url() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "https://eth-sepolia.g.alchemy.com/v2/test",
+                "https://eth-${CD_TEST_RPC_NETWORK}.g.alchemy.com/v2/${CD_TEST_RPC_KEY}",
+            )
+            .replace(
+                "[env.vars]",
+                "[env.vars]\nCD_TEST_RPC_NETWORK = \"sepolia\"\nCD_TEST_RPC_KEY = \"aB3dE5gH7jK9mN1pQ3sT5vX7\"",
+            );
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        assert_eq!(
+            deployer.resolve_rpc_url().unwrap(),
+            "https://eth-sepolia.g.alchemy.com/v2/********"
+        );
+    }
+
+    #[test]
+    fn test_typed_gas_flags() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\ngas_limit = \"${GAS_LIMIT}\"\ngas_price = \"20000000000\"\npriority_fee = \"1000000000\"",
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let new_deployer = |options: DeployOptions| {
+            let mut deployer =
+                ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+            deployer.env.set("GAS_LIMIT", "3000000");
+            deployer.config = deployer.raw_config.expand_with(&deployer.env);
+            deployer
+        };
+
+        let deployer = new_deployer(DeployOptions {
+            priority_fee: Some("2000000000".to_string()),
+            ..Default::default()
+        });
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        assert_eq!(
+            deployer.gas_flags(&network_config).unwrap(),
+            vec![
+                ("--gas-limit", "3000000".to_string()),
+                ("--with-gas-price", "20000000000".to_string()),
+                ("--priority-gas-price", "2000000000".to_string()),
+            ]
+        );
+
+        let deployer = new_deployer(DeployOptions {
+            gas_price: Some("20gwei".to_string()),
+            ..Default::default()
+        });
+        let err = deployer.gas_flags(&network_config).unwrap_err().to_string();
+        assert!(err.contains("gas_price must be a whole number"));
+    }
+
+    #[test]
+    fn test_tx_type() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let has_legacy = |deployer: &ContractDeployer| {
+            forge_args(
+                &deployer
+                    .build_forge_command(&network_config, "Deploy.s.sol")
+                    .unwrap(),
+            )
+            .contains(&"--legacy".to_string())
+        };
+
+        deployer.config.project.tx_type = Some(TxType::Eip1559);
+        assert!(!has_legacy(&deployer));
+
+        deployer.config.project.tx_type = Some(TxType::Legacy);
+        assert!(has_legacy(&deployer));
+
+        // A priority fee has no meaning for legacy transactions
+        deployer.config.project.priority_fee = Some("1000000000".to_string());
+        let err = deployer.gas_flags(&network_config).unwrap_err().to_string();
+        assert!(err.contains("priority_fee only applies to EIP-1559"));
+
+        deployer.config.project.priority_fee = None;
+        deployer.config.project.tx_type = Some(TxType::Eip1559);
+        deployer.config.project.default_forge_args = vec!["--legacy".to_string()];
+        let err = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("contain --legacy"));
+
+        // The banner reports --legacy from the extra arguments when no tx_type is set
+        deployer.config.project.tx_type = None;
+        assert_eq!(
+            deployer.tx_type_summary(&network_config),
+            "legacy (extra forge arguments)"
+        );
+        deployer.config.project.default_forge_args.clear();
+        assert_eq!(
+            deployer.tx_type_summary(&network_config),
+            "eip1559 (forge default)"
+        );
+    }
+
+    #[test]
+    fn test_network_gas_strategy() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = format!(
+            "{}{}",
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\ntx_type = \"legacy\"",
+            ),
+            r#"
+[networks.arbitrum]
+chain_id = 42161
+rpc_url = "https://arb1.arbitrum.io/rpc"
+verify = false
+gas = { type = "legacy", gas_price = "100000000" }
+
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth.llamarpc.com"
+verify = false
+gas = { type = "eip1559", priority_fee = "2" }
+"#
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let args_for = |deployer: &ContractDeployer, network: &str| {
+            let network_config = deployer.config.get_network(network).unwrap().clone();
+            forge_args(
+                &deployer
+                    .build_forge_command(&network_config, "Deploy.s.sol")
+                    .unwrap(),
+            )
+        };
+
+        // Legacy L2: single gas price and --legacy
+        let args = args_for(&deployer, "arbitrum");
+        assert!(args.contains(&"--legacy".to_string()));
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--with-gas-price", "100000000"])
+        );
+
+        // EIP-1559 mainnet overrides the project's legacy tx_type
+        let args = args_for(&deployer, "mainnet");
+        assert!(!args.contains(&"--legacy".to_string()));
+        assert!(args.windows(2).any(|w| w == ["--priority-gas-price", "2"]));
+
+        // A priority fee can't be combined with a legacy network
+        deployer.config.project.network = "arbitrum".to_string();
+        deployer
+            .config
+            .networks
+            .get_mut("arbitrum")
+            .unwrap()
+            .gas
+            .as_mut()
+            .unwrap()
+            .priority_fee = Some("2".to_string());
+        let network_config = deployer.config.get_network("arbitrum").unwrap().clone();
+        let err = deployer.gas_flags(&network_config).unwrap_err().to_string();
+        assert!(err.contains("networks.arbitrum.gas.type is \"legacy\""));
+
+        let unknown: Result<NetworkConfig, _> = toml::from_str(
+            "chain_id = 1\nrpc_url = \"x\"\nverify = false\ngas = { type = \"eip1559\", tip = \"2\" }",
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_optimizer_flags() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\noptimize = true\noptimizer_runs = \"${RUNS}\"",
+            ),
+        )
+        .unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("RUNS", "10000");
+        deployer.config = deployer.raw_config.expand_with(&deployer.env);
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        let args = forge_args(
+            &deployer
+                .build_forge_command(&network_config, "Deploy.s.sol")
+                .unwrap(),
+        );
+        assert!(args.contains(&"--optimize".to_string()));
+        assert!(args.windows(2).any(|w| w == ["--optimizer-runs", "10000"]));
+
+        deployer.config.project.optimizer_runs = None;
+        deployer.config.project.optimize = Some(false);
+        assert_eq!(deployer.optimizer_flags().unwrap(), ["--optimize=false"]);
+
+        deployer.config.project.optimize = None;
+        deployer.config.project.optimizer_runs = Some("many".to_string());
+        let err = deployer.optimizer_flags().unwrap_err().to_string();
+        assert!(err.contains("optimizer_runs must be a whole number"));
+    }
+
+    #[test]
+    fn test_fork_block() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace("verify = true", "verify = true\nfork_block = 19000000"),
+        )
+        .unwrap();
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let simulation = deployer
+            .forge_script_command(&network_config, "Deploy.s.sol", false)
+            .unwrap();
+        assert!(
+            forge_args(&simulation)
+                .windows(2)
+                .any(|w| w == ["--fork-block-number", "19000000"])
+        );
+        let broadcast = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        assert!(!forge_args(&broadcast).contains(&"--fork-block-number".to_string()));
+
+        let options = DeployOptions {
+            simulate_all: true,
+            fork_block: Some(19000500),
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let simulation = deployer
+            .forge_script_command(&network_config, "Deploy.s.sol", false)
+            .unwrap();
+        assert!(
+            forge_args(&simulation)
+                .windows(2)
+                .any(|w| w == ["--fork-block-number", "19000500"])
+        );
+    }
+
+    #[test]
+    fn test_echoed_command_masks_secrets() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT
+                .replace(
+                    "https://eth-sepolia.g.alchemy.com/v2/test",
+                    "https://eth-sepolia.g.alchemy.com/v2/aB3dE5gH7jK9mN1pQ3sT5vX7",
+                )
+                .replace(
+                    "priority-gas-price = \"1000000000\"",
+                    "priority-gas-price = \"1000000000\"\nprivate-key = \"0xdeadbeefcafe\"",
+                ),
+        )
+        .unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "release-signer");
+        deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+
+        let echoed = deployer.echoed_command(&forge_cmd);
+        assert!(echoed.starts_with("forge script script/Deploy.s.sol"));
+        for secret in [
+            "release-signer",
+            "hunter2",
+            "aB3dE5gH7jK9mN1pQ3sT5vX7",
+            "0xdeadbeefcafe",
+        ] {
+            assert!(!echoed.contains(secret), "{} in {}", secret, echoed);
+        }
+    }
+
+    #[test]
+    fn test_forge_env_names() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("TOKEN_NAME", "Token");
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let command = deployer
+            .deploy_command(&network_config, "Deploy.s.sol", temp_dir.path())
+            .unwrap();
+
+        // The merged environment plus the metadata variables; no git SHA outside a checkout
+        let mut expected: Vec<String> = deployer.env.get_vars().keys().cloned().collect();
+        expected.extend(["DEPLOY_TIMESTAMP".to_string(), "DEPLOY_NETWORK".to_string()]);
+        expected.sort();
+        assert_eq!(forge_env_names(&command), expected);
+        assert!(expected.contains(&"TOKEN_NAME".to_string()));
+    }
+
+    #[test]
+    fn test_export_command_json() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .deploy_command(&network_config, "Deploy.s.sol", temp_dir.path())
+            .unwrap();
+
+        let json = deployer
+            .export_command(&forge_cmd, &network_config)
+            .render(CommandFormat::Json)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let object = value.as_object().unwrap();
+        let mut keys: Vec<&str> = object.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["args", "env", "program", "working_dir"]);
+
+        assert_eq!(value["program"], "forge");
+        assert_eq!(
+            value["working_dir"],
+            temp_dir.path().to_string_lossy().as_ref()
+        );
+        let env: Vec<String> = serde_json::from_value(value["env"].clone()).unwrap();
+        assert_eq!(env, forge_env_names(&forge_cmd));
+
+        // Same args as the command that would run, with only secrets and the RPC key masked
+        let args: Vec<String> = serde_json::from_value(value["args"].clone()).unwrap();
+        let actual = forge_args(&forge_cmd);
+        assert_eq!(args.len(), actual.len());
+        for (exported, actual) in args.iter().zip(&actual) {
+            match actual.as_str() {
+                "hunter2" => assert_eq!(exported, "${KEYSTORE_PASSWORD}"),
+                url if url == network_config.rpc_url => {
+                    assert_eq!(*exported, redact::redact_rpc_url(url))
+                }
+                _ => assert_eq!(exported, actual),
+            }
+        }
+        assert!(!json.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_forge_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "load_files = [\".env\"]",
+                "load_files = [\".env\"]\nforge_prefix = \"FORGE_\"",
+            ),
+        )
+        .unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("FORGE_PRIVATE_KEY", "0xabc");
+        deployer.env.set("FORGE_", "bare");
+        deployer.env.set("ETHERSCAN_API_KEY", "key");
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let command = deployer
+            .deploy_command(&network_config, "Deploy.s.sol", temp_dir.path())
+            .unwrap();
+
+        let env = |name: &str| {
+            command
+                .get_envs()
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| value)
+                .map(|value| value.to_string_lossy().to_string())
+        };
+        assert_eq!(env("PRIVATE_KEY").as_deref(), Some("0xabc"));
+        // The prefixed name is still set; unprefixed variables pass through as before
+        assert_eq!(env("FORGE_PRIVATE_KEY").as_deref(), Some("0xabc"));
+        assert_eq!(env("ETHERSCAN_API_KEY").as_deref(), Some("key"));
+        assert!(command.get_envs().all(|(key, _)| !key.is_empty()));
+    }
+
+    #[test]
+    fn test_dangerous_inline_private_key() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "priority-gas-price = \"1000000000\"",
+                "priority-gas-price = \"1000000000\"\nprivate-key = \"0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80\"\n\n[networks.local]\nchain_id = 31337\nrpc_url = \"http://localhost:8545\"\nverify = false\n\n[networks.local.extra_args]\nprivate-key = \"0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80\"\n\n[networks.public]\nchain_id = 31337\nrpc_url = \"https://rpc.example.com\"\nverify = false\n\n[networks.public.extra_args]\nprivate-key = \"0x01\"",
+            ),
+        )
+        .unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let flagged = |network: &str| {
+            let network_config = deployer.config.get_network(network).unwrap().clone();
+            let forge_cmd = deployer
+                .build_forge_command(&network_config, "Deploy.s.sol")
+                .unwrap();
+            deployer
+                .dangerous_flags_in(&forge_cmd, &network_config)
+                .into_iter()
+                .map(|(flag, _)| flag.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flagged("sepolia"), ["--private-key"]);
+        // anvil's well-known keys are fine on a local node
+        assert!(flagged("local").is_empty());
+        // A local chain id on a public RPC is not a local node
+        assert_eq!(flagged("public"), ["--private-key"]);
+
+        // The prompt comes even under -y, and only --force-dangerous skips it
+        let confirm = |options: DeployOptions, read: fn() -> io::Result<String>| {
+            let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+            let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+            let forge_cmd = deployer
+                .build_forge_command(&network_config, "Deploy.s.sol")
+                .unwrap();
+            deployer
+                .confirm_dangerous_flags(&forge_cmd, &network_config, read)
+                .unwrap()
+        };
+        let yes = || DeployOptions {
+            skip_confirmation: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            confirm(yes(), || Ok("y\n".to_string())),
+            Confirmation::Declined
+        );
+        assert_eq!(
+            confirm(yes(), || Ok("yes\n".to_string())),
+            Confirmation::Approved
+        );
+        let forced = DeployOptions {
+            force_dangerous: true,
+            ..yes()
+        };
+        assert_eq!(
+            confirm(forced, || unreachable!("--force-dangerous doesn't ask")),
+            Confirmation::Approved
+        );
+
+        let list = HashMap::from([("--unlocked".to_string(), "node-held key".to_string())]);
+        let args = ["--private-key=0x01".to_string(), "--unlocked".to_string()];
+        assert_eq!(
+            dangerous_flags(&args, &list),
+            [("--unlocked", "node-held key")]
+        );
+        assert_eq!(
+            dangerous_flags(&args, &deployer.config.project.dangerous_flags)
+                .into_iter()
+                .map(|(flag, _)| flag)
+                .collect::<Vec<_>>(),
+            ["--private-key", "--unlocked"]
+        );
+    }
+
+    #[test]
+    fn test_skip_flags() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\nskip = [\"test/**\", \"script/Legacy.s.sol\"]",
+            ),
+        )
+        .unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let args = forge_args(
+            &deployer
+                .build_forge_command(&network_config, "Deploy.s.sol")
+                .unwrap(),
+        );
+        assert!(args.windows(2).any(|w| w == ["--skip", "test/**"]));
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--skip", "script/Legacy.s.sol"])
+        );
+
+        deployer.config.project.skip.push(" ".to_string());
+        let err = deployer.skip_flags().unwrap_err().to_string();
+        assert!(err.contains("empty pattern"));
+    }
+
+    #[test]
+    fn test_extra_args_summary() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\ndefault_forge_args = [\"--slow\"]",
+            ),
+        )
+        .unwrap();
+
+        let deployer = ContractDeployer::new(
+            config_path.to_str().unwrap(),
+            DeployOptions {
+                forge_args: vec!["--etherscan-api-key".to_string(), "ABCDEF123".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        assert_eq!(
+            deployer.extra_args_summary(&network_config),
+            "--slow --gas-limit 1000000 --priority-gas-price 1000000000 --etherscan-api-key ********"
+        );
+    }
+
+    #[test]
+    fn test_abort_on_warning() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace("verify = true", "verify = true\nmin_balance = 1.0"),
+        )
+        .unwrap();
+
+        let new_deployer = |abort_on_warning| {
+            ContractDeployer::new(
+                config_path.to_str().unwrap(),
+                DeployOptions {
+                    abort_on_warning,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        // A low balance and the missing .env are only warnings by default
+        let mut lenient = new_deployer(false);
+        let network_config = lenient.config.get_network("sepolia").unwrap().clone();
+        assert!(
+            lenient
+                .warn_on_low_balance(&network_config, Some(0))
+                .is_ok()
+        );
+        assert!(lenient.load_env().is_ok());
+
+        let mut strict = new_deployer(true);
+        let err = strict
+            .warn_on_low_balance(&network_config, Some(0))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("below the configured minimum"));
+        assert!(err.contains("--abort-on-warning"));
+        let err = strict.load_env().unwrap_err().to_string();
+        assert!(err.contains(".env does not exist"));
+
+        // Overriding a failed guard is a warning too
+        let mut behind = network_config.clone();
+        behind.require_synced = true;
+        behind.rpc_url = test_http::rpc_node(&[
+            ("eth_syncing", json!(true)),
+            ("eth_getBlockByNumber", json!({ "timestamp": "0x0" })),
+        ]);
+        lenient.options.force = true;
+        lenient.check_node_synced(&behind).unwrap();
+        strict.options.force = true;
+        let err = strict.check_node_synced(&behind).unwrap_err().to_string();
+        assert!(err.contains("continuing because of --force"));
+
+        // project.strict has the same effect
+        let mut from_config = new_deployer(false);
+        from_config.config.project.strict = true;
+        assert!(
+            from_config
+                .warn_on_low_balance(&network_config, Some(0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_command_wrapper() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\ncommand_wrapper = \"time -v\"",
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+
+        assert_eq!(forge_cmd.get_program(), "time");
+        assert_eq!(
+            &args[..4],
+            &["-v", "forge", "script", "script/Deploy.s.sol"]
+        );
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+
+        let command = deployer.plan_command(&forge_cmd, &network_config);
+        assert_eq!(command[0], "forge");
+        assert!(command.contains(&"${KEYSTORE_PASSWORD}".to_string()));
+        assert!(!command.contains(&"hunter2".to_string()));
+
+        // Secrets from any source, and API keys in URLs
+        deployer.env.set("ALCHEMY_API_KEY", "Zx81mQp4TfW2vLc9");
+        let mut forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        forge_cmd
+            .arg("--fork-url")
+            .arg("https://eth-mainnet.g.alchemy.com/v2/Zx81mQp4TfW2vLc9")
+            .arg("--verifier-url=https://api.example.com/api?apikey=9f8e7d6c5b4a39281706");
+        let command = deployer.plan_command(&forge_cmd, &network_config);
+        let joined = command.join(" ");
+        assert!(joined.contains("https://eth-mainnet.g.alchemy.com/v2/${ALCHEMY_API_KEY}"));
+        assert!(!joined.contains("Zx81mQp4TfW2vLc9"));
+        assert!(!joined.contains("9f8e7d6c5b4a39281706"));
+
+        let plan_path = temp_dir.path().join("plan.json");
+        DeploymentPlan::new(&deployer.raw_config, None, command)
+            .write(&plan_path)
+            .unwrap();
+
+        let replay =
+            ContractDeployer::from_plan(plan_path.to_str().unwrap(), DeployOptions::default())
+                .unwrap();
+        assert_eq!(replay.config.project.network, "sepolia");
+        assert_eq!(replay.config.get_script_name(), "Deploy.s.sol");
+        assert_eq!(
+            replay.plan.as_ref().unwrap().secrets,
+            vec![
+                "ALCHEMY_API_KEY".to_string(),
+                "KEYSTORE_PASSWORD".to_string()
+            ]
+        );
+
+        // The password is not in the plan and must come from the environment
+        let err = replay.validate_environment().unwrap_err().to_string();
+        assert!(err.contains("KEYSTORE_PASSWORD"));
+    }
+
+    #[test]
+    fn test_duplicate_broadcast_flag() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\ndefault_forge_args = [\"--broadcast\", \"--slow\"]",
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let options = DeployOptions {
+            forge_args: vec!["--rpc-url=http://localhost:8545".into()],
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        // Warns by default and still builds the command
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        assert!(forge_args(&forge_cmd).contains(&"--slow".to_string()));
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.config.project.duplicate_flags = FlagPolicy::Error;
+        let err = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("repeat flags the tool already sets: --broadcast"));
+
+        assert_eq!(
+            duplicate_flags(
+                &[
+                    "--broadcast".to_string(),
+                    "--rpc-url=http://localhost:8545".to_string(),
+                    "--broadcast".to_string(),
+                    "--slow".to_string(),
+                ],
+                &[]
+            ),
+            vec!["--broadcast", "--rpc-url"]
+        );
+
+        // Gas flags only clash when the typed gas settings set them too
+        let gas_args = ["--gas-limit".to_string(), "3000000".to_string()];
+        assert!(duplicate_flags(&gas_args, &[]).is_empty());
+        assert_eq!(
+            duplicate_flags(&gas_args, &["--gas-limit"]),
+            vec!["--gas-limit"]
+        );
+        deployer.config.project.gas_limit = Some("5000000".to_string());
+        let err = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("--gas-limit"), "{}", err);
+    }
+
+    #[test]
+    fn test_forge_args_order() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\ndefault_forge_args = [\"--slow\", \"--gas-estimate-multiplier\", \"130\"]",
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let options = DeployOptions {
+            forge_args: vec!["--gas-estimate-multiplier".into(), "150".into()],
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let forge_cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let args = forge_args(&forge_cmd);
+
+        let extra_start = args.iter().position(|a| a == "--slow").unwrap();
+        assert_eq!(
+            &args[extra_start..],
+            &[
+                "--slow",
+                "--gas-estimate-multiplier",
+                "130",
+                "--gas-limit",
+                "1000000",
+                "--priority-gas-price",
+                "1000000000",
+                "--gas-estimate-multiplier",
+                "150",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forge_exit_code_passthrough() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+
+        let mut failing_forge = Command::new("sh");
+        failing_forge.arg("-c").arg("exit 3");
+
+        let err = deployer
+            .execute_forge_command(failing_forge, false)
+            .unwrap_err();
+        assert_eq!(crate::exit_code::for_error(&err), 3);
+
+        // Errors from the tool itself use a distinct code
+        let config_err = ContractDeployer::new("missing.toml", DeployOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            crate::exit_code::for_error(&config_err),
+            crate::exit_code::TOOL_ERROR
+        );
+
+        let differ = anyhow::Error::from(crate::exit_code::ConfigsDiffer { changes: 2 });
+        assert_eq!(
+            crate::exit_code::for_error(&differ),
+            crate::exit_code::CONFIGS_DIFFER
+        );
+
+        let invalid = anyhow::Error::from(crate::exit_code::ValidationFailed { failed: 1 });
+        assert_eq!(
+            crate::exit_code::for_error(&invalid),
+            crate::exit_code::VALIDATION_FAILED
+        );
+    }
+
+    #[test]
+    fn test_explain_returns_result() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let options = DeployOptions {
+            explain: true,
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        let result = deployer.deploy().unwrap();
+
+        assert_eq!(result.status, DeploymentStatus::Explained);
+        assert_eq!(result.network, "sepolia");
+        assert_eq!(result.script, "Deploy.s.sol");
+        assert_eq!(result.forge_exit_code, None);
+        assert!(result.contracts.is_empty());
+    }
+
+    #[test]
+    fn test_setup_command_override() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+        let new_deployer = |setup_command_override: Option<&str>| {
+            let options = DeployOptions {
+                setup_command_override: setup_command_override.map(str::to_string),
+                ..Default::default()
+            };
+            ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap()
+        };
+
+        let configured = new_deployer(None).setup_command(temp_dir.path()).unwrap();
+        assert_eq!(configured.get_program(), "echo");
+
+        let overridden = new_deployer(Some("forge install && bun install"))
+            .setup_command(temp_dir.path())
+            .unwrap();
+        assert_eq!(overridden.get_program(), "sh");
+        assert_eq!(
+            forge_args(&overridden),
+            vec!["-c", "forge install && bun install"]
+        );
+
+        assert!(
+            new_deployer(Some(""))
+                .setup_command(temp_dir.path())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_no_setup_skips_setup_command() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace("echo 'test'", "touch setup-ran"),
+        )
+        .unwrap();
+        let project_dir = temp_dir.path().to_str().unwrap();
+        let marker = temp_dir.path().join("setup-ran");
+
+        let options = DeployOptions {
+            no_setup: true,
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        deployer.setup_project(project_dir).unwrap();
+        assert!(!marker.exists());
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.setup_project(project_dir).unwrap();
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn test_discover_script() {
+        let temp_dir = tempdir().unwrap();
+        let script_dir = temp_dir.path().join("script");
+        fs::create_dir_all(&script_dir).unwrap();
+        fs::write(script_dir.join("Deploy.s.sol"), "").unwrap();
+        fs::write(script_dir.join("Helpers.sol"), "").unwrap();
+
+        assert_eq!(discover_script(temp_dir.path()).unwrap(), "Deploy");
+
+        fs::write(script_dir.join("Upgrade.s.sol"), "").unwrap();
+        let err = discover_script(temp_dir.path()).unwrap_err().to_string();
+        assert!(err.contains("Deploy, Upgrade"));
+    }
+
+    #[test]
+    fn test_detect_setup_command() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        assert_eq!(detect_setup_command(dir), None);
+
+        // foundry.toml alone has nothing to install
+        fs::write(dir.join("foundry.toml"), "").unwrap();
+        assert_eq!(detect_setup_command(dir), None);
+
+        fs::write(dir.join(".gitmodules"), "").unwrap();
+        assert_eq!(
+            detect_setup_command(dir),
+            Some(("foundry.toml", "forge install"))
+        );
+
+        // JS lockfiles take precedence
+        fs::write(dir.join("package-lock.json"), "{}").unwrap();
+        assert_eq!(
+            detect_setup_command(dir),
+            Some(("package-lock.json", "npm ci"))
+        );
+
+        fs::write(dir.join("bun.lockb"), "").unwrap();
+        assert_eq!(
+            detect_setup_command(dir),
+            Some(("bun.lockb", "bun install"))
+        );
+    }
+
+    #[test]
+    fn test_auto_confirm_policy() {
+        // Unset follows -y
+        assert!(should_prompt(None, false));
+        assert!(!should_prompt(None, true));
+        // auto_confirm = true never prompts
+        assert!(!should_prompt(Some(true), false));
+        assert!(!should_prompt(Some(true), true));
+        // auto_confirm = false always prompts, even with -y
+        assert!(should_prompt(Some(false), false));
+        assert!(should_prompt(Some(false), true));
+    }
+
+    #[test]
+    fn test_approval_command() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A",
+        );
+        let mut network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        network_config.approval_command = Some(
+            r#"test "$DEPLOY_NETWORK" = sepolia && test "$DEPLOY_CHAIN_ID" = 11155111 && test -n "$DEPLOY_SENDER""#.to_string(),
+        );
+        assert_eq!(
+            deployer.confirm_execution(&network_config).unwrap(),
+            Confirmation::Approved
+        );
+
+        network_config.approval_command = Some("exit 3".to_string());
+        assert_eq!(
+            deployer.confirm_execution(&network_config).unwrap(),
+            Confirmation::Declined
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sender_from_keystore() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        // Stand-in for `cast wallet address --account <acct> --password-file <file>`
+        let cast = temp_dir.path().join("cast");
+        fs::write(
+            &cast,
+            "#!/bin/sh\n\
+             case \"$*\" in *s3cret*|*hunter2*) echo 'password on argv' >&2; exit 1 ;; esac\n\
+             if [ \"$4\" = deployer ] && [ \"$(cat \"$6\")\" = hunter2 ]; then\n\
+             echo 0x70997970C51812dc3A010C7d01b50e0d17dc79C8\n\
+             else echo \"wrong password $(cat \"$6\")\" >&2; exit 1; fi\n",
+        )
+        .unwrap();
+        fs::set_permissions(&cast, fs::Permissions::from_mode(0o755)).unwrap();
+        let cast = cast.to_str().unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+
+        deployer.derive_keystore_sender(cast).unwrap();
+        assert_eq!(
+            deployer.env.get("BROADCAST_ACCOUNT").unwrap(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+        );
+
+        // Cached for the rest of the run
+        deployer.env.set("KEYSTORE_PASSWORD", "wrong");
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0x00000000000000000000000000000000000000a1",
+        );
+        deployer.derive_keystore_sender(cast).unwrap();
+        assert_eq!(
+            deployer.env.get("BROADCAST_ACCOUNT").unwrap(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+        );
+
+        // A sender set by hand is checked against the keystore, whatever its source
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("KEYSTORE_ACCOUNT", "deployer");
+        deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0x00000000000000000000000000000000000000a1",
+        );
+        deployer.options.abort_on_warning = true;
+        let err = deployer.derive_keystore_sender(cast).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Ignoring BROADCAST_ACCOUNT (from computed)")
+        );
+
+        let err = keystore_address(cast, "deployer", "s3cret")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("keystore account 'deployer'"));
+        assert!(!err.contains("s3cret"));
+    }
+
+    #[test]
+    fn test_senders_build_one_command_each() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\n\
+                 senders = [\n\
+                   { account = \"alice\" },\n\
+                   { account = \"bob\", address = \"0x00000000000000000000000000000000000000B0\", password_var = \"BOB_PASSWORD\" },\n\
+                 ]",
+            ),
+        )
+        .unwrap();
+
+        // Stand-in cast: alice unlocks with hunter2, bob with b0b
+        let cast = temp_dir.path().join("cast");
+        fs::write(
+            &cast,
+            "#!/bin/sh\n\
+             case \"$4:$(cat \"$6\")\" in\n\
+             alice:hunter2) echo 0x00000000000000000000000000000000000000A1 ;;\n\
+             bob:b0b) echo 0x00000000000000000000000000000000000000b0 ;;\n\
+             *) echo 'wrong password' >&2; exit 1 ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        fs::set_permissions(&cast, fs::Permissions::from_mode(0o755)).unwrap();
+        let cast = cast.to_str().unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let senders = deployer.senders().to_vec();
+        assert_eq!(senders.len(), 2);
+
+        let mut commands = Vec::new();
+        for sender in &senders {
+            let options = DeployOptions {
+                sender: Some(sender.clone()),
+                ..Default::default()
+            };
+            let mut deployer =
+                ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+            deployer.env.set("KEYSTORE_PASSWORD", "hunter2");
+            deployer.env.set("BOB_PASSWORD", "b0b");
+            deployer.apply_sender(sender, cast).unwrap();
+
+            let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+            let args = forge_args(
+                &deployer
+                    .build_forge_command(&network_config, "Deploy.s.sol")
+                    .unwrap(),
+            );
+            commands.push(args);
+        }
+
+        assert!(commands[0].windows(2).any(|w| w == ["--account", "alice"]));
+        assert!(
+            commands[0]
+                .windows(2)
+                .any(|w| w == ["--password", "hunter2"])
+        );
+        assert!(
+            commands[0]
+                .windows(2)
+                .any(|w| w == ["--sender", "0x00000000000000000000000000000000000000A1"])
+        );
+        assert!(commands[1].windows(2).any(|w| w == ["--account", "bob"]));
+        assert!(commands[1].windows(2).any(|w| w == ["--password", "b0b"]));
+
+        // A configured address must match the keystore
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.env.set("BOB_PASSWORD", "b0b");
+        let wrong = SenderConfig {
+            address: Some("0x00000000000000000000000000000000000000C0".to_string()),
+            ..senders[1].clone()
+        };
+        let err = deployer.apply_sender(&wrong, cast).unwrap_err().to_string();
+        assert!(err.contains("keystore account bob is 0x00000000000000000000000000000000000000b0"));
+    }
+
+    #[test]
+    fn test_safe_mode() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "load_files = [\".env\"]",
+                "load_files = [\".env\"]\nreject_empty_required = false",
+            )
+            .replace(
+                "gas-limit = \"1000000\"",
+                "gas-limit = \"${SAFE_MODE_GAS_LIMIT}\"",
+            )
+            + r#"
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/${SAFE_MODE_MAINNET_KEY}"
+verify = true
+"#;
+        fs::write(&config_path, config).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let safe = |skip: &[SafeCheck]| DeployOptions {
+            safe: SafeMode::new(true, skip),
+            ..Default::default()
+        };
+
+        let mut deployer = ContractDeployer::new(config_path, safe(&[])).unwrap();
+        assert!(deployer.config.env.reject_empty_required);
+        assert!(
+            deployer
+                .config
+                .networks
+                .values()
+                .all(|network| network.require_synced)
+        );
+
+        // Only the selected network's variables have to be defined
+        let err = deployer.load_and_validate_environment().unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("networks.sepolia.extra_args.gas-limit: SAFE_MODE_GAS_LIMIT"));
+        assert!(!message.contains("SAFE_MODE_MAINNET_KEY"));
+
+        let mut deployer =
+            ContractDeployer::new(config_path, safe(&[SafeCheck::StrictExpansion])).unwrap();
+        deployer.load_and_validate_environment().unwrap();
+
+        assert_eq!(
+            typed_confirmation("sepolia\n", "sepolia"),
+            Confirmation::Approved
+        );
+        assert_eq!(typed_confirmation("y\n", "sepolia"), Confirmation::Declined);
+    }
+
+    #[test]
+    fn test_safe_mode_guards() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "priority-gas-price = \"1000000000\"",
+                "priority-gas-price = \"1000000000\"\nsender = \"${BROADCAST_ACCOUNT}\"",
+            )
+            .replace(
+                "BROADCAST_ACCOUNT = \"0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A\"\n",
+                "",
+            );
+        fs::write(&config_path, config).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let safe = |skip: &[SafeCheck]| DeployOptions {
+            safe: SafeMode::new(true, skip),
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path, safe(&[])).unwrap();
+        assert!(deployer.config.env.reject_empty_required);
+        assert!(deployer.config.project.check_code_size);
+        deployer.load_env().unwrap();
+
+        // A sender set while validating satisfies a reference to it
+        let err = deployer.check_strict_expansion().unwrap_err();
+        assert!(err.to_string().contains("BROADCAST_ACCOUNT"));
+        deployer.env.set(
+            "BROADCAST_ACCOUNT",
+            "0x00000000000000000000000000000000000000a1",
+        );
+        deployer.check_strict_expansion().unwrap();
+
+        let mut network_config = deployer.config.networks["sepolia"].clone();
+        let node = test_http::rpc_node;
+
+        // Chain id
+        network_config.rpc_url = node(&[("eth_chainId", json!("0x1"))]);
+        let err = deployer.check_chain_id(&network_config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("is on chain 1, but chain_id = 11155111")
+        );
+        network_config.rpc_url = node(&[("eth_chainId", json!("0xaa36a7"))]);
+        deployer.check_chain_id(&network_config).unwrap();
+        network_config.rpc_url = node(&[]);
+        let err = deployer.check_chain_id(&network_config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("could not verify the RPC chain id")
+        );
+
+        // Balance: readable, non-zero and above min_balance
+        network_config.rpc_url = node(&[("eth_getBalance", json!("0x0"))]);
+        let balance = deployer.fetch_deployer_balance(&network_config).unwrap();
+        assert_eq!(balance, Some(0));
+        let err = deployer
+            .check_safe_balance(&network_config, balance)
+            .unwrap_err();
+        assert!(err.to_string().contains("the deployer has no funds"));
+        network_config.rpc_url = node(&[]);
+        let balance = deployer.fetch_deployer_balance(&network_config).unwrap();
+        let err = deployer
+            .check_safe_balance(&network_config, balance)
+            .unwrap_err();
+        assert!(err.to_string().contains("balance could not be checked"));
+        network_config.min_balance = Some(1.0);
+        let err = deployer
+            .check_safe_balance(&network_config, Some(500_000_000_000_000_000))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("below the configured minimum of 1 ETH")
+        );
+        deployer
+            .check_safe_balance(&network_config, Some(1_000_000_000_000_000_000))
+            .unwrap();
+
+        // Synced, which --force still overrides
+        assert!(network_config.require_synced);
+        network_config.rpc_url = node(&[
+            (
+                "eth_syncing",
+                json!({ "currentBlock": "0x1", "highestBlock": "0x10" }),
+            ),
+            ("eth_getBlockByNumber", json!({ "timestamp": "0x0" })),
+        ]);
+        assert!(deployer.check_node_synced(&network_config).is_err());
+        deployer.options.force = true;
+        deployer.check_node_synced(&network_config).unwrap();
+        deployer.options.force = false;
+
+        // Typed confirmation, unless --assume-yes-for names the network
+        assert!(deployer.requires_typed_confirm());
+        deployer.options.skip_confirmation = true;
+        assert!(deployer.requires_typed_confirm());
+        deployer.options.assume_yes_for = vec!["sepolia".to_string()];
+        assert!(!deployer.requires_typed_confirm());
+
+        // A code size check that can't build the script fails instead of passing
+        let empty_project = tempdir().unwrap();
+        let err = deployer
+            .check_code_size(empty_project.path())
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("Contract sizes not checked"), "{}", err);
+        assert!(err.ends_with("(--safe)"), "{}", err);
+
+        // Skipped checks don't touch the RPC
+        let deployer = ContractDeployer::new(
+            config_path,
+            safe(&[SafeCheck::ChainId, SafeCheck::Balance, SafeCheck::Synced]),
+        )
+        .unwrap();
+        let network_config = deployer.config.networks["sepolia"].clone();
+        assert!(!network_config.require_synced);
+        deployer.check_chain_id(&network_config).unwrap();
+        deployer.check_safe_balance(&network_config, None).unwrap();
+    }
+
+    #[test]
+    fn test_explicit_cwd() {
+        let temp_dir = tempdir().unwrap();
+        let contracts = temp_dir.path().join("contracts");
+        fs::create_dir_all(&contracts).unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT
+            .replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\ncwd = \"${PROJECT_ROOT}/contracts\"",
+            )
+            .replace(
+                "[env.vars]",
+                &format!(
+                    "[env.vars]\nPROJECT_ROOT = \"{}\"",
+                    temp_dir.path().display()
+                ),
+            );
+        fs::write(&config_path, config).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let mut deployer = ContractDeployer::new(config_path, DeployOptions::default()).unwrap();
+        deployer.load_and_validate_environment().unwrap();
+        let err = deployer.prepare_deployment_context().unwrap_err();
+        assert!(err.to_string().contains("is not a foundry project"));
+
+        fs::write(contracts.join("foundry.toml"), FOUNDRY_CONFIG_CONTENT).unwrap();
+        let context = deployer.prepare_deployment_context().unwrap();
+        assert_eq!(
+            PathBuf::from(&context.working_directory),
+            fs::canonicalize(&contracts).unwrap()
+        );
+        assert!(context.cleanup_path.is_none());
+
+        // --cwd replaces the configured directory
+        let options = DeployOptions {
+            cwd_override: Some(temp_dir.path().join("missing").display().to_string()),
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path, options).unwrap();
+        deployer.load_and_validate_environment().unwrap();
+        let err = deployer.prepare_deployment_context().unwrap_err();
+        assert!(err.to_string().contains("project.cwd does not exist"));
+    }
+
+    #[test]
+    fn test_assume_yes_for() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.to_string()
+            + r#"
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/test"
+verify = true
+"#;
+        fs::write(&config_path, config).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let deployer_for = |network: &str, skip_confirmation: bool| {
+            let options = DeployOptions {
+                network_override: Some(network.to_string()),
+                skip_confirmation,
+                assume_yes_for: vec!["sepolia".to_string(), "base".to_string()],
+                ..Default::default()
+            };
+            ContractDeployer::new(config_path, options).unwrap()
+        };
+
+        // The multi-network run confirms sepolia unattended but still prompts for mainnet
+        let sepolia = deployer_for("sepolia", false);
+        let config = sepolia.config.get_network("sepolia").unwrap();
+        assert_eq!(
+            sepolia.confirm_execution(config).unwrap(),
+            Confirmation::Approved
+        );
+        let mainnet = deployer_for("mainnet", false);
+        assert!(!mainnet.assume_yes());
+        assert!(should_prompt(None, mainnet.assume_yes()));
+
+        // -y still covers every network
+        assert!(deployer_for("mainnet", true).assume_yes());
+    }
+
+    #[test]
+    fn test_config_dir_resolution() {
+        let temp_dir = tempdir().unwrap();
+        let configs = temp_dir.path().join("configs");
+        let shared = temp_dir.path().join("shared");
+        for (dir, value) in [(&configs, "from-configs"), (&shared, "from-shared")] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join(".env"), format!("CD_TEST_CONFIG_DIR={}", value)).unwrap();
+            fs::write(
+                dir.join("networks.toml"),
+                format!(
+                    "[networks.{}]\nchain_id = 8453\nrpc_url = \"https://mainnet.base.org\"\nverify = false\n",
+                    value.replace('-', "_")
+                ),
+            )
+            .unwrap();
+        }
+        let config_path = configs.join("deploy.toml");
+        fs::write(
+            &config_path,
+            format!("networks_file = \"networks.toml\"\n{}", CONFIG_CONTENT),
+        )
+        .unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        // Both the include and the env file resolve against the config file's directory
+        let mut deployer = ContractDeployer::new(config_path, DeployOptions::default()).unwrap();
+        deployer.load_and_validate_environment().unwrap();
+        assert!(deployer.config.networks.contains_key("from_configs"));
+        assert_eq!(
+            deployer.env.get("CD_TEST_CONFIG_DIR").unwrap(),
+            "from-configs"
+        );
+
+        // ... or both against --config-dir
+        let options = DeployOptions {
+            config_dir: Some(shared.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let mut deployer = ContractDeployer::new(config_path, options).unwrap();
+        deployer.load_and_validate_environment().unwrap();
+        assert!(deployer.config.networks.contains_key("from_shared"));
+        assert!(!deployer.config.networks.contains_key("from_configs"));
+        assert_eq!(
+            deployer.env.get("CD_TEST_CONFIG_DIR").unwrap(),
+            "from-shared"
+        );
+    }
+
+    #[test]
+    fn test_metadata_env() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let repo = init_repo_with_commit(temp_dir.path());
+        let sha = repo.head().unwrap().target().unwrap().to_string();
+
+        let env_of = |config: &str| {
+            fs::write(&config_path, config).unwrap();
+            let deployer =
+                ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default())
+                    .unwrap();
+            let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+            let mut cmd = deployer
+                .build_forge_command(&network_config, "Deploy.s.sol")
+                .unwrap();
+            deployer
+                .set_metadata_env(&mut cmd, temp_dir.path())
+                .unwrap();
+            cmd.get_envs()
+                .map(|(k, v)| {
+                    (
+                        k.to_string_lossy().to_string(),
+                        v.map(|v| v.to_string_lossy().to_string()),
+                    )
+                })
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+
+        let envs = env_of(CONFIG_CONTENT);
+        assert_eq!(envs["DEPLOY_GIT_SHA"].as_deref(), Some(sha.as_str()));
+        assert_eq!(envs["DEPLOY_NETWORK"].as_deref(), Some("sepolia"));
+        assert!(
+            envs["DEPLOY_TIMESTAMP"]
+                .as_ref()
+                .unwrap()
+                .parse::<u64>()
+                .is_ok()
+        );
+
+        let envs = env_of(&CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\nmetadata_env_prefix = \"BUILD_\"",
+        ));
+        assert!(envs.contains_key("BUILD_GIT_SHA"));
+        assert!(!envs.contains_key("DEPLOY_GIT_SHA"));
+
+        let envs = env_of(&CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\nmetadata_env = false",
+        ));
+        assert!(envs.is_empty());
+
+        // A variable the user set wins, with a warning
+        let config =
+            CONFIG_CONTENT.replace("[env.vars]", "[env.vars]\nDEPLOY_NETWORK = \"staging\"");
+        fs::write(&config_path, &config).unwrap();
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.load_env().ok();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let cmd = deployer
+            .deploy_command(&network_config, "Deploy.s.sol", temp_dir.path())
+            .unwrap();
+        let network = cmd
+            .get_envs()
+            .find(|(key, _)| *key == "DEPLOY_NETWORK")
+            .and_then(|(_, value)| value);
+        assert_eq!(network, Some(std::ffi::OsStr::new("staging")));
+
+        deployer.options.abort_on_warning = true;
+        let err = deployer
+            .deploy_command(&network_config, "Deploy.s.sol", temp_dir.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("DEPLOY_NETWORK is already set"));
+    }
+
+    #[test]
+    fn test_missing_broadcast() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT.replace(
+                "setup_command = \"echo 'test'\"",
+                "setup_command = \"echo 'test'\"\ncommand_wrapper = \"true\"",
+            ),
+        )
+        .unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let project_dir = temp_dir.path();
+
+        // `true forge script ...` exits 0 without broadcasting anything
+        let before = deployer.latest_broadcast_time(project_dir, &network_config);
+        let cmd = deployer
+            .build_forge_command(&network_config, "Deploy.s.sol")
+            .unwrap();
+        let run = deployer.execute_forge_command(cmd, false).unwrap();
+        assert_eq!(run.exit_code, Some(0));
+        assert!(run.log.is_none());
+        let problem = deployer
+            .missing_broadcast(project_dir, &network_config, before)
+            .unwrap();
+        assert!(problem.contains("no broadcast artifact"));
+
+        let run_dir = deployer.broadcast_run_dir(project_dir, &network_config);
+        fs::create_dir_all(&run_dir).unwrap();
+        let latest = run_dir.join("run-latest.json");
+        fs::write(&latest, r#"{"transactions":[]}"#).unwrap();
+        let problem = deployer
+            .missing_broadcast(project_dir, &network_config, None)
+            .unwrap();
+        assert!(problem.contains("no transactions"));
+
+        fs::write(&latest, r#"{"transactions":[{"hash":"0x01"}]}"#).unwrap();
+        assert_eq!(
+            deployer.missing_broadcast(project_dir, &network_config, None),
+            None
+        );
+
+        // A leftover artifact from an earlier run doesn't count
+        let before = deployer.latest_broadcast_time(project_dir, &network_config);
+        let problem = deployer
+            .missing_broadcast(project_dir, &network_config, before)
+            .unwrap();
+        assert!(problem.contains("was not updated"));
+    }
+
+    #[test]
+    fn test_wait_for_broadcast() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        fs::write(&config_path, CONFIG_CONTENT).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        let project_dir = temp_dir.path();
+        let run_dir = deployer.broadcast_run_dir(project_dir, &network_config);
+
+        deployer.config.project.broadcast_read_retries = 0;
+        let problem = deployer.wait_for_broadcast(project_dir, &network_config, None);
+        assert!(problem.unwrap().contains("no broadcast artifact"));
+
+        // The artifact shows up between the first and the second attempt
+        deployer.config.project.broadcast_read_retries = 1;
+        deployer.config.project.broadcast_read_delay_ms = 400;
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            fs::create_dir_all(&run_dir).unwrap();
+            fs::write(
+                run_dir.join("run-latest.json"),
+                r#"{"transactions":[{"hash":"0x01"}]}"#,
+            )
+            .unwrap();
+        });
+        assert_eq!(
+            deployer.wait_for_broadcast(project_dir, &network_config, None),
+            None
+        );
+        writer.join().unwrap();
+
+        // A file caught half-written is read again
+        let latest = deployer
+            .broadcast_run_dir(project_dir, &network_config)
+            .join("run-latest.json");
+        fs::write(&latest, r#"{"transactions":["#).unwrap();
+        let writer = {
+            let latest = latest.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                fs::write(&latest, broadcast::tests::BROADCAST_CONTENT).unwrap();
+            })
+        };
+        let contracts = deployer.recorded_contracts(&latest).unwrap();
+        writer.join().unwrap();
+        assert_eq!(contracts[0].name, "MyToken");
+    }
+
+    #[test]
+    fn test_watch_refusals() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let watch_error = |config: String| {
+            fs::write(&config_path, config).unwrap();
+            let options = DeployOptions {
+                network_override: Some("anvil".to_string()),
+                ..Default::default()
+            };
+            ContractDeployer::new(config_path.to_str().unwrap(), options)
+                .unwrap()
+                .watch()
+                .unwrap_err()
+                .to_string()
+        };
+        let with_network = |rpc_url: &str| {
+            CONFIG_CONTENT.replace(
+                "[networks.sepolia]",
+                &format!(
+                    "[networks.anvil]\nchain_id = 31337\nrpc_url = \"{}\"\nverify = false\n\n[networks.sepolia]",
+                    rpc_url
+                ),
+            )
+        };
+
+        let cloned = with_network("http://127.0.0.1:8545").replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\nrepo = \"https://github.com/example/token.git\"",
+        );
+        assert!(watch_error(cloned).contains("--watch needs a local project"));
+
+        // anvil's chain id on a public RPC isn't a local network
+        assert!(
+            watch_error(with_network("https://rpc.example.com"))
+                .contains("only runs against local networks")
+        );
+    }
+
+    #[test]
+    fn test_confirm_message() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let config = CONFIG_CONTENT.to_string()
+            + r#"
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/test"
+verify = true
+confirm_message = "⚠️ PRODUCTION — funds at risk"
+"#;
+        fs::write(&config_path, config).unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+
+        let mut out = Vec::new();
+        write_confirm_message(deployer.config.get_network("mainnet").unwrap(), &mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("⚠️ PRODUCTION — funds at risk")
+        );
+
+        let mut out = Vec::new();
+        write_confirm_message(deployer.config.get_network("sepolia").unwrap(), &mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_confirm_timeout_defaults_to_no() {
+        let answered = read_line_with_timeout(|| Ok("y\n".to_string()), Duration::from_secs(5));
+        assert_eq!(answered.unwrap(), Some("y\n".to_string()));
+
+        let unanswered = read_line_with_timeout(
+            || {
+                thread::sleep(Duration::from_secs(5));
+                Ok("y\n".to_string())
+            },
+            Duration::from_millis(50),
+        );
+        assert_eq!(unanswered.unwrap(), None);
+
+        let cancelled: anyhow::Error = crate::exit_code::Cancelled {
+            reason: "no answer within 1s".to_string(),
+        }
+        .into();
+        assert_eq!(
+            crate::exit_code::for_error(&cancelled),
+            crate::exit_code::CANCELLED
+        );
+    }
+
+    #[test]
+    fn test_network_selection_requires_tty() {
+        let config_content = CONFIG_CONTENT.replace("network = \"sepolia\"\n", "")
+            + r#"
+[networks.mainnet]
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/test"
+verify = true
+"#;
+        let config: DeploymentConfig = toml::from_str(&config_content).unwrap();
+        assert!(config.project.network.is_empty());
+
+        // Without a TTY (or with -y) this stays an error
+        let err = select_network(&config, false, &mut "1\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("available: mainnet, sepolia"));
+
+        // Interactively the numbered choice is used
+        assert_eq!(
+            select_network(&config, true, &mut "2\n".as_bytes()).unwrap(),
+            "sepolia"
+        );
+        assert!(select_network(&config, true, &mut "3\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_guard_redeploy() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let project_dir = temp_dir.path().join("project");
+        let config = CONFIG_CONTENT.replace(
+            "setup_command = \"echo 'test'\"",
+            "setup_command = \"echo 'test'\"\nguard_redeploy = true",
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        // Nothing deployed yet
+        deployer
+            .check_redeploy(&project_dir, &network_config)
+            .unwrap();
+
+        let latest = broadcast::run_dir(&project_dir, "broadcast", "Deploy.s.sol", 11155111)
+            .join("run-latest.json");
+        fs::create_dir_all(latest.parent().unwrap()).unwrap();
+        fs::write(&latest, broadcast::tests::BROADCAST_CONTENT).unwrap();
+
+        let err = deployer
+            .check_redeploy(&project_dir, &network_config)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("MyToken at 0x5fbdb2315678afecb367f032d93f642f64180aa3"));
+        assert!(err.contains("commit 4b825dc"));
+        assert!(err.contains("--redeploy"));
+
+        let options = DeployOptions {
+            redeploy: true,
+            ..Default::default()
+        };
+        let deployer = ContractDeployer::new(config_path.to_str().unwrap(), options).unwrap();
+        deployer
+            .check_redeploy(&project_dir, &network_config)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_collect_artifacts() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+        let project_dir = temp_dir.path().join("project");
+        let output_dir = temp_dir.path().join("artifacts");
+
+        fs::write(
+            &config_path,
+            CONFIG_CONTENT
+                .replace("/v2/test", "/v2/${ALCHEMY_API_KEY}")
+                .replace(
+                    "[env.vars]",
+                    "[env.vars]\nALCHEMY_API_KEY = \"abcdef0123456789abcdef\"",
+                ),
+        )
+        .unwrap();
+        let run_dir = broadcast::run_dir(&project_dir, "broadcast", "Deploy.s.sol", 11155111);
+        fs::create_dir_all(&run_dir).unwrap();
+        // An earlier run, left out of this run's artifacts
+        let earlier = fs::File::create(run_dir.join("run-100.json")).unwrap();
+        earlier
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+        let started = SystemTime::now() - Duration::from_secs(1);
+        fs::write(
+            run_dir.join("run-latest.json"),
+            broadcast::tests::BROADCAST_CONTENT,
+        )
+        .unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        deployer.load_env().unwrap();
+        deployer.config = deployer.raw_config.expand_with(&deployer.env);
+        let network_config = deployer.config.get_network("sepolia").unwrap().clone();
+
+        let run_dir = deployer
+            .collect_artifacts(
+                &project_dir,
+                &output_dir,
+                &network_config,
+                started,
+                Some(b"Script ran successfully.\n"),
+            )
+            .unwrap();
+
+        assert!(run_dir.starts_with(&output_dir));
+        assert!(run_dir.join("broadcast/run-latest.json").exists());
+        assert!(!run_dir.join("broadcast/run-100.json").exists());
+        assert_eq!(
+            fs::read_to_string(run_dir.join("forge.log")).unwrap(),
+            "Script ran successfully.\n"
+        );
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(run_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["network"], "sepolia");
+        assert_eq!(manifest["contracts"][0]["name"], "MyToken");
+
+        let config = fs::read_to_string(run_dir.join("config.toml")).unwrap();
+        assert!(config.contains("KEYSTORE_ACCOUNT = \"deployer\""));
+        assert!(config.contains("KEYSTORE_PASSWORD = \"********\""));
+        assert!(config.contains(&format!(
+            "rpc_url = \"https://eth-sepolia.g.alchemy.com/v2/{}\"",
+            redact::MASK
+        )));
+        assert!(!config.contains("abcdef0123456789abcdef"));
+    }
+
+    #[test]
+    fn test_min_balance_check() {
+        // 0.05 ETH
+        let balance = 50_000_000_000_000_000;
+
+        assert!(is_below_min_balance(balance, 0.1));
+        assert!(!is_below_min_balance(balance, 0.05));
+        assert!(!is_below_min_balance(balance, 0.01));
+        assert!(!is_below_min_balance(0, 0.0));
+        assert!(is_below_min_balance(0, 0.000000000000000001));
+        assert!(is_below_min_balance(999_999_999_999_999_999, 1.0));
+        assert!(!is_below_min_balance(1_000_000_000_000_000_000, 1.0));
+    }
+
+    #[test]
+    fn test_sync_problem() {
+        assert_eq!(sync_problem(false, 12, 60), None);
+        assert!(sync_problem(true, 0, 60).unwrap().contains("still syncing"));
+        assert!(
+            sync_problem(false, 600, 60)
+                .unwrap()
+                .contains("600s behind")
+        );
+    }
+
+    #[test]
+    fn test_require_synced_unreachable_node() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let content =
+            CONFIG_CONTENT.replace("verify = true", "verify = true\nrequire_synced = true");
+        fs::write(&config_path, content).unwrap();
+
+        let mut deployer =
+            ContractDeployer::new(config_path.to_str().unwrap(), DeployOptions::default()).unwrap();
+        let mut network_config = deployer.config.get_network("sepolia").unwrap().clone();
+        assert!(network_config.require_synced);
+        network_config.rpc_url = "http://127.0.0.1:1".to_string();
+
+        let err = deployer.check_node_synced(&network_config).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+
+        deployer.options.force = true;
+        assert!(deployer.check_node_synced(&network_config).is_ok());
+
+        network_config.require_synced = false;
+        deployer.options.force = false;
+        assert!(deployer.check_node_synced(&network_config).is_ok());
     }
 }
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::rpc::RpcClient;
+
+/// ENS registry address (same on every chain ENS is deployed to)
+const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+
+/// `resolver(bytes32)` on the ENS registry
+const RESOLVER_SELECTOR: &str = "0178b8bf";
+
+/// `addr(bytes32)` on a public resolver
+const ADDR_SELECTOR: &str = "3b3b57de";
+
+/// Chains with an ENS registry: mainnet, sepolia and holesky
+const ENS_CHAIN_IDS: &[u64] = &[1, 11155111, 17000];
+
+/// Resolves ENS names to addresses
+pub trait NameResolver {
+    fn resolve_name(&self, name: &str) -> Result<String>;
+}
+
+/// Resolver that queries the ENS registry through a JSON-RPC endpoint
+pub struct EnsResolver {
+    client: RpcClient,
+}
+
+impl EnsResolver {
+    pub fn new(client: RpcClient) -> Self {
+        EnsResolver { client }
+    }
+}
+
+impl NameResolver for EnsResolver {
+    fn resolve_name(&self, name: &str) -> Result<String> {
+        let node = hex(&namehash(name));
+
+        let resolver = word_to_address(
+            &self
+                .client
+                .call(ENS_REGISTRY, &format!("0x{}{}", RESOLVER_SELECTOR, node))?,
+        )?;
+        if is_zero_address(&resolver) {
+            anyhow::bail!("ENS name {} has no resolver", name);
+        }
+
+        let address = word_to_address(
+            &self
+                .client
+                .call(&resolver, &format!("0x{}{}", ADDR_SELECTOR, node))?,
+        )?;
+        if is_zero_address(&address) {
+            anyhow::bail!("ENS name {} does not resolve to an address", name);
+        }
+
+        Ok(address)
+    }
+}
+
+/// Resolve a sender that may be an ENS name into a plain address.
+///
+/// Addresses are returned unchanged. ENS names are only resolved on chains
+/// with an ENS registry; anything else must already be an address.
+pub fn resolve_sender(sender: &str, chain_id: u64, resolver: &dyn NameResolver) -> Result<String> {
+    if is_address(sender) {
+        return Ok(sender.to_string());
+    }
+
+    if !ENS_CHAIN_IDS.contains(&chain_id) {
+        anyhow::bail!(
+            "Sender '{}' is not an address and ENS is not available on chain {}",
+            sender,
+            chain_id
+        );
+    }
+
+    resolver
+        .resolve_name(sender)
+        .context(format!("Failed to resolve ENS name '{}'", sender))
+}
+
+/// Check whether a value is a `0x`-prefixed 20-byte hex address
+pub fn is_address(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Compute the EIP-137 namehash of an ENS name
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+
+    if name.is_empty() {
+        return node;
+    }
+
+    for label in name.rsplit('.') {
+        let label_hash = keccak256(label.as_bytes());
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&node);
+        buf[32..].copy_from_slice(&label_hash);
+        node = keccak256(&buf);
+    }
+
+    node
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Extract the address from the last 20 bytes of an ABI-encoded word
+fn word_to_address(word: &str) -> Result<String> {
+    let digits = word.strip_prefix("0x").unwrap_or(word);
+    if digits.len() < 64 {
+        anyhow::bail!("Unexpected eth_call result: {}", word);
+    }
+
+    Ok(format!("0x{}", &digits[24..64]))
+}
+
+fn is_zero_address(address: &str) -> bool {
+    address.trim_start_matches("0x").chars().all(|c| c == '0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockResolver;
+
+    impl NameResolver for MockResolver {
+        fn resolve_name(&self, name: &str) -> Result<String> {
+            match name {
+                "deployer.eth" => Ok("0xaa31349a2ef4a37dc4dd742e3b0e32182f524a6a".to_string()),
+                _ => anyhow::bail!("unknown name"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(hex(&namehash("")), "0".repeat(64));
+        assert_eq!(
+            hex(&namehash("eth")),
+            "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+    }
+
+    #[test]
+    fn test_resolve_sender() {
+        let address = "0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A";
+
+        // Addresses pass through on any chain
+        assert_eq!(
+            resolve_sender(address, 31337, &MockResolver).unwrap(),
+            address
+        );
+
+        // ENS names resolve on mainnet
+        assert_eq!(
+            resolve_sender("deployer.eth", 1, &MockResolver).unwrap(),
+            "0xaa31349a2ef4a37dc4dd742e3b0e32182f524a6a"
+        );
+        assert!(resolve_sender("missing.eth", 1, &MockResolver).is_err());
+
+        // ENS names are rejected on chains without ENS
+        assert!(resolve_sender("deployer.eth", 31337, &MockResolver).is_err());
+    }
+}
@@ -1,56 +1,308 @@
-use crate::config::EnvConfig;
+use crate::config::{EncryptedEnvConfig, EnvConfig, VaultConfig, VaultSecret};
+use crate::redact;
 use anyhow::{Context, Result};
 use colored::*;
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where the value of a variable came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarSource {
+    /// `[env.vars]` in the config
+    Config,
+    /// An `env.load_files` entry
+    File(PathBuf),
+    /// A file passed with `--env-file`
+    CliFile(PathBuf),
+    /// An age-encrypted `[env.encrypted]` file
+    #[cfg(feature = "age")]
+    Encrypted(PathBuf),
+    #[cfg(feature = "vault")]
+    Vault,
+    #[cfg(feature = "aws")]
+    AwsSecrets,
+    System,
+    /// Set by the deployer itself, e.g. a sender resolved from ENS
+    Computed,
+}
+
+impl fmt::Display for VarSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VarSource::Config => write!(f, "config [env.vars]"),
+            VarSource::File(path) => write!(f, "{}", path.display()),
+            VarSource::CliFile(path) => write!(f, "{} (--env-file)", path.display()),
+            #[cfg(feature = "age")]
+            VarSource::Encrypted(path) => write!(f, "{} (encrypted)", path.display()),
+            #[cfg(feature = "vault")]
+            VarSource::Vault => write!(f, "vault"),
+            #[cfg(feature = "aws")]
+            VarSource::AwsSecrets => write!(f, "aws secrets manager"),
+            VarSource::System => write!(f, "system"),
+            VarSource::Computed => write!(f, "computed"),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Environment {
     vars: HashMap<String, String>,
+    /// Source of each loaded variable, updated whenever a later source overrides it
+    sources: HashMap<String, VarSource>,
+    /// Whether `validate_required` treats empty values as missing
+    reject_empty: bool,
+    /// Required variables exempt from `reject_empty`
+    allow_empty: Vec<String>,
+    /// Problems found while loading that don't stop it, see [`Environment::take_warnings`]
+    warnings: Vec<String>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Environment {
             vars: HashMap::new(),
+            sources: HashMap::new(),
+            reject_empty: true,
+            allow_empty: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
-    pub fn load_from_config(&mut self, config: &EnvConfig) -> Result<()> {
+    fn insert(&mut self, key: String, value: String, source: VarSource) {
+        self.sources.insert(key.clone(), source);
+        self.vars.insert(key, value);
+    }
+
+    /// Load variables from the config, its env files, any `--env-file` paths and the system.
+    ///
+    /// Precedence (lowest to highest): `[env.vars]`, `load_files`, CLI env files,
+    /// `[env.encrypted]`, `[env.vault]` secrets, `[env.aws_secrets]`, system env.
+    ///
+    /// Relative `load_files` and `[env.encrypted]` paths resolve against
+    /// `base_dir`; CLI env files against the current directory.
+    pub fn load_from_config(
+        &mut self,
+        config: &EnvConfig,
+        base_dir: &Path,
+        cli_env_files: &[String],
+    ) -> Result<()> {
+        self.reject_empty = config.reject_empty_required;
+        self.allow_empty = config.allow_empty.clone();
+
         // Load environment files
         // Load additional variables from config
         for (key, value) in config.vars.iter() {
-            self.vars.insert(key.clone(), value.clone());
+            self.insert(key.clone(), value.clone(), VarSource::Config);
         }
 
         if let Some(load_files) = &config.load_files {
             for file_path in load_files {
-                let full_path = Path::new(&file_path);
+                let full_path = base_dir.join(file_path);
                 if full_path.exists() {
-                    self.load_env_file(full_path)?;
+                    self.load_env_file(&full_path, VarSource::File(full_path.clone()))?;
                 } else {
-                    println!(
-                        "Warning: Env file {} does not exist",
-                        full_path.display().to_string().yellow()
-                    );
+                    self.warnings
+                        .push(format!("Env file {} does not exist", full_path.display()));
                 }
             }
         }
 
+        // Files passed on the command line must exist and override config files
+        for file_path in cli_env_files {
+            let full_path = Path::new(file_path);
+            if !full_path.exists() {
+                anyhow::bail!("Env file {} does not exist", full_path.display());
+            }
+            self.load_env_file(full_path, VarSource::CliFile(full_path.to_path_buf()))?;
+        }
+
+        if let Some(encrypted) = &config.encrypted {
+            self.load_encrypted_file(encrypted, base_dir)?;
+        }
+
+        // The secret providers read their settings, and tokens named there, with
+        // `${VAR}` references already expanded
+        if config.vault.is_some() || config.aws_secrets.is_some() {
+            self.expand_config_vars(&config.vars, Self::expand_with_system);
+        }
+
+        if let Some(vault) = &config.vault {
+            let vault = VaultConfig {
+                address: vault.address.as_deref().map(|a| self.expand_with_system(a)),
+                token_var: self.expand_with_system(&vault.token_var),
+                secrets: vault
+                    .secrets
+                    .iter()
+                    .map(|secret| VaultSecret {
+                        path: self.expand_with_system(&secret.path),
+                        key: self.expand_with_system(&secret.key),
+                        var: secret.var.clone(),
+                    })
+                    .collect(),
+            };
+            self.load_vault_secrets(&vault)?;
+        }
+
+        if let Some(aws_secrets) = &config.aws_secrets {
+            let aws_secrets = aws_secrets
+                .iter()
+                .map(|(var, reference)| (var.clone(), self.expand_with_system(reference)))
+                .collect();
+            self.load_aws_secrets(&aws_secrets)?;
+        }
+
         // Load system environment variables (override file variables)
         for (key, value) in env::vars() {
-            self.vars.insert(key, value);
+            self.insert(key, value, VarSource::System);
+        }
+
+        self.expand_config_vars(&config.vars, Self::expand_variables);
+
+        Ok(())
+    }
+
+    /// Expand `${VAR}` references in `[env.vars]` values against the combined environment.
+    ///
+    /// Values overridden by a higher-precedence source are left untouched. Each
+    /// value is expanded from its raw form in a single pass over a snapshot, like
+    /// `expand_variables`, so references between config vars can't loop and a
+    /// second expansion sees the sources loaded since the first.
+    fn expand_config_vars(
+        &mut self,
+        config_vars: &HashMap<String, String>,
+        expand: fn(&Self, &str) -> String,
+    ) {
+        let expanded: Vec<(String, String)> = config_vars
+            .iter()
+            .filter(|(key, _)| self.sources.get(*key) == Some(&VarSource::Config))
+            .map(|(key, value)| (key.clone(), expand(self, value)))
+            .collect();
+
+        self.vars.extend(expanded);
+    }
+
+    /// `text` with `${VAR}` references expanded against the system environment,
+    /// then the variables loaded so far; for settings read before the system
+    /// environment is merged in
+    fn expand_with_system(&self, text: &str) -> String {
+        substitute(text, |var_name| {
+            env::var(var_name)
+                .ok()
+                .or_else(|| self.vars.get(var_name).cloned())
+                .unwrap_or_default()
+        })
+    }
+
+    #[cfg(feature = "age")]
+    fn load_encrypted_file(
+        &mut self,
+        encrypted: &EncryptedEnvConfig,
+        base_dir: &Path,
+    ) -> Result<()> {
+        let identity = match (&encrypted.identity_var, &encrypted.identity_file) {
+            (Some(var), _) => env::var(var)
+                .ok()
+                .or_else(|| self.vars.get(var).cloned())
+                .context(format!("age identity variable {} is not set", var))?,
+            (None, Some(file)) => fs::read_to_string(base_dir.join(file))
+                .context(format!("Failed to read age identity file {}", file))?,
+            (None, None) => anyhow::bail!(
+                "[env.encrypted] needs identity_var or identity_file to decrypt {}",
+                encrypted.file
+            ),
+        };
+
+        let path = base_dir.join(&encrypted.file);
+        let content = crate::age::decrypt_file(&path, &identity)?;
+        let count = self.load_env_content(&content, VarSource::Encrypted(path.to_path_buf()));
+        println!(
+            "Loaded {} variable(s) from {}",
+            count.to_string().green(),
+            encrypted.file
+        );
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "age"))]
+    fn load_encrypted_file(
+        &mut self,
+        _encrypted: &EncryptedEnvConfig,
+        _base_dir: &Path,
+    ) -> Result<()> {
+        anyhow::bail!(
+            "[env.encrypted] requires contract-deployer to be built with the `age` feature"
+        )
+    }
+
+    #[cfg(feature = "vault")]
+    fn load_vault_secrets(&mut self, vault: &VaultConfig) -> Result<()> {
+        let lookup = |key: &str| env::var(key).ok().or_else(|| self.vars.get(key).cloned());
+
+        let address = vault
+            .address
+            .clone()
+            .or_else(|| lookup("VAULT_ADDR"))
+            .context("Vault address is not configured (set env.vault.address or VAULT_ADDR)")?;
+        let token = lookup(&vault.token_var).context(format!(
+            "Vault token variable {} is not set",
+            vault.token_var
+        ))?;
+
+        let secrets = crate::vault::fetch_secrets(vault, &address, &token)?;
+        println!(
+            "Loaded {} secret(s) from Vault",
+            secrets.len().to_string().green()
+        );
+        for (key, value) in secrets {
+            self.insert(key, value, VarSource::Vault);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "vault"))]
+    fn load_vault_secrets(&mut self, _vault: &VaultConfig) -> Result<()> {
+        anyhow::bail!("[env.vault] requires contract-deployer to be built with the `vault` feature")
+    }
+
+    #[cfg(feature = "aws")]
+    fn load_aws_secrets(&mut self, mappings: &HashMap<String, String>) -> Result<()> {
+        let secrets = crate::aws::fetch_secrets(mappings)?;
+        println!(
+            "Loaded {} secret(s) from AWS Secrets Manager",
+            secrets.len().to_string().green()
+        );
+        for (key, value) in secrets {
+            self.insert(key, value, VarSource::AwsSecrets);
         }
 
         Ok(())
     }
 
-    fn load_env_file(&mut self, file_path: &Path) -> Result<()> {
+    #[cfg(not(feature = "aws"))]
+    fn load_aws_secrets(&mut self, _mappings: &HashMap<String, String>) -> Result<()> {
+        anyhow::bail!(
+            "[env.aws_secrets] requires contract-deployer to be built with the `aws` feature"
+        )
+    }
+
+    fn load_env_file(&mut self, file_path: &Path, source: VarSource) -> Result<()> {
         let content = fs::read_to_string(file_path)
             .context(format!("Failed to read env file: {:?}", file_path))?;
 
+        self.load_env_content(&content, source);
+        Ok(())
+    }
+
+    /// Parse `KEY=VALUE` lines, skipping blanks and comments, and return how many were set
+    fn load_env_content(&mut self, content: &str, source: VarSource) -> usize {
+        let mut count = 0;
+
         for line in content.lines() {
             let line = line.trim();
             if line.is_empty() || line.starts_with('#') {
@@ -59,46 +311,170 @@ impl Environment {
 
             if let Some((key, value)) = line.split_once('=') {
                 let key = key.trim();
-                let value = value.trim().trim_matches('"').trim_matches('\'');
-                self.vars.insert(key.to_string(), value.to_string());
+                self.insert(key.to_string(), unquote(value.trim()), source.clone());
+                count += 1;
             }
         }
 
-        Ok(())
+        count
     }
 
     pub fn get(&self, key: &str) -> Option<&String> {
         self.vars.get(key)
     }
 
+    /// Warnings collected while loading, for the caller to report
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Set a value computed at runtime, recorded as [`VarSource::Computed`]
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.insert(key.to_string(), value.to_string(), VarSource::Computed);
+    }
+
+    /// Where the current value of `key` came from
+    pub fn get_source(&self, key: &str) -> Option<&VarSource> {
+        self.sources.get(key)
+    }
+
+    /// `KEY (from <source>)`, or just `KEY` when the source is unknown, for
+    /// messages about a variable's value
+    pub fn describe(&self, key: &str) -> String {
+        match self.get_source(key) {
+            Some(source) => format!("{} (from {})", key, source),
+            None => key.to_string(),
+        }
+    }
+
     pub fn get_vars(&self) -> &HashMap<String, String> {
         &self.vars
     }
 
-    pub fn expand_variables(&self, text: &str) -> String {
-        let mut result = text.to_string();
-
-        // Replace ${VAR} patterns
-        while let Some(start) = result.find("${") {
-            if let Some(end) = result[start..].find('}') {
-                let var_name = &result[start + 2..start + end];
-                let empty_string = "".to_string();
-                let replacement = self.get(var_name).unwrap_or(&empty_string);
-                result.replace_range(start..start + end + 1, replacement);
+    /// Every variable with its value and source, sorted by name
+    pub fn provenance(&self) -> Vec<(&str, &str, Option<&VarSource>)> {
+        let mut entries: Vec<_> = self
+            .vars
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str(), self.sources.get(key)))
+            .collect();
+        entries.sort_by_key(|(key, _, _)| *key);
+        entries
+    }
+
+    /// Name, source and value of every variable as `--print-env` shows them:
+    /// secret-looking values masked, credentials and keys in URLs redacted
+    pub fn printable(&self) -> Vec<(&str, String, String)> {
+        self.provenance()
+            .into_iter()
+            .map(|(key, value, source)| {
+                let source = source.map_or_else(|| "-".to_string(), |s| s.to_string());
+                let value = if redact::is_sensitive_key(key) {
+                    redact::MASK.to_string()
+                } else {
+                    redact::redact_rpc_url(value)
+                };
+                (key, source, value)
+            })
+            .collect()
+    }
+
+    /// The resolved variables as a sourceable file: `export KEY='value'` lines,
+    /// each preceded by a comment naming its source.
+    ///
+    /// System variables are left out since the shell already has them. Secrets
+    /// are listed as comments only and keys in URLs are masked, unless
+    /// `include_secrets` is set.
+    pub fn export(&self, include_secrets: bool) -> String {
+        let mut out = String::new();
+        for (key, value, source) in self.provenance() {
+            if matches!(source, Some(VarSource::System)) {
+                continue;
+            }
+
+            let source = source.map_or_else(|| "-".to_string(), |s| s.to_string());
+            if redact::is_sensitive_key(key) && !include_secrets {
+                out.push_str(&format!(
+                    "# {}: {} (secret omitted, use --include-secrets)\n",
+                    key, source
+                ));
+                continue;
+            }
+
+            let value = if value.contains("://") && !include_secrets {
+                redact::redact_rpc_url(value)
+            } else {
+                value.to_string()
+            };
+            if value.contains(redact::MASK) && !include_secrets {
+                out.push_str(&format!(
+                    "# {}: {} (key masked, use --include-secrets)\n",
+                    key, source
+                ));
             } else {
-                break;
+                out.push_str(&format!("# {}: {}\n", key, source));
             }
+            out.push_str(&format!(
+                "export {}='{}'\n",
+                key,
+                value.replace('\'', "'\\''")
+            ));
         }
+        out
+    }
 
-        result
+    /// Replace `${VAR}` references with their values (undefined variables become empty).
+    ///
+    /// `$${VAR}` is an escape that produces a literal `${VAR}`. Substituted
+    /// values are not expanded again, so self-referencing values can't loop.
+    pub fn expand_variables(&self, text: &str) -> String {
+        substitute(text, |var_name| {
+            self.get(var_name).cloned().unwrap_or_default()
+        })
     }
 
+    /// Names referenced as `${VAR}` in `text` that are not defined
+    pub fn undefined_variables(&self, text: &str) -> Vec<String> {
+        let mut undefined = Vec::new();
+
+        substitute(text, |var_name| {
+            if !self.vars.contains_key(var_name) {
+                undefined.push(var_name.to_string());
+            }
+            String::new()
+        });
+
+        undefined
+    }
+
+    /// Whether `validate_required` would report `var` as missing
+    pub fn is_missing(&self, var: &str) -> bool {
+        match self.vars.get(var) {
+            None => true,
+            Some(value) => self.rejects_empty(var, value),
+        }
+    }
+
+    fn rejects_empty(&self, var: &str, value: &str) -> bool {
+        value.trim().is_empty()
+            && self.reject_empty
+            && !self.allow_empty.iter().any(|allowed| allowed == var)
+    }
+
+    /// Fail when a required variable is missing.
+    ///
+    /// Unless `env.reject_empty_required = false`, an empty value counts as
+    /// missing too, except for names listed in `env.allow_empty`.
     pub fn validate_required(&self, required_vars: &[&str]) -> Result<()> {
         let mut missing = Vec::new();
 
         for &var in required_vars {
-            if !self.vars.contains_key(var) {
-                missing.push(var);
+            match self.vars.get(var) {
+                None => missing.push(var.to_string()),
+                Some(value) if self.rejects_empty(var, value) => {
+                    missing.push(format!("{} is empty", self.describe(var)));
+                }
+                Some(_) => {}
             }
         }
 
@@ -113,12 +489,113 @@ impl Environment {
     }
 }
 
+/// Value of an env file line: `"..."` has `\"` and `\\` unescaped, `'...'` is
+/// taken literally, and stray quotes around anything else are dropped
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut result = String::with_capacity(value.len());
+        let mut chars = value[1..value.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some(next @ ('"' | '\\'))) => {
+                    result.push(next);
+                    chars.next();
+                }
+                _ => result.push(c),
+            }
+        }
+        return result;
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].to_string();
+    }
+    value.trim_matches('"').trim_matches('\'').to_string()
+}
+
+/// Write `content` to `path`, readable by the owner only since env files hold secrets
+pub fn write_private(path: &Path, content: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                .context(format!("Failed to restrict {}", path.display()))?;
+        }
+    }
+    let mut file = options
+        .open(path)
+        .context(format!("Failed to write {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .context(format!("Failed to write {}", path.display()))
+}
+
+/// Scan `text` once, replacing each `${VAR}` with `replace(VAR)` and each
+/// escaped `$${VAR}` with a literal `${VAR}`
+fn substitute(text: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        let (escaped, reference) = if tail.starts_with("$${") {
+            (true, &tail[1..])
+        } else {
+            (false, tail)
+        };
+
+        if !reference.starts_with("${") {
+            result.push('$');
+            rest = &tail[1..];
+            continue;
+        }
+
+        let Some(end) = reference.find('}') else {
+            // Unterminated reference, keep the remainder as-is
+            result.push_str(tail);
+            return result;
+        };
+
+        if escaped {
+            result.push_str(&reference[..=end]);
+        } else {
+            result.push_str(&replace(&reference[2..end]));
+        }
+        rest = &reference[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
 
+    /// An env config with the given vars and files and every other field at its serde default
+    pub(crate) fn env_config(
+        vars: HashMap<String, String>,
+        load_files: Option<Vec<String>>,
+    ) -> EnvConfig {
+        EnvConfig {
+            vars,
+            load_files,
+            vault: None,
+            aws_secrets: None,
+            encrypted: None,
+            reject_empty_required: true,
+            allow_empty: Vec::new(),
+            forge_prefix: None,
+        }
+    }
+
     #[test]
     fn test_env_variable_expansion() {
         let mut env = Environment::new();
@@ -131,6 +608,23 @@ mod tests {
         assert_eq!(expanded, "https://eth-sepolia.g.alchemy.com/v2/test123");
     }
 
+    #[test]
+    fn test_escaped_variable_is_literal() {
+        let mut env = Environment::new();
+        env.vars
+            .insert("API_KEY".to_string(), "test123".to_string());
+        env.vars.insert("LOOP".to_string(), "${LOOP}".to_string());
+
+        assert_eq!(
+            env.expand_variables("https://rpc.example/$${API_KEY}/${API_KEY}"),
+            "https://rpc.example/${API_KEY}/test123"
+        );
+        assert_eq!(env.expand_variables("cost: $5, ${MISSING}."), "cost: $5, .");
+        assert_eq!(env.expand_variables("${LOOP}"), "${LOOP}");
+        assert!(env.undefined_variables("$${MISSING}").is_empty());
+        assert_eq!(env.undefined_variables("${MISSING}"), vec!["MISSING"]);
+    }
+
     #[test]
     fn test_env_file_loading() {
         let temp_dir = tempdir().unwrap();
@@ -143,12 +637,389 @@ mod tests {
         .unwrap();
 
         let mut env = Environment::new();
-        env.load_env_file(&env_file).unwrap();
+        env.load_env_file(&env_file, VarSource::File(env_file.clone()))
+            .unwrap();
 
         assert_eq!(env.get("TEST_KEY"), Some(&"test_value".to_string()));
         assert_eq!(env.get("ANOTHER_KEY"), Some(&"another_value".to_string()));
     }
 
+    #[test]
+    fn test_cli_env_file_precedence() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join(".env");
+        let cli_file = temp_dir.path().join(".env.cli");
+
+        fs::write(&config_file, "CD_TEST_CLI_PRECEDENCE=from_config_file").unwrap();
+        fs::write(&cli_file, "CD_TEST_CLI_PRECEDENCE=from_cli_file").unwrap();
+
+        let config = env_config(
+            HashMap::new(),
+            Some(vec![config_file.to_string_lossy().to_string()]),
+        );
+
+        let mut env = Environment::new();
+        env.load_from_config(
+            &config,
+            Path::new(""),
+            &[cli_file.to_string_lossy().to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            env.get("CD_TEST_CLI_PRECEDENCE"),
+            Some(&"from_cli_file".to_string())
+        );
+
+        // Missing files passed on the command line are an error
+        let missing = temp_dir.path().join("missing.env");
+        let mut env = Environment::new();
+        assert!(
+            env.load_from_config(
+                &config,
+                Path::new(""),
+                &[missing.to_string_lossy().to_string()]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_vars_expand_against_env_files() {
+        let temp_dir = tempdir().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        fs::write(&env_file, "CD_TEST_EXPAND_KEY=secret123").unwrap();
+
+        let vars = [
+            (
+                "CD_TEST_EXPAND_URL",
+                "https://${CD_TEST_EXPAND_NETWORK}.example/${CD_TEST_EXPAND_KEY}",
+            ),
+            ("CD_TEST_EXPAND_NETWORK", "sepolia"),
+            ("CD_TEST_EXPAND_LOOP", "${CD_TEST_EXPAND_LOOP}"),
+        ];
+        let config = env_config(
+            vars.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            Some(vec![env_file.to_string_lossy().to_string()]),
+        );
+
+        let mut env = Environment::new();
+        env.load_from_config(&config, Path::new(""), &[]).unwrap();
+        assert_eq!(
+            env.get("CD_TEST_EXPAND_URL"),
+            Some(&"https://sepolia.example/secret123".to_string())
+        );
+        assert_eq!(
+            env.get("CD_TEST_EXPAND_LOOP"),
+            Some(&"${CD_TEST_EXPAND_LOOP}".to_string())
+        );
+    }
+
+    #[cfg(feature = "vault")]
+    #[test]
+    fn test_vault_settings_expand_before_fetching() {
+        let (url, server) =
+            crate::test_http::serve_once(r#"{"data":{"data":{"password":"hunter2"}}}"#);
+        let temp_dir = tempdir().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        fs::write(&env_file, format!("CD_TEST_VAULT_FILE_URL={}", url)).unwrap();
+
+        let vars = [
+            ("CD_TEST_VAULT_URL", "${CD_TEST_VAULT_FILE_URL}"),
+            ("CD_TEST_VAULT_APP", "token"),
+            ("CD_TEST_VAULT_TOKEN", "${CD_TEST_VAULT_APP}-token"),
+            ("CD_TEST_VAULT_DSN", "pw=${CD_TEST_VAULT_PASSWORD}"),
+        ];
+        let config = EnvConfig {
+            vault: Some(VaultConfig {
+                address: Some("${CD_TEST_VAULT_URL}".to_string()),
+                token_var: "CD_TEST_VAULT_TOKEN".to_string(),
+                secrets: vec![VaultSecret {
+                    path: "secret/data/${CD_TEST_VAULT_APP}".to_string(),
+                    key: "password".to_string(),
+                    var: "CD_TEST_VAULT_PASSWORD".to_string(),
+                }],
+            }),
+            ..env_config(
+                vars.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                Some(vec![env_file.to_string_lossy().to_string()]),
+            )
+        };
+
+        let mut env = Environment::new();
+        env.load_from_config(&config, Path::new(""), &[]).unwrap();
+        assert_eq!(
+            env.get("CD_TEST_VAULT_PASSWORD"),
+            Some(&"hunter2".to_string())
+        );
+        assert_eq!(
+            env.get("CD_TEST_VAULT_TOKEN"),
+            Some(&"token-token".to_string())
+        );
+        // Config vars are expanded again once the secrets are loaded
+        assert_eq!(
+            env.get("CD_TEST_VAULT_DSN"),
+            Some(&"pw=hunter2".to_string())
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_export_format() {
+        let mut env = Environment::new();
+        env.insert(
+            "RPC_KEY".to_string(),
+            "it's".to_string(),
+            VarSource::File(PathBuf::from(".env")),
+        );
+        env.insert(
+            "KEYSTORE_PASSWORD".to_string(),
+            "hunter2".to_string(),
+            VarSource::Config,
+        );
+        env.insert("HOME".to_string(), "/root".to_string(), VarSource::System);
+        env.set("BROADCAST_ACCOUNT", "0xabc");
+        env.insert(
+            "MAINNET_RPC".to_string(),
+            "https://eth.example/v2/abcdef0123456789abcdef".to_string(),
+            VarSource::Config,
+        );
+
+        assert_eq!(
+            env.export(false),
+            format!(
+                "# BROADCAST_ACCOUNT: computed\n\
+                 export BROADCAST_ACCOUNT='0xabc'\n\
+                 # KEYSTORE_PASSWORD: config [env.vars] (secret omitted, use --include-secrets)\n\
+                 # MAINNET_RPC: config [env.vars] (key masked, use --include-secrets)\n\
+                 export MAINNET_RPC='https://eth.example/v2/{}'\n\
+                 # RPC_KEY: .env\n\
+                 export RPC_KEY='it'\\''s'\n",
+                redact::MASK
+            )
+        );
+        let with_secrets = env.export(true);
+        assert!(with_secrets.contains(
+            "# KEYSTORE_PASSWORD: config [env.vars]\nexport KEYSTORE_PASSWORD='hunter2'\n"
+        ));
+        assert!(
+            with_secrets
+                .contains("export MAINNET_RPC='https://eth.example/v2/abcdef0123456789abcdef'\n")
+        );
+    }
+
+    #[test]
+    fn test_provenance_tracks_winning_source() {
+        let temp_dir = tempdir().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        fs::write(&env_file, "CD_TEST_PROVENANCE_BOTH=from_file").unwrap();
+
+        let config = env_config(
+            HashMap::from([
+                (
+                    "CD_TEST_PROVENANCE_BOTH".to_string(),
+                    "from_config".to_string(),
+                ),
+                (
+                    "CD_TEST_PROVENANCE_CONFIG".to_string(),
+                    "from_config".to_string(),
+                ),
+                (
+                    "CD_TEST_PROVENANCE_RPC_URL".to_string(),
+                    "https://eth-sepolia.g.alchemy.com/v2/abcdef0123456789abcdef".to_string(),
+                ),
+            ]),
+            Some(vec![env_file.to_string_lossy().to_string()]),
+        );
+
+        let mut env = Environment::new();
+        env.load_from_config(&config, Path::new(""), &[]).unwrap();
+
+        let provenance = env.provenance();
+        let source_of = |name: &str| {
+            provenance
+                .iter()
+                .find(|(key, _, _)| *key == name)
+                .and_then(|(_, _, source)| source.cloned())
+        };
+        assert_eq!(
+            source_of("CD_TEST_PROVENANCE_BOTH"),
+            Some(VarSource::File(env_file.clone()))
+        );
+        assert_eq!(
+            source_of("CD_TEST_PROVENANCE_CONFIG"),
+            Some(VarSource::Config)
+        );
+
+        // --print-env masks the key of an RPC URL whose name doesn't look secret
+        let printed = env.printable();
+        let (_, source, value) = printed
+            .iter()
+            .find(|(key, _, _)| *key == "CD_TEST_PROVENANCE_RPC_URL")
+            .unwrap();
+        assert_eq!(source, "config [env.vars]");
+        assert_eq!(
+            value,
+            &format!("https://eth-sepolia.g.alchemy.com/v2/{}", redact::MASK)
+        );
+    }
+
+    #[test]
+    fn test_get_source_after_overrides() {
+        let temp_dir = tempdir().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        let cli_file = temp_dir.path().join(".env.cli");
+        fs::write(
+            &env_file,
+            "CD_TEST_SOURCE_FILE=file\nCD_TEST_SOURCE_CLI=file\nCD_TEST_SOURCE_EMPTY=",
+        )
+        .unwrap();
+        fs::write(&cli_file, "CD_TEST_SOURCE_CLI=cli").unwrap();
+
+        let config = env_config(
+            HashMap::from([
+                ("CD_TEST_SOURCE_FILE".to_string(), "config".to_string()),
+                ("CD_TEST_SOURCE_CLI".to_string(), "config".to_string()),
+                ("CD_TEST_SOURCE_COMPUTED".to_string(), "config".to_string()),
+                ("PATH".to_string(), "config".to_string()),
+            ]),
+            Some(vec![env_file.to_string_lossy().to_string()]),
+        );
+
+        let mut env = Environment::new();
+        env.load_from_config(
+            &config,
+            Path::new(""),
+            &[cli_file.to_string_lossy().to_string()],
+        )
+        .unwrap();
+        env.set("CD_TEST_SOURCE_COMPUTED", "computed");
+
+        assert_eq!(
+            env.get_source("CD_TEST_SOURCE_FILE"),
+            Some(&VarSource::File(env_file.clone()))
+        );
+        assert_eq!(
+            env.get_source("CD_TEST_SOURCE_CLI"),
+            Some(&VarSource::CliFile(cli_file.clone()))
+        );
+        assert_eq!(
+            env.get_source("CD_TEST_SOURCE_COMPUTED"),
+            Some(&VarSource::Computed)
+        );
+        assert_eq!(env.get_source("PATH"), Some(&VarSource::System));
+        assert_eq!(env.get_source("CD_TEST_SOURCE_MISSING"), None);
+
+        let err = env
+            .validate_required(&["CD_TEST_SOURCE_EMPTY"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&format!(
+            "CD_TEST_SOURCE_EMPTY (from {})",
+            env_file.display()
+        )));
+    }
+
+    #[test]
+    fn test_empty_required_vars() {
+        let mut env = Environment::new();
+        env.set("KEYSTORE_ACCOUNT", "deployer");
+        env.set("KEYSTORE_PASSWORD", "");
+
+        let err = env
+            .validate_required(&["KEYSTORE_ACCOUNT", "KEYSTORE_PASSWORD", "BROADCAST_ACCOUNT"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("KEYSTORE_PASSWORD (from computed) is empty"));
+        assert!(err.contains("BROADCAST_ACCOUNT"));
+        assert!(!err.contains("KEYSTORE_ACCOUNT"));
+
+        env.allow_empty = vec!["KEYSTORE_PASSWORD".to_string()];
+        assert!(
+            env.validate_required(&["KEYSTORE_ACCOUNT", "KEYSTORE_PASSWORD"])
+                .is_ok()
+        );
+
+        env.allow_empty.clear();
+        env.reject_empty = false;
+        assert!(
+            env.validate_required(&["KEYSTORE_ACCOUNT", "KEYSTORE_PASSWORD"])
+                .is_ok()
+        );
+    }
+
+    #[cfg(feature = "age")]
+    #[test]
+    #[ignore = "needs the age and age-keygen CLIs; run with --features age -- --ignored"]
+    fn test_encrypted_env_round_trip() {
+        use std::process::Command;
+
+        let temp_dir = tempdir().unwrap();
+        let identity_file = temp_dir.path().join("key.txt");
+        let wrong_identity_file = temp_dir.path().join("wrong.txt");
+        let plaintext = temp_dir.path().join("secrets.env");
+        let encrypted = temp_dir.path().join("secrets.env.age");
+
+        for file in [&identity_file, &wrong_identity_file] {
+            let status = Command::new("age-keygen")
+                .arg("-o")
+                .arg(file)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success());
+        }
+        let identity = fs::read_to_string(&identity_file).unwrap();
+        let recipient = identity
+            .lines()
+            .find_map(|line| line.strip_prefix("# public key: "))
+            .unwrap()
+            .to_string();
+
+        fs::write(
+            &plaintext,
+            "DEPLOY_KEY=0xabc123\n# comment\nRPC_TOKEN=\"t0k3n\"\n",
+        )
+        .unwrap();
+        let status = Command::new("age")
+            .args(["-r", &recipient, "-o"])
+            .arg(&encrypted)
+            .arg(&plaintext)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::remove_file(&plaintext).unwrap();
+
+        let config = |identity_file: &Path| EnvConfig {
+            encrypted: Some(EncryptedEnvConfig {
+                file: encrypted.to_string_lossy().to_string(),
+                identity_var: None,
+                identity_file: Some(identity_file.to_string_lossy().to_string()),
+            }),
+            ..env_config(HashMap::new(), None)
+        };
+
+        let mut env = Environment::new();
+        env.load_from_config(&config(&identity_file), Path::new(""), &[])
+            .unwrap();
+        assert_eq!(env.get("DEPLOY_KEY"), Some(&"0xabc123".to_string()));
+        assert_eq!(env.get("RPC_TOKEN"), Some(&"t0k3n".to_string()));
+        assert_eq!(
+            env.get_source("DEPLOY_KEY"),
+            Some(&VarSource::Encrypted(encrypted.clone()))
+        );
+
+        let err = Environment::new()
+            .load_from_config(&config(&wrong_identity_file), Path::new(""), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Failed to decrypt"));
+        assert!(!err.contains("0xabc123"));
+    }
+
     #[test]
     fn test_validation() {
         let mut env = Environment::new();
@@ -0,0 +1,94 @@
+use std::fmt;
+
+/// Exit code for failures in the tool itself (config, environment, clone, setup)
+pub const TOOL_ERROR: i32 = 70;
+
+/// Exit code when the confirmation was declined or not answered in time
+pub const CANCELLED: i32 = 75;
+
+/// Exit code of `diff-config` when the configs differ
+pub const CONFIGS_DIFFER: i32 = 1;
+
+/// Exit code of `validate` when a check failed
+pub const VALIDATION_FAILED: i32 = 1;
+
+/// Returned by `diff-config` when the configs differ
+#[derive(Debug)]
+pub struct ConfigsDiffer {
+    pub changes: usize,
+}
+
+impl fmt::Display for ConfigsDiffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The configs differ in {} field(s)", self.changes)
+    }
+}
+
+impl std::error::Error for ConfigsDiffer {}
+
+/// Returned by `validate` when at least one check failed
+#[derive(Debug)]
+pub struct ValidationFailed {
+    pub failed: usize,
+}
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Validation failed: {} check(s) failed", self.failed)
+    }
+}
+
+impl std::error::Error for ValidationFailed {}
+
+/// Returned when the forge process exits unsuccessfully, carrying its exit code
+#[derive(Debug)]
+pub struct ForgeFailed {
+    /// Exit code of the forge process, `None` if it was killed by a signal
+    pub code: Option<i32>,
+}
+
+impl fmt::Display for ForgeFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "Script execution failed with exit code {}", code),
+            None => write!(f, "Script execution was terminated by a signal"),
+        }
+    }
+}
+
+impl std::error::Error for ForgeFailed {}
+
+/// Returned when the deployment is cancelled: the confirmation was declined or timed out
+#[derive(Debug)]
+pub struct Cancelled {
+    pub reason: String,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Deployment cancelled: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Map an error to the process exit code: forge's own code for forge failures,
+/// `CANCELLED` for cancellations, `CONFIGS_DIFFER` for a `diff-config` difference,
+/// `VALIDATION_FAILED` for a failed `validate` check, `TOOL_ERROR` for everything else
+pub fn for_error(error: &anyhow::Error) -> i32 {
+    if error.downcast_ref::<Cancelled>().is_some() {
+        return CANCELLED;
+    }
+    if error.downcast_ref::<ConfigsDiffer>().is_some() {
+        return CONFIGS_DIFFER;
+    }
+    if error.downcast_ref::<ValidationFailed>().is_some() {
+        return VALIDATION_FAILED;
+    }
+
+    match error.downcast_ref::<ForgeFailed>() {
+        Some(ForgeFailed { code: Some(code) }) => *code,
+        Some(ForgeFailed { code: None }) => 1,
+        None => TOOL_ERROR,
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::broadcast::DeployedContract;
+
+/// The `manifest.json` written by `--output-dir` for each run
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    project: Option<String>,
+    network: String,
+    #[serde(default)]
+    script: Option<String>,
+    timestamp: u64,
+    #[serde(default)]
+    git_sha: Option<String>,
+    #[serde(default)]
+    contracts: Vec<DeployedContract>,
+}
+
+/// The most recent deployment of a contract on a network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InventoryEntry {
+    pub network: String,
+    pub contract: String,
+    pub address: String,
+    /// Unix timestamp of the run that deployed it
+    pub timestamp: u64,
+    pub git_sha: Option<String>,
+}
+
+/// The most recent recorded run of a script on a network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorRun {
+    pub timestamp: u64,
+    pub git_sha: Option<String>,
+    pub contracts: Vec<DeployedContract>,
+}
+
+/// Network, script and commit of the most recent recorded run of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastRun {
+    pub network: String,
+    /// Script name without the `.s.sol` suffix, as in `project.script`
+    pub script: String,
+    pub timestamp: u64,
+    pub git_sha: Option<String>,
+}
+
+/// Every `<dir>/*/manifest.json`; a missing directory has none
+fn read_manifests(dir: &Path) -> Result<Vec<Manifest>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut manifests = Vec::new();
+    for entry in fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path().join("manifest.json");
+        if !path.is_file() {
+            continue;
+        }
+
+        let content =
+            fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        manifests.push(
+            serde_json::from_str(&content)
+                .context(format!("Failed to parse {}", path.display()))?,
+        );
+    }
+
+    Ok(manifests)
+}
+
+/// Read every `<dir>/*/manifest.json` and keep the latest deployment per network and contract.
+///
+/// A missing directory yields an empty inventory. When `networks` is not
+/// empty only those networks are included.
+pub fn load(dir: &Path, networks: &[String]) -> Result<Vec<InventoryEntry>> {
+    let mut latest: HashMap<(String, String), InventoryEntry> = HashMap::new();
+
+    for manifest in read_manifests(dir)? {
+        if !networks.is_empty() && !networks.contains(&manifest.network) {
+            continue;
+        }
+
+        for contract in manifest.contracts {
+            let key = (manifest.network.clone(), contract.name.clone());
+            if latest
+                .get(&key)
+                .is_some_and(|existing| existing.timestamp >= manifest.timestamp)
+            {
+                continue;
+            }
+
+            latest.insert(
+                key,
+                InventoryEntry {
+                    network: manifest.network.clone(),
+                    contract: contract.name,
+                    address: contract.address,
+                    timestamp: manifest.timestamp,
+                    git_sha: manifest.git_sha.clone(),
+                },
+            );
+        }
+    }
+
+    let mut entries: Vec<InventoryEntry> = latest.into_values().collect();
+    entries.sort_by(|a, b| (&a.network, &a.contract).cmp(&(&b.network, &b.contract)));
+    Ok(entries)
+}
+
+/// Latest manifest in `dir` for `script` on `network` that deployed at least one contract
+pub fn latest_run(dir: &Path, network: &str, script: &str) -> Result<Option<PriorRun>> {
+    Ok(read_manifests(dir)?
+        .into_iter()
+        .filter(|m| {
+            m.network == network && m.script.as_deref() == Some(script) && !m.contracts.is_empty()
+        })
+        .max_by_key(|m| m.timestamp)
+        .map(|m| PriorRun {
+            timestamp: m.timestamp,
+            git_sha: m.git_sha,
+            contracts: m.contracts,
+        }))
+}
+
+/// Commit of the latest manifest in `dir` for `script` on `network`, whether or not it
+/// deployed anything; manifests are only written for runs where forge succeeded
+pub fn last_deployed_commit(dir: &Path, network: &str, script: &str) -> Result<Option<String>> {
+    Ok(read_manifests(dir)?
+        .into_iter()
+        .filter(|m| m.network == network && m.script.as_deref() == Some(script))
+        .max_by_key(|m| m.timestamp)
+        .and_then(|m| m.git_sha))
+}
+
+/// Latest manifest in `dir` written for `project`, whether or not it deployed anything.
+/// Manifests that name no script can't be re-run and are ignored.
+pub fn last_run(dir: &Path, project: &str) -> Result<Option<LastRun>> {
+    Ok(read_manifests(dir)?
+        .into_iter()
+        .filter(|m| m.project.as_deref().is_none_or(|name| name == project))
+        .filter_map(|m| {
+            let script = m.script?;
+            Some(LastRun {
+                network: m.network,
+                script: script.trim_end_matches(".s.sol").to_string(),
+                timestamp: m.timestamp,
+                git_sha: m.git_sha,
+            })
+        })
+        .max_by_key(|run| run.timestamp))
+}
+
+pub fn print_human(entries: &[InventoryEntry], dir: &Path) {
+    if entries.is_empty() {
+        println!("No deployment manifests found in {}", dir.display());
+        return;
+    }
+
+    let network_width = column_width(entries.iter().map(|e| e.network.len()), "NETWORK");
+    let contract_width = column_width(entries.iter().map(|e| e.contract.len()), "CONTRACT");
+
+    let header = format!(
+        "{:<network_width$}  {:<contract_width$}  {:<42}  {:<10}  GIT_SHA",
+        "NETWORK", "CONTRACT", "ADDRESS", "TIMESTAMP"
+    );
+    println!("{}", header.blue());
+
+    for entry in entries {
+        let sha = entry
+            .git_sha
+            .as_deref()
+            .map(|sha| &sha[..sha.len().min(12)])
+            .unwrap_or("-");
+        println!(
+            "{:<network_width$}  {:<contract_width$}  {:<42}  {:<10}  {}",
+            entry.network, entry.contract, entry.address, entry.timestamp, sha
+        );
+    }
+}
+
+fn column_width(lengths: impl Iterator<Item = usize>, header: &str) -> usize {
+    lengths.max().unwrap_or_default().max(header.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_manifest(dir: &Path, run: &str, network: &str, timestamp: u64, address: &str) {
+        let run_dir = dir.join(run);
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(
+            run_dir.join("manifest.json"),
+            format!(
+                r#"{{"project":"my-token","network":"{}","chain_id":1,"script":"Deploy.s.sol","timestamp":{},"git_sha":"4b825dc642cb6eb9a060e54bf8d69288fbee4904","contracts":[{{"name":"MyToken","address":"{}"}}]}}"#,
+                network, timestamp, address
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_load_inventory() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        write_manifest(dir, "sepolia-100", "sepolia", 100, "0x01");
+        write_manifest(dir, "sepolia-200", "sepolia", 200, "0x02");
+        write_manifest(dir, "mainnet-150", "mainnet", 150, "0x03");
+
+        let entries = load(dir, &[]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].network, "mainnet");
+        assert_eq!(entries[1].address, "0x02");
+        assert_eq!(entries[1].timestamp, 200);
+
+        let sepolia = load(dir, &["sepolia".to_string()]).unwrap();
+        assert_eq!(sepolia.len(), 1);
+
+        assert!(load(&dir.join("missing"), &[]).unwrap().is_empty());
+
+        let prior = latest_run(dir, "sepolia", "Deploy.s.sol").unwrap().unwrap();
+        assert_eq!(prior.timestamp, 200);
+        assert_eq!(prior.contracts[0].address, "0x02");
+        assert!(
+            latest_run(dir, "sepolia", "Upgrade.s.sol")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
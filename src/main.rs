@@ -1,24 +1,411 @@
+#[cfg(feature = "age")]
+mod age;
+#[cfg(feature = "aws")]
+mod aws;
+mod batch;
+mod broadcast;
+mod bytecode;
+mod chains;
 mod cli;
+mod clone_cache;
+mod code_size;
+mod command_export;
 mod config;
+mod config_diff;
 mod deployer;
+mod ens;
 mod environment;
+mod exit_code;
+mod inventory;
+mod manifest_commit;
+mod multi_network;
+mod notify;
+mod plan;
+mod proxy;
+mod redact;
+mod repo_size;
+mod rpc;
+mod safe;
+mod setup_env;
+mod simulate;
+mod sourcify;
+mod summary;
+#[cfg(test)]
+mod test_http;
+mod validate;
+#[cfg(feature = "vault")]
+mod vault;
+mod verify;
+mod version;
+mod watch;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::Args;
-use deployer::ContractDeployer;
+use cli::{Args, Commands, OutputFormat};
+use colored::*;
+use deployer::{ContractDeployer, DeployOptions};
+use safe::SafeMode;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use summary::{DeploymentResult, DeploymentStatus};
+use validate::{CheckStatus, ValidationReport};
 
-fn main() -> Result<()> {
+fn main() {
     let args = Args::parse();
 
-    let mut deployer = ContractDeployer::new(
-        &args.config,
-        args.skip_confirmation,
-        args.network,
-        args.script,
-    )?;
+    let result = match &args.command {
+        Some(Commands::Validate) => run_validate(&args),
+        Some(Commands::Schema) => run_schema(),
+        Some(Commands::Deployed { dir }) => run_deployed(&args, dir),
+        Some(Commands::Verify { address, contract }) => run_verify(&args, address, contract),
+        Some(Commands::Version { full }) => run_version(&args, *full),
+        Some(Commands::SetupEnv { file }) => run_setup_env(&args, file.as_deref()),
+        Some(Commands::DiffConfig { old, new }) => run_diff_config(&args, old, new),
+        None => run_deploy(args),
+    };
 
-    deployer.deploy()?;
+    // Forge failures exit with forge's own code so CI can tell reverts from tool errors
+    if let Err(e) = result {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(exit_code::for_error(&e));
+    }
+}
+
+fn deploy_options(args: &Args, network: Option<String>) -> DeployOptions {
+    DeployOptions {
+        skip_confirmation: args.skip_confirmation,
+        assume_yes_for: args.assume_yes_for.clone(),
+        network_override: network,
+        script_override: args.script.clone(),
+        from_last: args.from_last,
+        only_if_changed: args.only_if_changed,
+        check_balance: args.check_balance,
+        force: args.force,
+        redeploy: args.redeploy,
+        force_dangerous: args.force_dangerous,
+        estimate_clone: args.estimate_clone,
+        refresh_cache: args.refresh_cache,
+        abort_on_warning: args.abort_on_warning,
+        no_broadcast_check: args.no_broadcast_check,
+        no_cleanup: args.no_cleanup,
+        explain: args.explain,
+        simulate_all: args.simulate_all,
+        fork_block: args.fork_block,
+        notify_local: args.notify_local,
+        sender_from_keystore: args.sender_from_keystore,
+        sender: None,
+        dump_forge_env: args.dump_forge_env,
+        plan_out: args.plan_out.clone(),
+        emit_command: args.emit_command,
+        env_files: args.env_files.clone(),
+        output_dir: args.output_dir.clone(),
+        confirm_timeout: args.confirm_timeout,
+        clean: args.clean,
+        setup_command_override: args.setup_command.clone(),
+        no_setup: args.no_setup,
+        forge_args: args.forge_args.clone(),
+        clone_timeout: args.clone_timeout,
+        rpc_timeout: args.rpc_timeout,
+        git_ref_override: args.git_ref.clone(),
+        cwd_override: args.cwd.clone(),
+        config_dir: args.config_dir.clone(),
+        network_json: args.network_override.clone(),
+        gas_limit: args.gas_limit.clone(),
+        gas_price: args.gas_price.clone(),
+        priority_fee: args.priority_fee.clone(),
+        safe: SafeMode::new(args.safe, &args.safe_skip),
+        no_network_prompt: false,
+    }
+}
+
+fn run_deploy(args: Args) -> Result<()> {
+    if let Some(plan) = &args.plan {
+        let options = deploy_options(&args, None);
+        let result = ContractDeployer::from_plan(plan, options)?.deploy()?;
+        return print_result(&args, &result);
+    }
+
+    let config_path = args.config_path()?;
+
+    if batch::is_batch_file(config_path) {
+        return run_batch(&args, config_path);
+    }
+
+    if args.print_env {
+        let options = deploy_options(&args, args.network.first().cloned());
+        return ContractDeployer::new(config_path, options)?.print_env();
+    }
+
+    if let Some(path) = &args.export_env {
+        let options = deploy_options(&args, args.network.first().cloned());
+        return ContractDeployer::new(config_path, options)?
+            .export_env(Path::new(path), args.include_secrets);
+    }
+
+    if args.resolve_rpc {
+        let options = deploy_options(&args, args.network.first().cloned());
+        let rpc_url = ContractDeployer::new(config_path, options)?.resolve_rpc_url()?;
+        println!("{}", rpc_url);
+        return Ok(());
+    }
+
+    if args.watch {
+        if args.network.len() > 1 {
+            anyhow::bail!("--watch deploys to a single network");
+        }
+        let options = deploy_options(&args, args.network.first().cloned());
+        return ContractDeployer::new(config_path, options)?.watch();
+    }
+
+    if args.network.len() <= 1 {
+        let options = deploy_options(&args, args.network.first().cloned());
+        let mut deployer = ContractDeployer::new(config_path, options)?;
+
+        if !deployer.senders().is_empty() {
+            return run_senders(&args, config_path, deployer);
+        }
+
+        let result = deployer.deploy()?;
+
+        return print_result(&args, &result);
+    }
+
+    // Each network gets a fresh deployer so environment and clones don't leak between runs
+    let outcomes = multi_network::run_networks(&args.network, args.keep_going, |network| {
+        let options = deploy_options(&args, Some(network.to_string()));
+        let mut deployer = ContractDeployer::new(config_path, options)?;
+        if !deployer.senders().is_empty() {
+            anyhow::bail!(
+                "project.senders deploys to a single network; run each network separately"
+            );
+        }
+        let result = deployer.deploy()?;
+        print_result(&args, &result)?;
+        Ok(result)
+    });
+
+    multi_network::print_summary(&outcomes, &args.network, args.summary_order);
+    multi_network::into_result(outcomes)
+}
+
+/// Deploy once per `project.senders` account, each run with its own clone,
+/// confirmation and summary row
+fn run_senders(args: &Args, config_path: &str, mut deployer: ContractDeployer) -> Result<()> {
+    deployer.check_senders()?;
+    let network = deployer.network().to_string();
+    let senders = deployer.senders().to_vec();
+    let labels: Vec<String> = senders
+        .iter()
+        .map(|sender| format!("{} as {}", network, sender.account))
+        .collect();
+
+    let outcomes = multi_network::run_networks(&labels, args.keep_going, |label| {
+        let index = labels.iter().position(|l| l == label).unwrap_or_default();
+        let mut options = deploy_options(args, Some(network.clone()));
+        options.sender = Some(senders[index].clone());
+        let result = ContractDeployer::new(config_path, options)?.deploy()?;
+        print_result(args, &result)?;
+        Ok(result)
+    });
+
+    multi_network::print_summary(&outcomes, &labels, args.summary_order);
+    multi_network::into_result(outcomes)
+}
+
+/// Clone and set up every `[[batch]]` project concurrently, then deploy them in order
+fn run_batch(args: &Args, batch_path: &str) -> Result<()> {
+    let batch = batch::BatchConfig::from_file(batch_path)?;
+
+    let outcomes = batch::run(
+        &batch.batch,
+        args.max_parallel,
+        args.keep_going,
+        |entry| {
+            let mut options = deploy_options(args, entry.network.clone());
+            if entry.script.is_some() {
+                options.script_override = entry.script.clone();
+            }
+            options.no_network_prompt = true;
+            let mut deployer = ContractDeployer::new(&entry.config, options)?;
+            let prepared = deployer.prepare()?;
+            Ok((deployer, prepared))
+        },
+        |_, (mut deployer, prepared)| {
+            let result = deployer.deploy_prepared(prepared)?;
+            print_result(args, &result)?;
+            Ok(result)
+        },
+        |_, (deployer, prepared)| deployer.release(prepared),
+    );
+
+    batch::print_summary(&outcomes);
+    batch::into_result(outcomes)
+}
+
+/// Print the run's summary; a declined or unanswered prompt becomes a
+/// cancellation error so it exits with the cancellation code
+fn print_result(args: &Args, result: &DeploymentResult) -> Result<()> {
+    match (args.output, result.status) {
+        (_, DeploymentStatus::Explained | DeploymentStatus::Emitted) => {}
+        (OutputFormat::Human, _) => result.print_human(),
+        (OutputFormat::Json, _) => println!("{}", result.to_json()?),
+    }
+
+    // Only meaningful inside GitHub Actions, which sets GITHUB_STEP_SUMMARY
+    if args.github_summary
+        && !matches!(
+            result.status,
+            DeploymentStatus::Explained | DeploymentStatus::Emitted
+        )
+        && let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY")
+    {
+        // The deploy already happened; a missing summary must not fail it
+        if let Err(e) = result.append_markdown(std::path::Path::new(&path)) {
+            println!(
+                "{}",
+                format!("Warning: Failed to write the GitHub step summary: {:#}", e).yellow()
+            );
+        }
+    }
+
+    let reason = match result.status {
+        DeploymentStatus::Cancelled => "the confirmation was declined",
+        DeploymentStatus::TimedOut => "the confirmation prompt was not answered in time",
+        _ => return Ok(()),
+    };
+    Err(exit_code::Cancelled {
+        reason: reason.to_string(),
+    }
+    .into())
+}
+
+/// Print the config JSON Schema so editors and CI can validate deploy files
+fn run_schema() -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&config::DeploymentConfig::json_schema())?
+    );
+    Ok(())
+}
+
+/// Print the structured difference between two configs, exiting 1 if they differ
+fn run_diff_config(args: &Args, old: &str, new: &str) -> Result<()> {
+    let old = config::DeploymentConfig::from_file(old)?;
+    let new = config::DeploymentConfig::from_file(new)?;
+    let changes = config_diff::diff(&old, &new)?;
+
+    match args.output {
+        OutputFormat::Human => config_diff::print_human(&changes),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+    }
+
+    if !changes.is_empty() {
+        return Err(exit_code::ConfigsDiffer {
+            changes: changes.len(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Print the deployed contracts recorded in the run manifests under `dir`
+fn run_deployed(args: &Args, dir: &str) -> Result<()> {
+    let dir = std::path::Path::new(dir);
+    let entries = inventory::load(dir, &args.network)?;
+
+    match args.output {
+        OutputFormat::Human => inventory::print_human(&entries, dir),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+    }
+
+    Ok(())
+}
+
+/// Print the tool version, or with `--full` the forge, git and build details
+fn run_version(args: &Args, full: bool) -> Result<()> {
+    if !full {
+        println!("contract-deployer {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    let info = version::VersionInfo::detect();
+    match args.output {
+        OutputFormat::Human => info.print_human(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&info)?),
+    }
+
+    Ok(())
+}
+
+/// Verify one already deployed contract on the selected network's explorer
+fn run_verify(args: &Args, address: &str, contract: &str) -> Result<()> {
+    if args.network.len() > 1 {
+        anyhow::bail!("verify accepts a single --network");
+    }
+
+    let options = deploy_options(args, args.network.first().cloned());
+    ContractDeployer::new(args.config_path()?, options)?.verify_address(address, contract)
+}
+
+fn run_setup_env(args: &Args, file: Option<&str>) -> Result<()> {
+    if args.network.len() > 1 {
+        anyhow::bail!("setup-env accepts a single --network");
+    }
+
+    let options = deploy_options(args, args.network.first().cloned());
+    let mut deployer = ContractDeployer::new(args.config_path()?, options)?;
+    let missing = deployer.missing_env_vars()?;
+    if missing.is_empty() {
+        println!("All required variables are set");
+        return Ok(());
+    }
+
+    let path = file.map_or_else(|| deployer.default_env_file(), PathBuf::from);
+    println!(
+        "{} variable(s) missing, answers go to {} (leave empty to skip)",
+        missing.len(),
+        path.display()
+    );
+
+    let stdin = std::io::stdin();
+    let terminal = stdin.is_terminal();
+    let written = setup_env::run(&path, &missing, &mut stdin.lock(), terminal)?;
+    println!("Wrote {} variable(s) to {}", written.len(), path.display());
+
+    Ok(())
+}
+
+/// Run the validation checks and exit non-zero if any of them failed
+fn run_validate(args: &Args) -> Result<()> {
+    if args.network.len() > 1 {
+        anyhow::bail!("validate accepts a single --network");
+    }
+
+    let network = args.network.first().cloned();
+    let report = match ContractDeployer::new(args.config_path()?, deploy_options(args, network)) {
+        Ok(mut deployer) => deployer.validate(),
+        Err(e) => {
+            let mut report =
+                ValidationReport::new(args.network.first().cloned(), args.script.clone());
+            report.add_result("config", Err(e));
+            report
+        }
+    };
+
+    match args.output {
+        OutputFormat::Human => report.print_human(),
+        OutputFormat::Json => println!("{}", report.to_json()?),
+    }
+
+    if !report.passed {
+        let failed = report
+            .checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count();
+        return Err(exit_code::ValidationFailed { failed }.into());
+    }
 
     Ok(())
 }
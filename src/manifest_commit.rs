@@ -0,0 +1,231 @@
+use anyhow::{Context, Result};
+use git2::{Index, Oid, Repository};
+use std::path::{Path, PathBuf};
+
+/// Default `project.commit_message`
+pub const DEFAULT_MESSAGE: &str = "Record {project} deployment on {network} ({sha})";
+
+/// `project.commit_message` with `{project}`, `{network}`, `{script}` and `{sha}` filled in
+pub fn message(template: &str, project: &str, network: &str, script: &str, sha: &str) -> String {
+    template
+        .replace("{project}", project)
+        .replace("{network}", network)
+        .replace("{script}", script)
+        .replace("{sha}", sha)
+}
+
+/// The repository `dir` belongs to, with an error naming `dir` when there is none
+pub fn open(dir: &Path) -> Result<Repository> {
+    Repository::discover(dir).context(format!(
+        "project.commit_manifest: {} is not inside a git repository",
+        dir.display()
+    ))
+}
+
+/// Check up front that `commit_files` will be able to commit files written under
+/// `output_dir`: the repository of `repo_dir` has a working tree and a git
+/// identity, and `output_dir` is inside that working tree
+pub fn check(repo_dir: &Path, output_dir: &Path) -> Result<()> {
+    let repo = open(repo_dir)?;
+    let workdir = workdir(&repo)?;
+    repo.signature()
+        .context("No git identity to commit the manifest with; set user.name and user.email")?;
+
+    // The output directory may not exist yet; resolve what does
+    let output_dir = std::path::absolute(output_dir)?;
+    let mut existing = output_dir.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let Some(parent) = existing.parent() else {
+            break;
+        };
+        missing.push(existing.file_name().unwrap_or_default());
+        existing = parent;
+    }
+    let mut resolved = existing.canonicalize()?;
+    resolved.extend(missing.iter().rev());
+
+    if !resolved.starts_with(&workdir) {
+        anyhow::bail!(
+            "project.commit_manifest: --output-dir {} is outside the repository at {}",
+            output_dir.display(),
+            workdir.display()
+        );
+    }
+    Ok(())
+}
+
+fn workdir(repo: &Repository) -> Result<PathBuf> {
+    Ok(repo
+        .workdir()
+        .context("project.commit_manifest: the repository is bare")?
+        .canonicalize()?)
+}
+
+/// Commit `files` on top of HEAD in the repository of `repo_dir`, and nothing else.
+///
+/// Changes already staged stay staged but are left out of the commit. Files
+/// must be inside the repository's working tree; ignored files are added anyway.
+/// Nothing is pushed.
+pub fn commit_files(repo_dir: &Path, files: &[PathBuf], message: &str) -> Result<Oid> {
+    let repo = open(repo_dir)?;
+    let workdir = workdir(&repo)?;
+
+    let paths = files
+        .iter()
+        .map(|file| {
+            let absolute = file
+                .canonicalize()
+                .context(format!("Failed to read {}", file.display()))?;
+            absolute
+                .strip_prefix(&workdir)
+                .map(Path::to_path_buf)
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "{} is outside the repository at {}",
+                        file.display(),
+                        workdir.display()
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+
+    // The commit's tree is HEAD's plus these files, built apart from the repository index
+    let mut tree_index = Index::new()?;
+    if let Some(parent) = &parent {
+        tree_index.read_tree(&parent.tree()?)?;
+    }
+    let mut index = repo.index()?;
+    for path in &paths {
+        index
+            .add_path(path)
+            .context(format!("Failed to stage {}", path.display()))?;
+        let entry = index
+            .get_path(path, 0)
+            .context(format!("Failed to stage {}", path.display()))?;
+        tree_index.add(&entry)?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(tree_index.write_tree_to(&repo)?)?;
+
+    let signature = repo
+        .signature()
+        .context("No git identity to commit the manifest with; set user.name and user.email")?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .context("Failed to commit the manifest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_commit_only_manifest_files() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let repo = Repository::init(root).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "deployer").unwrap();
+        config
+            .set_str("user.email", "deployer@example.com")
+            .unwrap();
+
+        fs::write(root.join("README.md"), "token\n").unwrap();
+        fs::write(root.join(".gitignore"), "deployments/\n").unwrap();
+        let initial = commit_files(
+            root,
+            &[root.join("README.md"), root.join(".gitignore")],
+            "Initial",
+        )
+        .unwrap();
+
+        // A change someone already staged, and one that isn't staged
+        fs::write(root.join("staged.txt"), "wip\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(root.join("README.md"), "changed\n").unwrap();
+
+        let run_dir = root.join("deployments/sepolia-100");
+        fs::create_dir_all(run_dir.join("broadcast")).unwrap();
+        fs::write(run_dir.join("manifest.json"), "{}").unwrap();
+        fs::write(run_dir.join("broadcast/run-latest.json"), "{}").unwrap();
+
+        let oid = commit_files(
+            root,
+            &[
+                run_dir.join("manifest.json"),
+                run_dir.join("broadcast/run-latest.json"),
+            ],
+            &message(
+                DEFAULT_MESSAGE,
+                "token",
+                "sepolia",
+                "Deploy.s.sol",
+                "abc123",
+            ),
+        )
+        .unwrap();
+
+        let commit = repo.find_commit(oid).unwrap();
+        assert_eq!(
+            commit.message(),
+            Some("Record token deployment on sepolia (abc123)")
+        );
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), [initial]);
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+
+        let parent_tree = repo.find_commit(initial).unwrap().tree().unwrap();
+        let diff = repo
+            .diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree().unwrap()), None)
+            .unwrap();
+        let changed: Vec<String> = diff
+            .deltas()
+            .map(|delta| {
+                delta
+                    .new_file()
+                    .path()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                "deployments/sepolia-100/broadcast/run-latest.json",
+                "deployments/sepolia-100/manifest.json"
+            ]
+        );
+
+        let outside = tempdir().unwrap();
+        let error = commit_files(outside.path(), &[], "x").unwrap_err();
+        assert!(error.to_string().contains("is not inside a git repository"));
+
+        // Checked before deploying: where the output goes, and who commits it
+        check(root, &root.join("deployments/next")).unwrap();
+        let error = check(root, outside.path()).unwrap_err();
+        assert!(error.to_string().contains("is outside the repository"));
+        config.remove("user.name").unwrap();
+        config.remove("user.email").unwrap();
+        if repo.signature().is_err() {
+            let error = check(root, &root.join("deployments")).unwrap_err();
+            assert!(error.to_string().contains("No git identity"));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::*;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::summary::DeploymentResult;
+
+/// What happened to one network in a multi-network run
+#[derive(Debug)]
+pub enum NetworkStatus {
+    Deployed(DeploymentResult),
+    Failed(anyhow::Error),
+    /// Not attempted because an earlier network failed in fail-fast mode
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct NetworkOutcome {
+    pub network: String,
+    pub status: NetworkStatus,
+    /// Wall time of the attempt, zero when skipped
+    pub duration: Duration,
+}
+
+/// Order of the networks in the summary, independent of completion order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SummaryOrder {
+    /// As given with `--network`
+    #[default]
+    Given,
+    Alphabetical,
+}
+
+/// Deploy to each network in order.
+///
+/// In fail-fast mode the first failure stops the run and the remaining
+/// networks are marked as skipped; with `keep_going` every network is attempted.
+pub fn run_networks<F>(networks: &[String], keep_going: bool, mut deploy: F) -> Vec<NetworkOutcome>
+where
+    F: FnMut(&str) -> Result<DeploymentResult>,
+{
+    let mut outcomes = Vec::with_capacity(networks.len());
+    let mut failed = false;
+
+    for network in networks {
+        let started = Instant::now();
+        let status = if failed && !keep_going {
+            NetworkStatus::Skipped
+        } else {
+            println!(
+                "\n{}",
+                format!("══════════ Deploying to {} ══════════", network).green()
+            );
+
+            match deploy(network) {
+                Ok(result) => NetworkStatus::Deployed(result),
+                Err(e) => {
+                    println!(
+                        "{}",
+                        format!("Deployment to {} failed: {:#}", network, e).red()
+                    );
+                    failed = true;
+                    NetworkStatus::Failed(e)
+                }
+            }
+        };
+
+        let duration = match status {
+            NetworkStatus::Skipped => Duration::ZERO,
+            _ => started.elapsed(),
+        };
+        outcomes.push(NetworkOutcome {
+            network: network.clone(),
+            status,
+            duration,
+        });
+    }
+
+    outcomes
+}
+
+pub fn print_summary(outcomes: &[NetworkOutcome], requested: &[String], order: SummaryOrder) {
+    // A closed stdout leaves nothing to report to
+    let _ = write_summary(outcomes, requested, order, &mut io::stdout());
+}
+
+/// One line per network with status, duration and deployed addresses.
+///
+/// Lines follow `order` (`Given` uses the position in `requested`), never the
+/// order in which the networks finished, so logs of repeated runs diff cleanly.
+pub fn write_summary(
+    outcomes: &[NetworkOutcome],
+    requested: &[String],
+    order: SummaryOrder,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut sorted: Vec<&NetworkOutcome> = outcomes.iter().collect();
+    match order {
+        SummaryOrder::Given => sorted.sort_by_key(|outcome| {
+            requested
+                .iter()
+                .position(|network| *network == outcome.network)
+                .unwrap_or(usize::MAX)
+        }),
+        SummaryOrder::Alphabetical => sorted.sort_by(|a, b| a.network.cmp(&b.network)),
+    }
+    let width = sorted
+        .iter()
+        .map(|outcome| outcome.network.len())
+        .max()
+        .unwrap_or_default();
+
+    writeln!(
+        out,
+        "\n{}",
+        "══════════════════════════════════ NETWORK SUMMARY ══════════════════════════════════"
+            .green()
+    )?;
+
+    for outcome in sorted {
+        let network = format!("{:<width$}", outcome.network);
+        let duration = format!("{:>7.1}s", outcome.duration.as_secs_f64());
+        match &outcome.status {
+            NetworkStatus::Deployed(result) => {
+                let contracts = result
+                    .contracts
+                    .iter()
+                    .map(|contract| format!("{} {}", contract.name, contract.address))
+                    .collect::<Vec<_>>();
+                let contracts = if contracts.is_empty() {
+                    "no contracts".to_string()
+                } else {
+                    contracts.join(", ")
+                };
+                writeln!(
+                    out,
+                    "{} {} {} {}: {}",
+                    "✓".green(),
+                    network,
+                    duration,
+                    result.status,
+                    contracts
+                )?
+            }
+            NetworkStatus::Failed(e) => {
+                writeln!(out, "{} {} {} {:#}", "✗".red(), network, duration, e)?
+            }
+            NetworkStatus::Skipped => {
+                writeln!(out, "{} {} {:>8} skipped", "-".yellow(), network, "-")?
+            }
+        }
+    }
+
+    writeln!(
+        out,
+        "{}",
+        "═══════════════════════════════════════════════════════════════════════════════════════"
+            .green()
+    )
+}
+
+/// Turn the outcomes into the run's result, failing if any network failed.
+///
+/// The first failure's error is kept as the source so its exit code is preserved.
+pub fn into_result(outcomes: Vec<NetworkOutcome>) -> Result<()> {
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, NetworkStatus::Failed(_)))
+        .map(|o| o.network.clone())
+        .collect();
+
+    let first_error = outcomes.into_iter().find_map(|o| match o.status {
+        NetworkStatus::Failed(e) => Some(e),
+        _ => None,
+    });
+
+    match first_error {
+        Some(e) => Err(e.context(format!("Deployment failed on: {}", failed.join(", ")))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcast::DeployedContract;
+    use crate::summary::{ContractSummary, DeploymentStatus};
+
+    fn networks() -> Vec<String> {
+        vec!["sepolia".into(), "base".into(), "optimism".into()]
+    }
+
+    fn deployed(network: &str) -> DeploymentResult {
+        let mut result =
+            DeploymentResult::not_deployed(DeploymentStatus::Succeeded, "token", network, "Deploy");
+        result.contracts.push(ContractSummary::new(
+            DeployedContract {
+                name: "Token".to_string(),
+                address: format!("0x{}", network),
+            },
+            None,
+        ));
+        result
+    }
+
+    fn deploy_failing_on_base(network: &str) -> Result<DeploymentResult> {
+        if network == "base" {
+            anyhow::bail!("forge failed");
+        }
+        Ok(deployed(network))
+    }
+
+    #[test]
+    fn test_fail_fast_skips_remaining_networks() {
+        let mut attempted = Vec::new();
+        let outcomes = run_networks(&networks(), false, |network| {
+            attempted.push(network.to_string());
+            deploy_failing_on_base(network)
+        });
+
+        assert_eq!(attempted, vec!["sepolia", "base"]);
+        assert!(matches!(outcomes[0].status, NetworkStatus::Deployed(_)));
+        assert!(matches!(outcomes[1].status, NetworkStatus::Failed(_)));
+        assert!(matches!(outcomes[2].status, NetworkStatus::Skipped));
+        assert!(into_result(outcomes).is_err());
+    }
+
+    #[test]
+    fn test_keep_going_attempts_all_networks() {
+        let outcomes = run_networks(&networks(), true, deploy_failing_on_base);
+
+        assert!(matches!(outcomes[0].status, NetworkStatus::Deployed(_)));
+        assert!(matches!(outcomes[1].status, NetworkStatus::Failed(_)));
+        assert!(matches!(outcomes[2].status, NetworkStatus::Deployed(_)));
+
+        let err = into_result(outcomes).unwrap_err();
+        assert!(format!("{:#}", err).contains("Deployment failed on: base"));
+    }
+
+    #[test]
+    fn test_summary_order_ignores_completion_order() {
+        let outcome = |network: &str, status| NetworkOutcome {
+            network: network.to_string(),
+            status,
+            duration: Duration::from_millis(1500),
+        };
+        // Finished as optimism, sepolia, base
+        let outcomes = vec![
+            outcome("optimism", NetworkStatus::Skipped),
+            outcome("sepolia", NetworkStatus::Deployed(deployed("sepolia"))),
+            outcome(
+                "base",
+                NetworkStatus::Failed(anyhow::anyhow!("forge failed")),
+            ),
+        ];
+
+        let lines = |order| {
+            let mut out = Vec::new();
+            write_summary(&outcomes, &networks(), order, &mut out).unwrap();
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .filter(|line| !line.contains('═') && !line.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        let given = lines(SummaryOrder::Given);
+        assert_eq!(given.len(), 3);
+        assert!(given[0].contains("sepolia ") && given[0].contains("1.5s"));
+        assert!(given[0].contains("Token 0xsepolia"));
+        assert!(given[1].contains("base ") && given[1].contains("forge failed"));
+        assert!(given[2].contains("optimism") && given[2].contains("skipped"));
+
+        let alphabetical = lines(SummaryOrder::Alphabetical);
+        assert!(alphabetical[0].contains("base "));
+        assert!(alphabetical[1].contains("optimism"));
+        assert!(alphabetical[2].contains("sepolia "));
+        assert_eq!(lines(SummaryOrder::Given), given);
+    }
+}
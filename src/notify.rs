@@ -0,0 +1,85 @@
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::config::NotifyConfig;
+
+/// Announce that a deployment finished, on a best-effort basis.
+///
+/// Rings the terminal bell (when stderr is a terminal and `bell` is on) and
+/// runs `on_complete_command` with `DEPLOY_STATUS`, `DEPLOY_PROJECT` and
+/// `DEPLOY_NETWORK` set. A failing command is returned for the caller to warn about.
+pub fn on_complete(
+    config: &NotifyConfig,
+    status: &str,
+    project: &str,
+    network: &str,
+) -> Result<(), String> {
+    if config.bell && io::stderr().is_terminal() {
+        let _ = io::stderr().write_all(b"\x07");
+    }
+
+    let Some(command) = &config.on_complete_command else {
+        return Ok(());
+    };
+
+    let result = shell_command(command)
+        .env("DEPLOY_STATUS", status)
+        .env("DEPLOY_PROJECT", project)
+        .env("DEPLOY_NETWORK", network)
+        .stdin(Stdio::null())
+        .status();
+
+    match result {
+        Ok(exit) if exit.success() => Ok(()),
+        Ok(exit) => Err(format!("Notification command failed ({})", exit)),
+        Err(e) => Err(format!("Failed to run notification command: {}", e)),
+    }
+}
+
+/// Run `command` through the platform shell
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_on_complete_command() {
+        let temp_dir = tempdir().unwrap();
+        let out = temp_dir.path().join("notified");
+
+        let config = NotifyConfig {
+            local: true,
+            bell: false,
+            on_complete_command: Some(format!(
+                "echo \"$DEPLOY_STATUS $DEPLOY_PROJECT $DEPLOY_NETWORK\" > {}",
+                out.display()
+            )),
+        };
+        on_complete(&config, "succeeded", "my-token", "sepolia").unwrap();
+        assert_eq!(
+            fs::read_to_string(&out).unwrap().trim(),
+            "succeeded my-token sepolia"
+        );
+
+        // A failing notifier is reported for the caller to warn about
+        let failing = NotifyConfig {
+            on_complete_command: Some("exit 1".to_string()),
+            ..config
+        };
+        let warning = on_complete(&failing, "failed", "my-token", "sepolia").unwrap_err();
+        assert!(warning.contains("Notification command failed"));
+    }
+}
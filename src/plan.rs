@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::config::DeploymentConfig;
+use crate::redact;
+
+const PLAN_VERSION: u32 = 1;
+
+/// Everything needed to repeat a reviewed deployment (`--plan-out` / `--plan`).
+///
+/// Secrets are never stored: sensitive `env.vars` entries are dropped and
+/// the recorded command refers to secrets from any source as `${VAR}`; both
+/// are listed by name in `secrets`. They are resolved from the environment
+/// when the plan is replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentPlan {
+    pub version: u32,
+    pub network: String,
+    pub script: String,
+    /// Commit the plan was made from; the replay refuses any other checkout
+    pub git_sha: Option<String>,
+    /// forge invocation for review, with secrets shown as `${VAR}`
+    pub command: Vec<String>,
+    /// Variables that must be provided by the environment at replay time
+    pub secrets: Vec<String>,
+    /// Unexpanded config, so `${VAR}` references resolve again at replay time
+    pub config: DeploymentConfig,
+}
+
+impl DeploymentPlan {
+    pub fn new(
+        raw_config: &DeploymentConfig,
+        git_sha: Option<String>,
+        command: Vec<String>,
+    ) -> Self {
+        let mut config = raw_config.clone();
+        let mut secrets: Vec<String> = config
+            .env
+            .vars
+            .keys()
+            .filter(|key| redact::is_sensitive_key(key))
+            .cloned()
+            .chain(command.iter().flat_map(|arg| referenced_vars(arg)))
+            .collect();
+        secrets.sort();
+        secrets.dedup();
+        config
+            .env
+            .vars
+            .retain(|key, _| !redact::is_sensitive_key(key));
+
+        DeploymentPlan {
+            version: PLAN_VERSION,
+            network: config.project.network.clone(),
+            script: config.project.script.clone(),
+            git_sha,
+            command,
+            secrets,
+            config,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize plan")?;
+        fs::write(path, json).context(format!("Failed to write plan: {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read plan: {}", path.display()))?;
+        let plan: DeploymentPlan = serde_json::from_str(&content)
+            .context(format!("Failed to parse plan: {}", path.display()))?;
+
+        if plan.version != PLAN_VERSION {
+            anyhow::bail!(
+                "Unsupported plan version {} in {} (expected {})",
+                plan.version,
+                path.display(),
+                PLAN_VERSION
+            );
+        }
+
+        Ok(plan)
+    }
+
+    /// The config to replay, pinned to the planned network, script and commit
+    pub fn replay_config(&self) -> DeploymentConfig {
+        let mut config = self.config.clone();
+        config.project.network = self.network.clone();
+        config.project.script = self.script.clone();
+
+        if config.project.repo.is_some()
+            && let Some(sha) = &self.git_sha
+        {
+            config.project.git_branch = None;
+            config.project.git_ref = Some(sha.clone());
+        }
+
+        config
+    }
+}
+
+/// Names of the `${VAR}` references in `arg`
+fn referenced_vars(arg: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        names.push(rest[..end].to_string());
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plan_round_trip() {
+        let config: DeploymentConfig = toml::from_str(
+            r#"
+[project]
+name = "my-token"
+script = "Deploy"
+network = "sepolia"
+repo = "https://github.com/example/my-token.git"
+git_branch = "main"
+
+[env.vars]
+KEYSTORE_ACCOUNT = "deployer"
+KEYSTORE_PASSWORD = "hunter2"
+RPC_URL = "https://eth-sepolia.g.alchemy.com/v2/${ALCHEMY_API_KEY}"
+
+[networks.sepolia]
+chain_id = 11155111
+rpc_url = "${RPC_URL}"
+verify = false
+"#,
+        )
+        .unwrap();
+        let sha = "0123456789abcdef0123456789abcdef01234567".to_string();
+        let command = vec![
+            "forge".to_string(),
+            "--password".to_string(),
+            "${KEYSTORE_PASSWORD}".to_string(),
+            "--fork-url".to_string(),
+            "https://eth-mainnet.g.alchemy.com/v2/${ALCHEMY_API_KEY}".to_string(),
+        ];
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("plan.json");
+        DeploymentPlan::new(&config, Some(sha.clone()), command)
+            .write(&path)
+            .unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(!written.contains("hunter2"));
+        assert!(written.contains("${ALCHEMY_API_KEY}"));
+
+        let plan = DeploymentPlan::load(&path).unwrap();
+        assert_eq!(plan.secrets, ["ALCHEMY_API_KEY", "KEYSTORE_PASSWORD"]);
+        assert_eq!(plan.command[2], "${KEYSTORE_PASSWORD}");
+
+        let replay = plan.replay_config();
+        assert_eq!(replay.project.network, "sepolia");
+        assert_eq!(replay.project.script, "Deploy");
+        assert_eq!(replay.project.git_ref.as_deref(), Some(sha.as_str()));
+        assert_eq!(replay.project.git_branch, None);
+        assert_eq!(replay.env.vars["KEYSTORE_ACCOUNT"], "deployer");
+        assert!(!replay.env.vars.contains_key("KEYSTORE_PASSWORD"));
+        assert_eq!(replay.networks["sepolia"].rpc_url, "${RPC_URL}");
+    }
+}
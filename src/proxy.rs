@@ -0,0 +1,116 @@
+/// Proxy to use for `url`: `configured` when set, otherwise `HTTPS_PROXY` /
+/// `HTTP_PROXY` (or their lowercase forms) by scheme, unless the host matches
+/// `NO_PROXY`. ssh and scp-like remotes never go through a proxy.
+pub fn for_url(url: &str, configured: Option<&str>) -> Option<String> {
+    resolve(url, configured, |name| std::env::var(name).ok())
+}
+
+fn resolve(
+    url: &str,
+    configured: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let var = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| env(name).filter(|value| !value.trim().is_empty()))
+    };
+
+    let (scheme, rest) = url.split_once("://")?;
+    let names: &[&str] = match scheme.to_lowercase().as_str() {
+        "https" => &["HTTPS_PROXY", "https_proxy"],
+        "http" => &["HTTP_PROXY", "http_proxy"],
+        _ => return None,
+    };
+    if let Some(proxy) = configured.filter(|proxy| !proxy.trim().is_empty()) {
+        return Some(proxy.trim().to_string());
+    }
+
+    let proxy = var(names)?;
+
+    let no_proxy = var(&["NO_PROXY", "no_proxy"]).unwrap_or_default();
+    (!excluded(host(rest), &no_proxy)).then(|| proxy.trim().to_string())
+}
+
+/// Host of the part after `scheme://`, without credentials or port
+fn host(rest: &str) -> &str {
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    if let Some(bracketed) = authority.strip_prefix('[') {
+        return bracketed.split(']').next().unwrap_or_default();
+    }
+    authority.split(':').next().unwrap_or_default()
+}
+
+/// Whether `host` matches a `NO_PROXY` entry: `*`, the host itself, or a
+/// parent domain (`example.com` and `.example.com` both cover `rpc.example.com`).
+/// Ports in entries are ignored.
+fn excluded(host: &str, no_proxy: &str) -> bool {
+    let host = host.to_lowercase();
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let entry = match entry.rsplit_once(':') {
+                Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+                _ => &entry,
+            };
+            let domain = entry.trim_start_matches('.');
+            host == domain || host.ends_with(&format!(".{}", domain))
+        })
+}
+
+/// git2 proxy options for a clone or fetch through `proxy`; without one,
+/// libgit2 connects directly.
+pub fn git_options(proxy: Option<&str>) -> git2::ProxyOptions<'_> {
+    let mut options = git2::ProxyOptions::new();
+    if let Some(proxy) = proxy {
+        options.url(proxy);
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_proxy_resolution() {
+        let vars = HashMap::from([
+            ("HTTPS_PROXY", "http://proxy:3128"),
+            ("http_proxy", "http://plain:8080"),
+            ("NO_PROXY", "localhost, .internal.example,10.0.0.1:8545"),
+        ]);
+        let env = |name: &str| vars.get(name).map(|value| value.to_string());
+
+        let proxy = |url: &str, configured| resolve(url, configured, env);
+        assert_eq!(
+            proxy("https://github.com/example/token.git", None).as_deref(),
+            Some("http://proxy:3128")
+        );
+        assert_eq!(
+            proxy("http://node.example:8545", None).as_deref(),
+            Some("http://plain:8080")
+        );
+        assert_eq!(proxy("http://localhost:8545", None), None);
+        assert_eq!(proxy("https://rpc.internal.example/v1", None), None);
+        assert_eq!(proxy("https://internal.example", None), None);
+        assert_eq!(proxy("http://10.0.0.1:8545", None), None);
+        assert_eq!(proxy("git@github.com:example/token.git", None), None);
+        assert_eq!(proxy("ssh://git@github.com/example/token.git", None), None);
+        assert!(proxy("https://user:pw@notinternal.example", None).is_some());
+
+        // A configured proxy wins over the environment and NO_PROXY
+        assert_eq!(
+            proxy("http://localhost:8545", Some("http://tunnel:3128")).as_deref(),
+            Some("http://tunnel:3128")
+        );
+    }
+}
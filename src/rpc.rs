@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+
+use crate::redact;
+
+/// Timeout of a JSON-RPC request unless `rpc_timeout_secs` says otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of wei in one ether
+const WEI_PER_ETHER: u128 = 1_000_000_000_000_000_000;
+
+/// Minimal JSON-RPC client used for preflight checks against a network RPC
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+    timeout: Duration,
+}
+
+impl RpcClient {
+    /// Client whose requests give up after `timeout` instead of waiting on a dead endpoint,
+    /// connecting through `proxy` when one is given
+    pub fn new(url: &str, timeout: Duration, proxy: Option<&str>) -> Result<Self> {
+        let mut builder = ureq::AgentBuilder::new().timeout(timeout);
+        if let Some(proxy) = proxy {
+            let proxy = ureq::Proxy::new(proxy)
+                .context(format!("Invalid proxy: {}", redact::redact_url(proxy)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(RpcClient {
+            url: url.to_string(),
+            agent: builder.build(),
+            timeout,
+        })
+    }
+
+    /// Send a JSON-RPC request and return its `result` field
+    fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        // ureq errors embed the request URL, which may contain an API key
+        let started = Instant::now();
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(body)
+            .map_err(|e| match e {
+                ureq::Error::Status(code, _) => {
+                    anyhow::anyhow!("{} failed with HTTP {}", method, code)
+                }
+                ureq::Error::Transport(_) if started.elapsed() >= self.timeout => {
+                    anyhow::anyhow!("{} timed out after {}s", method, self.timeout.as_secs_f64())
+                }
+                ureq::Error::Transport(t) => anyhow::anyhow!("{} failed: {}", method, t.kind()),
+            })?
+            .into_json()
+            .context(format!("Failed to parse {} response", method))?;
+
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("{} returned an error: {}", method, error);
+        }
+
+        response
+            .get("result")
+            .cloned()
+            .context(format!("{} response has no result", method))
+    }
+
+    /// Get the balance (in wei) of an address at the latest block
+    pub fn get_balance(&self, address: &str) -> Result<u128> {
+        let result = self.request("eth_getBalance", json!([address, "latest"]))?;
+        parse_quantity(&result)
+    }
+
+    /// Get the runtime bytecode deployed at an address (`0x` when there is none)
+    pub fn get_code(&self, address: &str) -> Result<String> {
+        let result = self.request("eth_getCode", json!([address, "latest"]))?;
+
+        result
+            .as_str()
+            .map(str::to_string)
+            .context("eth_getCode result is not a hex string")
+    }
+
+    /// Chain id the node reports (`eth_chainId`)
+    pub fn chain_id(&self) -> Result<u64> {
+        let result = self.request("eth_chainId", json!([]))?;
+        u64::try_from(parse_quantity(&result)?).context("eth_chainId result is out of range")
+    }
+
+    /// Whether the node reports it is still syncing (`eth_syncing` returns an object)
+    pub fn is_syncing(&self) -> Result<bool> {
+        let result = self.request("eth_syncing", json!([]))?;
+        Ok(result != Value::Bool(false))
+    }
+
+    /// Get the timestamp (unix seconds) of the latest block
+    pub fn latest_block_timestamp(&self) -> Result<u64> {
+        let block = self.request("eth_getBlockByNumber", json!(["latest", false]))?;
+        let timestamp = block
+            .get("timestamp")
+            .context("Latest block has no timestamp")?;
+
+        u64::try_from(parse_quantity(timestamp)?).context("Block timestamp is out of range")
+    }
+
+    /// Execute a read-only `eth_call` and return the hex-encoded result
+    pub fn call(&self, to: &str, data: &str) -> Result<String> {
+        let result = self.request("eth_call", json!([{ "to": to, "data": data }, "latest"]))?;
+
+        result
+            .as_str()
+            .map(str::to_string)
+            .context("eth_call result is not a hex string")
+    }
+}
+
+/// Parse a hex-encoded JSON-RPC quantity such as `"0x1bc16d674ec80000"`
+pub fn parse_quantity(value: &Value) -> Result<u128> {
+    let text = value.as_str().context("Expected a hex quantity string")?;
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+
+    if digits.is_empty() {
+        return Ok(0);
+    }
+
+    u128::from_str_radix(digits, 16).context(format!("Invalid hex quantity: {}", text))
+}
+
+/// Format a wei amount as a decimal ETH string without trailing zeros
+pub fn format_ether(wei: u128) -> String {
+    let whole = wei / WEI_PER_ETHER;
+    let fraction = wei % WEI_PER_ETHER;
+
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:018}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Parse a decimal ETH amount (e.g. `"0.05"`) into wei
+pub fn parse_ether(amount: &str) -> Result<u128> {
+    let amount = amount.trim();
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+
+    if fraction.len() > 18 {
+        anyhow::bail!("ETH amount has more than 18 decimals: {}", amount);
+    }
+
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole
+            .parse()
+            .context(format!("Invalid ETH amount: {}", amount))?
+    };
+
+    let fraction: u128 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<18}", fraction)
+            .parse()
+            .context(format!("Invalid ETH amount: {}", amount))?
+    };
+
+    whole
+        .checked_mul(WEI_PER_ETHER)
+        .and_then(|w| w.checked_add(fraction))
+        .context(format!("ETH amount is too large: {}", amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_timeout() {
+        let url = crate::test_http::stalled();
+
+        let client = RpcClient::new(&url, Duration::from_millis(300), None).unwrap();
+        let started = Instant::now();
+        let err = client.chain_id().unwrap_err().to_string();
+
+        assert!(err.contains("eth_chainId timed out after 0.3s"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_ether_conversion() {
+        assert_eq!(format_ether(0), "0");
+        assert_eq!(format_ether(2_000_000_000_000_000_000), "2");
+        assert_eq!(format_ether(1_500_000_000_000_000), "0.0015");
+
+        assert_eq!(parse_ether("0.05").unwrap(), 50_000_000_000_000_000);
+        assert_eq!(parse_ether("1").unwrap(), WEI_PER_ETHER);
+        assert!(parse_ether("abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(
+            parse_quantity(&json!("0x1bc16d674ec80000")).unwrap(),
+            2 * WEI_PER_ETHER
+        );
+        assert_eq!(parse_quantity(&json!("0x0")).unwrap(), 0);
+        assert!(parse_quantity(&json!(12)).is_err());
+    }
+}
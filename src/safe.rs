@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+
+/// A preflight check that `--safe` turns on and makes mandatory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SafeCheck {
+    /// The RPC's `eth_chainId` must match the network's `chain_id`
+    ChainId,
+    /// `require_synced` on every network
+    Synced,
+    /// The deployer balance must be readable, non-zero and above `min_balance`
+    Balance,
+    /// `env.reject_empty_required = true`, whatever the config says
+    EmptyRequired,
+    /// Every `${VAR}` in the config must be defined
+    StrictExpansion,
+    /// The network name must be typed at the prompt; `-y` and `auto_confirm` are ignored
+    TypedConfirm,
+    /// `project.check_code_size`, with contracts over the EIP-170 limit failing the run
+    CodeSize,
+}
+
+impl SafeCheck {
+    pub const ALL: [SafeCheck; 7] = [
+        SafeCheck::ChainId,
+        SafeCheck::Synced,
+        SafeCheck::Balance,
+        SafeCheck::EmptyRequired,
+        SafeCheck::StrictExpansion,
+        SafeCheck::TypedConfirm,
+        SafeCheck::CodeSize,
+    ];
+}
+
+/// The checks enforced for this run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SafeMode {
+    checks: Vec<SafeCheck>,
+}
+
+impl SafeMode {
+    /// Every check when `safe` is set, minus the ones named in `--safe-skip`
+    pub fn new(safe: bool, skip: &[SafeCheck]) -> Self {
+        let checks = if safe {
+            SafeCheck::ALL
+                .into_iter()
+                .filter(|check| !skip.contains(check))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        SafeMode { checks }
+    }
+
+    /// Stop enforcing `check` for the rest of the run
+    pub fn skip(&mut self, check: SafeCheck) {
+        self.checks.retain(|&enforced| enforced != check);
+    }
+
+    pub fn requires(&self, check: SafeCheck) -> bool {
+        self.checks.contains(&check)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.checks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_mode_checks() {
+        let off = SafeMode::new(false, &[]);
+        assert!(!off.is_enabled());
+        assert!(SafeCheck::ALL.iter().all(|&check| !off.requires(check)));
+
+        let all = SafeMode::new(true, &[]);
+        assert!(SafeCheck::ALL.iter().all(|&check| all.requires(check)));
+
+        let partial = SafeMode::new(true, &[SafeCheck::Balance, SafeCheck::TypedConfirm]);
+        assert!(partial.requires(SafeCheck::ChainId));
+        assert!(partial.requires(SafeCheck::StrictExpansion));
+        assert!(!partial.requires(SafeCheck::Balance));
+        assert!(!partial.requires(SafeCheck::TypedConfirm));
+
+        let mut watched = SafeMode::new(true, &[]);
+        watched.skip(SafeCheck::TypedConfirm);
+        assert!(!watched.requires(SafeCheck::TypedConfirm));
+        assert!(watched.requires(SafeCheck::ChainId));
+    }
+}
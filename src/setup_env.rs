@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::environment;
+use crate::redact;
+
+/// Prompt for each missing variable and write the answers to the env file at `path`.
+///
+/// Variables already in the file are only replaced after confirmation; an empty
+/// answer skips the variable. Secrets are read without echo when `terminal` is set.
+/// Returns the names that were written.
+pub fn run(
+    path: &Path,
+    missing: &[String],
+    input: &mut dyn BufRead,
+    terminal: bool,
+) -> Result<Vec<String>> {
+    let mut lines: Vec<String> = if path.exists() {
+        fs::read_to_string(path)
+            .context(format!("Failed to read {}", path.display()))?
+            .lines()
+            .map(String::from)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut written = Vec::new();
+    for var in missing {
+        let existing = lines.iter().position(|line| line_key(line) == Some(var));
+        if existing.is_some() {
+            let answer = prompt(
+                &format!(
+                    "{} is already in {}, replace it? [y/N]: ",
+                    var,
+                    path.display()
+                ),
+                input,
+                false,
+            )?;
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                continue;
+            }
+        }
+
+        let secret = redact::is_sensitive_key(var);
+        let label = if secret {
+            format!("{} (hidden): ", var)
+        } else {
+            format!("{}: ", var)
+        };
+        let value = prompt(&label, input, secret && terminal)?;
+        if value.trim().is_empty() {
+            println!("{}", format!("Skipped {}", var).yellow());
+            continue;
+        }
+
+        let line = format!("{}={}", var, quote(&value));
+        match existing {
+            Some(index) => lines[index] = line,
+            None => lines.push(line),
+        }
+        written.push(var.clone());
+    }
+
+    if !written.is_empty() {
+        let mut content = lines.join("\n");
+        content.push('\n');
+        environment::write_private(path, &content)?;
+    }
+
+    Ok(written)
+}
+
+/// Variable name of a `KEY=value` (or `export KEY=value`) line
+fn line_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Double-quote values that other env file readers would cut at whitespace or
+/// `#`, or whose quotes the loader would strip, escaping `"` and `\` inside
+fn quote(value: &str) -> String {
+    if value.contains(|c: char| c.is_whitespace() || matches!(c, '#' | '"' | '\'')) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn prompt(label: &str, input: &mut dyn BufRead, hidden: bool) -> Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+
+    let echo_off = hidden && set_echo(false);
+    let mut answer = String::new();
+    let read = input.read_line(&mut answer);
+    if echo_off {
+        set_echo(true);
+        println!();
+    }
+    read?;
+
+    Ok(answer.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Toggle terminal echo with `stty`; `false` when that isn't possible
+#[cfg(unix)]
+fn set_echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn set_echo(_on: bool) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_setup_env_writes_answers() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(".env");
+        fs::write(&path, "# deployer\nKEYSTORE_ACCOUNT=\nRPC_KEY=abc\n").unwrap();
+
+        let missing = [
+            "KEYSTORE_ACCOUNT".to_string(),
+            "RPC_KEY".to_string(),
+            "KEYSTORE_PASSWORD".to_string(),
+            "BROADCAST_ACCOUNT".to_string(),
+            "LABEL".to_string(),
+        ];
+        // replace KEYSTORE_ACCOUNT, keep RPC_KEY, skip BROADCAST_ACCOUNT
+        let mut input = Cursor::new("y\ndeployer\nn\nhunter2\n\nmy token\n");
+        let written = run(&path, &missing, &mut input, false).unwrap();
+
+        assert_eq!(written, ["KEYSTORE_ACCOUNT", "KEYSTORE_PASSWORD", "LABEL"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# deployer\nKEYSTORE_ACCOUNT=deployer\nRPC_KEY=abc\nKEYSTORE_PASSWORD=hunter2\nLABEL=\"my token\"\n"
+        );
+    }
+
+    #[test]
+    fn test_setup_env_quotes_and_restricts_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(".env");
+
+        let missing = [
+            "KEYSTORE_PASSWORD".to_string(),
+            "LABEL".to_string(),
+            "PATH_HINT".to_string(),
+        ];
+        let mut input = Cursor::new("pass \"word\"\nmy#token\nC:\\bin\\\n");
+        run(&path, &missing, &mut input, false).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "KEYSTORE_PASSWORD=\"pass \\\"word\\\"\"\nLABEL=\"my#token\"\nPATH_HINT=C:\\bin\\\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // The loader reads back what was typed
+        let config = environment::tests::env_config(
+            HashMap::new(),
+            Some(vec![path.to_string_lossy().to_string()]),
+        );
+        let mut env = environment::Environment::new();
+        env.load_from_config(&config, Path::new(""), &[]).unwrap();
+        assert_eq!(
+            env.get("KEYSTORE_PASSWORD"),
+            Some(&"pass \"word\"".to_string())
+        );
+        assert_eq!(env.get("LABEL"), Some(&"my#token".to_string()));
+        assert_eq!(env.get("PATH_HINT"), Some(&"C:\\bin\\".to_string()));
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use colored::*;
+use std::collections::HashMap;
+
+use crate::broadcast::DeployedContract;
+
+/// Outcome of simulating one script without broadcasting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptEstimate {
+    pub script: String,
+    /// Sum of the gas limits forge estimated for the script's transactions
+    pub gas: u128,
+    pub contracts: Vec<DeployedContract>,
+}
+
+/// Env var through which a later script can read an earlier simulated address, e.g. `MY_TOKEN_ADDRESS`
+pub fn address_var(contract_name: &str) -> String {
+    let mut name = String::new();
+    let mut previous_lower = false;
+
+    for c in contract_name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            name.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        name.push(if c.is_ascii_alphanumeric() {
+            c.to_ascii_uppercase()
+        } else {
+            '_'
+        });
+    }
+
+    format!("{}_ADDRESS", name)
+}
+
+/// Simulate `scripts` in order, stopping at the first failure.
+///
+/// Every script sees the addresses created by the scripts before it as
+/// `<CONTRACT>_ADDRESS` variables. `simulate` runs one script with those
+/// variables and is injected so the ordering can be tested without forge.
+pub fn simulate_in_order<F>(scripts: &[String], mut simulate: F) -> Result<Vec<ScriptEstimate>>
+where
+    F: FnMut(&str, &HashMap<String, String>) -> Result<ScriptEstimate>,
+{
+    let mut addresses = HashMap::new();
+    let mut estimates = Vec::new();
+
+    for script in scripts {
+        let estimate = simulate(script, &addresses)?;
+        for contract in &estimate.contracts {
+            addresses.insert(address_var(&contract.name), contract.address.clone());
+        }
+        estimates.push(estimate);
+    }
+
+    Ok(estimates)
+}
+
+/// Print the per-script gas estimates and their total
+pub fn print_estimates(estimates: &[ScriptEstimate]) {
+    let width = estimates
+        .iter()
+        .map(|e| e.script.len())
+        .max()
+        .unwrap_or_default()
+        .max("SCRIPT".len());
+
+    println!(
+        "\n{}",
+        format!("{:<width$}  {:>14}  CONTRACTS", "SCRIPT", "GAS").blue()
+    );
+    for estimate in estimates {
+        println!(
+            "{:<width$}  {:>14}  {}",
+            estimate.script,
+            estimate.gas,
+            estimate.contracts.len()
+        );
+    }
+
+    let total: u128 = estimates.iter().map(|e| e.gas).sum();
+    println!("{:<width$}  {:>14}", "TOTAL", total.to_string().green());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulate_in_order_passes_addresses() {
+        let scripts = vec![
+            "DeployToken.s.sol".to_string(),
+            "DeployVault.s.sol".to_string(),
+        ];
+        let mut seen = Vec::new();
+
+        let estimates = simulate_in_order(&scripts, |script, addresses| {
+            seen.push((
+                script.to_string(),
+                addresses.get("MY_TOKEN_ADDRESS").cloned(),
+            ));
+            let (name, gas) = match script {
+                "DeployToken.s.sol" => ("MyToken", 1_200_000),
+                _ => ("Vault", 800_000),
+            };
+            Ok(ScriptEstimate {
+                script: script.to_string(),
+                gas,
+                contracts: vec![DeployedContract {
+                    name: name.to_string(),
+                    address: "0x5fbdb2315678afecb367f032d93f642f64180aa3".to_string(),
+                }],
+            })
+        })
+        .unwrap();
+
+        assert_eq!(
+            seen,
+            vec![
+                ("DeployToken.s.sol".to_string(), None),
+                (
+                    "DeployVault.s.sol".to_string(),
+                    Some("0x5fbdb2315678afecb367f032d93f642f64180aa3".to_string())
+                ),
+            ]
+        );
+        assert_eq!(estimates.iter().map(|e| e.gas).sum::<u128>(), 2_000_000);
+
+        let failed = simulate_in_order(&scripts, |script, _| anyhow::bail!("{} reverted", script));
+        assert!(failed.unwrap_err().to_string().contains("DeployToken"));
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::broadcast::DeployedContract;
+
+/// Sourcify server used when the network sets no `verifier_url`
+pub const DEFAULT_SERVER: &str = "https://sourcify.dev/server";
+
+/// Timeout for one verification request; Sourcify recompiles before answering
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How closely the deployed bytecode matched the uploaded sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Bytecode and metadata hash match ("perfect" in the Sourcify API)
+    Full,
+    /// Bytecode matches but the metadata hash differs
+    Partial,
+}
+
+impl std::fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchKind::Full => write!(f, "full match"),
+            MatchKind::Partial => write!(f, "partial match"),
+        }
+    }
+}
+
+/// Find the forge artifact for a contract name anywhere below `out_dir`:
+/// `<File>.sol/<Name>.json`, or `<Name>.<solc version>.json` when several
+/// compiler versions were used
+pub fn find_artifact(out_dir: &Path, name: &str) -> Result<PathBuf> {
+    let mut found = Vec::new();
+    collect_artifacts(out_dir, name, &mut found)
+        .context(format!("Failed to read {}", out_dir.display()))?;
+    found.sort();
+
+    match found.len() {
+        0 => anyhow::bail!("No artifact for {} in {}", name, out_dir.display()),
+        1 => Ok(found.remove(0)),
+        _ => anyhow::bail!(
+            "Several artifacts are named {}: {}",
+            name,
+            found
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn collect_artifacts(dir: &Path, name: &str, found: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_artifacts(&path, name, found)?;
+        } else if path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .is_some_and(|file_name| is_artifact_of(file_name, name))
+        {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `Name.json`, or `Name.0.8.20.json` for a versioned build
+fn is_artifact_of(file_name: &str, name: &str) -> bool {
+    let Some(rest) = file_name
+        .strip_prefix(name)
+        .and_then(|rest| rest.strip_suffix(".json"))
+    else {
+        return false;
+    };
+    match rest.strip_prefix('.') {
+        None => rest.is_empty(),
+        Some(version) => {
+            let parts: Vec<&str> = version.split('.').collect();
+            parts.len() == 3
+                && parts
+                    .iter()
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        }
+    }
+}
+
+/// The metadata file and every source it lists, keyed as Sourcify expects
+pub fn collect_files(project_dir: &Path, artifact: &Path) -> Result<Map<String, Value>> {
+    let content = fs::read_to_string(artifact)
+        .context(format!("Failed to read artifact: {}", artifact.display()))?;
+    let artifact_json: Value = serde_json::from_str(&content)
+        .context(format!("Failed to parse artifact: {}", artifact.display()))?;
+
+    // forge keeps the exact compiler metadata in `rawMetadata`
+    let raw_metadata = match (
+        artifact_json.get("rawMetadata").and_then(Value::as_str),
+        artifact_json.get("metadata"),
+    ) {
+        (Some(raw), _) => raw.to_string(),
+        (None, Some(metadata)) if metadata.is_object() => metadata.to_string(),
+        _ => anyhow::bail!(
+            "{} has no metadata; build with forge's default extra_output",
+            artifact.display()
+        ),
+    };
+    let metadata: Value =
+        serde_json::from_str(&raw_metadata).context("Failed to parse contract metadata")?;
+
+    let sources = metadata
+        .get("sources")
+        .and_then(Value::as_object)
+        .context("Contract metadata lists no sources")?;
+
+    let mut files = Map::new();
+    files.insert("metadata.json".to_string(), Value::String(raw_metadata));
+    for source in sources.keys() {
+        let path = project_dir.join(source);
+        let text = fs::read_to_string(&path)
+            .context(format!("Failed to read source: {}", path.display()))?;
+        files.insert(source.clone(), Value::String(text));
+    }
+
+    Ok(files)
+}
+
+/// Upload metadata and sources for one deployed contract and return the match Sourcify reports
+pub fn verify_contract(
+    server: &str,
+    project_dir: &Path,
+    out_dir: &Path,
+    contract: &DeployedContract,
+    chain_id: u64,
+) -> Result<MatchKind> {
+    let artifact = find_artifact(out_dir, &contract.name)?;
+    let files = collect_files(project_dir, &artifact)?;
+
+    let body = json!({
+        "address": contract.address,
+        "chain": chain_id.to_string(),
+        "files": files,
+    });
+
+    let url = format!("{}/verify", server.trim_end_matches('/'));
+    let response: Value = match ureq::post(&url).timeout(REQUEST_TIMEOUT).send_json(body) {
+        Ok(response) => response
+            .into_json()
+            .context("Failed to parse Sourcify response")?,
+        Err(ureq::Error::Status(code, response)) => {
+            let reason = response
+                .into_json::<Value>()
+                .ok()
+                .and_then(|body| body.get("error").and_then(Value::as_str).map(String::from))
+                .unwrap_or_else(|| format!("HTTP {}", code));
+            anyhow::bail!("Sourcify rejected the verification: {}", reason);
+        }
+        Err(ureq::Error::Transport(t)) => {
+            anyhow::bail!("Sourcify request failed: {}", transport_detail(&t))
+        }
+    };
+
+    let status = response
+        .pointer("/result/0/status")
+        .and_then(Value::as_str)
+        .context("Sourcify response has no match status")?;
+
+    match status {
+        "perfect" | "full" => Ok(MatchKind::Full),
+        "partial" => Ok(MatchKind::Partial),
+        other => anyhow::bail!("Sourcify did not match the contract (status: {})", other),
+    }
+}
+
+/// What went wrong with a request, without the URL the transport error embeds
+fn transport_detail(error: &ureq::Transport) -> String {
+    let mut detail = error.kind().to_string();
+    if let Some(message) = error.message() {
+        detail.push_str(&format!(": {}", message));
+    }
+    if let Some(source) = std::error::Error::source(error) {
+        detail.push_str(&format!(": {}", source));
+    }
+    detail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::serve_once;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sourcify_upload() {
+        let temp_dir = tempdir().unwrap();
+        let project_dir = temp_dir.path();
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src/Token.sol"), "contract Token {}").unwrap();
+
+        let out_dir = project_dir.join("out");
+        fs::create_dir_all(out_dir.join("Token.sol")).unwrap();
+        let metadata = json!({
+            "language": "Solidity",
+            "settings": { "compilationTarget": { "src/Token.sol": "Token" } },
+            "sources": { "src/Token.sol": { "keccak256": "0x00" } },
+        })
+        .to_string();
+        fs::write(
+            out_dir.join("Token.sol/Token.json"),
+            json!({ "rawMetadata": metadata }).to_string(),
+        )
+        .unwrap();
+
+        let contract = DeployedContract {
+            name: "Token".to_string(),
+            address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+        };
+
+        let (server, request) = serve_once(
+            r#"{"result":[{"address":"0x5FbDB2315678afecb367f032d93F642f64180aa3","chainId":"100","status":"partial"}]}"#,
+        );
+        let matched = verify_contract(&server, project_dir, &out_dir, &contract, 100).unwrap();
+        assert_eq!(matched, MatchKind::Partial);
+
+        let body: Value = serde_json::from_str(&request.join().unwrap()).unwrap();
+        assert_eq!(body["chain"], "100");
+        assert_eq!(body["address"], contract.address);
+        assert_eq!(body["files"]["src/Token.sol"], "contract Token {}");
+        assert_eq!(body["files"]["metadata.json"], metadata);
+
+        let missing = DeployedContract {
+            name: "Vault".to_string(),
+            ..contract.clone()
+        };
+        let err = verify_contract(&server, project_dir, &out_dir, &missing, 100).unwrap_err();
+        assert!(err.to_string().contains("No artifact for Vault"));
+
+        // A refused connection says why
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let err = verify_contract(&server, project_dir, &out_dir, &contract, 100).unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("Sourcify request failed: Connection Failed: "));
+        assert!(message.to_lowercase().contains("refused"), "{}", message);
+    }
+
+    #[test]
+    fn test_find_artifact() {
+        let temp_dir = tempdir().unwrap();
+        let out_dir = temp_dir.path();
+        for artifact in [
+            "Token.sol/Token.json",
+            "Token.sol/TokenFactory.json",
+            "lib/Math.sol/Math.0.8.20.json",
+            "Vault.sol/Vault.0.8.19.json",
+            "Vault.sol/Vault.0.8.24.json",
+        ] {
+            let path = out_dir.join(artifact);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+
+        assert_eq!(
+            find_artifact(out_dir, "Token").unwrap(),
+            out_dir.join("Token.sol/Token.json")
+        );
+        assert_eq!(
+            find_artifact(out_dir, "Math").unwrap(),
+            out_dir.join("lib/Math.sol/Math.0.8.20.json")
+        );
+        let err = find_artifact(out_dir, "Vault").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Several artifacts are named Vault")
+        );
+        assert!(find_artifact(out_dir, "Tok").is_err());
+    }
+}
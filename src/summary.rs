@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::broadcast::DeployedContract;
+
+/// A deployed contract with its block explorer link, if the network has one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContractSummary {
+    pub name: String,
+    pub address: String,
+    pub explorer_url: Option<String>,
+    /// Outcome of deferred verification, `None` when it did not run
+    pub verified: Option<bool>,
+}
+
+impl ContractSummary {
+    pub fn new(contract: DeployedContract, explorer_url: Option<&str>) -> Self {
+        let explorer_url = explorer_url.map(|base| {
+            format!(
+                "{}/address/{}",
+                base.trim_end_matches('/'),
+                contract.address
+            )
+        });
+
+        ContractSummary {
+            name: contract.name,
+            address: contract.address,
+            explorer_url,
+            verified: None,
+        }
+    }
+}
+
+/// How a deployment run ended without a hard failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentStatus {
+    /// The script was broadcast successfully
+    Succeeded,
+    /// The confirmation prompt was declined
+    Cancelled,
+    /// The confirmation prompt got no answer within `--confirm-timeout`
+    TimedOut,
+    /// Only the clone plan was printed (`--explain`)
+    Explained,
+    /// The scripts were simulated without broadcasting (`--simulate-all`)
+    Simulated,
+    /// A plan was written with `--plan-out` instead of broadcasting
+    Planned,
+    /// The forge command was printed with `--emit-command` instead of being run
+    Emitted,
+    /// `--only-if-changed` found the commit already deployed
+    Unchanged,
+}
+
+impl fmt::Display for DeploymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            DeploymentStatus::Succeeded => "succeeded",
+            DeploymentStatus::Cancelled => "cancelled",
+            DeploymentStatus::TimedOut => "timed out",
+            DeploymentStatus::Explained => "explained",
+            DeploymentStatus::Simulated => "simulated",
+            DeploymentStatus::Planned => "planned",
+            DeploymentStatus::Emitted => "emitted",
+            DeploymentStatus::Unchanged => "unchanged",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// Everything a deployment run produced, used for the end-of-run summary
+#[derive(Debug, Clone, Serialize)]
+pub struct DeploymentResult {
+    pub status: DeploymentStatus,
+    pub project: String,
+    pub network: String,
+    pub chain_id: Option<u64>,
+    /// Commit of the deployed checkout, when it is a git repository
+    pub git_sha: Option<String>,
+    pub script: String,
+    /// Sender address (`BROADCAST_ACCOUNT`)
+    pub deployer: Option<String>,
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    pub forge_exit_code: Option<i32>,
+    pub contracts: Vec<ContractSummary>,
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64((duration.as_secs_f64() * 100.0).round() / 100.0)
+}
+
+impl DeploymentResult {
+    /// Result of a run that did not broadcast anything
+    pub fn not_deployed(
+        status: DeploymentStatus,
+        project: &str,
+        network: &str,
+        script: &str,
+    ) -> Self {
+        DeploymentResult {
+            status,
+            project: project.to_string(),
+            network: network.to_string(),
+            chain_id: None,
+            git_sha: None,
+            script: script.to_string(),
+            deployer: None,
+            duration: Duration::ZERO,
+            forge_exit_code: None,
+            contracts: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize deployment result")
+    }
+
+    /// Render the summary as GitHub-flavored markdown, for `$GITHUB_STEP_SUMMARY`
+    pub fn to_markdown(&self) -> String {
+        let optional = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| format!("`{}`", v))
+                .unwrap_or_else(|| "-".to_string())
+        };
+
+        let mut markdown = format!("### Deployment: {} on {}\n\n", self.project, self.network);
+        markdown.push_str("| | |\n|---|---|\n");
+        markdown.push_str(&format!("| Status | {} |\n", self.status));
+        if let Some(chain_id) = self.chain_id {
+            markdown.push_str(&format!("| Chain ID | {} |\n", chain_id));
+        }
+        markdown.push_str(&format!("| Git SHA | {} |\n", optional(&self.git_sha)));
+        markdown.push_str(&format!("| Script | `{}` |\n", self.script));
+        markdown.push_str(&format!("| Deployer | {} |\n", optional(&self.deployer)));
+        markdown.push_str(&format!(
+            "| Duration | {:.1}s |\n",
+            self.duration.as_secs_f64()
+        ));
+
+        if !self.contracts.is_empty() {
+            markdown.push_str("\n| Contract | Address | Verified |\n|---|---|---|\n");
+            for contract in &self.contracts {
+                let address = match &contract.explorer_url {
+                    Some(url) => format!("[`{}`]({})", contract.address, url),
+                    None => format!("`{}`", contract.address),
+                };
+                let verified = match contract.verified {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "-",
+                };
+                markdown.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    contract.name, address, verified
+                ));
+            }
+        }
+
+        markdown.push('\n');
+        markdown
+    }
+
+    /// Append the markdown summary to a file such as `$GITHUB_STEP_SUMMARY`
+    pub fn append_markdown(&self, path: &Path) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open {}", path.display()))?;
+
+        file.write_all(self.to_markdown().as_bytes())
+            .context(format!("Failed to write {}", path.display()))
+    }
+
+    pub fn print_human(&self) {
+        println!(
+            "\n{}",
+            "════════════════════════════════════ DEPLOYMENT SUMMARY ════════════════════════════════════".green()
+        );
+
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let rows = [
+            ("PROJECT", self.project.clone()),
+            ("NETWORK", self.network.clone()),
+            ("STATUS", self.status.to_string()),
+            (
+                "CHAIN_ID",
+                self.chain_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            ("GIT_SHA", optional(&self.git_sha)),
+            ("SCRIPT", self.script.clone()),
+            ("DEPLOYER", optional(&self.deployer)),
+            ("DURATION", format!("{:.1}s", self.duration.as_secs_f64())),
+            (
+                "FORGE_EXIT_CODE",
+                self.forge_exit_code
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+        ];
+        for (label, value) in rows {
+            println!(
+                "{}{}",
+                format!("{:<17}", format!("{}:", label)).blue(),
+                value
+            );
+        }
+
+        println!();
+        if self.contracts.is_empty() {
+            println!("No contracts deployed");
+        } else {
+            let name_width = self
+                .contracts
+                .iter()
+                .map(|c| c.name.len())
+                .max()
+                .unwrap_or_default()
+                .max("CONTRACT".len());
+
+            let show_verified = self.contracts.iter().any(|c| c.verified.is_some());
+            let verified_header = if show_verified { "VERIFIED  " } else { "" };
+
+            let header = format!(
+                "{:<name_width$}  {:<42}  {}EXPLORER",
+                "CONTRACT", "ADDRESS", verified_header
+            );
+            println!("{}", header.blue());
+            for contract in &self.contracts {
+                let verified = match (show_verified, contract.verified) {
+                    (false, _) => String::new(),
+                    (true, Some(true)) => format!("{:<10}", "yes"),
+                    (true, Some(false)) => format!("{:<10}", "no"),
+                    (true, None) => format!("{:<10}", "-"),
+                };
+                println!(
+                    "{:<name_width$}  {:<42}  {}{}",
+                    contract.name,
+                    contract.address,
+                    verified,
+                    contract.explorer_url.as_deref().unwrap_or("-")
+                );
+            }
+        }
+
+        println!(
+            "{}",
+            "═══════════════════════════════════════════════════════════════════════════════════════".green()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_result() -> DeploymentResult {
+        DeploymentResult {
+            status: DeploymentStatus::Succeeded,
+            project: "my-token".to_string(),
+            network: "sepolia".to_string(),
+            chain_id: Some(11155111),
+            git_sha: Some("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_string()),
+            script: "Deploy.s.sol".to_string(),
+            deployer: Some("0xaa31349a2eF4A37Dc4Dd742E3b0E32182F524A6A".to_string()),
+            duration: Duration::from_millis(12_340),
+            forge_exit_code: Some(0),
+            contracts: vec![ContractSummary::new(
+                DeployedContract {
+                    name: "MyToken".to_string(),
+                    address: "0x5fbdb2315678afecb367f032d93f642f64180aa3".to_string(),
+                },
+                Some("https://sepolia.etherscan.io/"),
+            )],
+        }
+    }
+
+    #[test]
+    fn test_result_json() {
+        let result = sample_result();
+
+        let json: serde_json::Value = serde_json::from_str(&result.to_json().unwrap()).unwrap();
+        assert_eq!(json["status"], "succeeded");
+        assert_eq!(json["network"], "sepolia");
+        assert_eq!(json["chain_id"], 11155111);
+        assert_eq!(json["duration_secs"], 12.34);
+        assert_eq!(json["forge_exit_code"], 0);
+        assert_eq!(json["contracts"][0]["name"], "MyToken");
+        assert_eq!(
+            json["contracts"][0]["explorer_url"],
+            "https://sepolia.etherscan.io/address/0x5fbdb2315678afecb367f032d93f642f64180aa3"
+        );
+    }
+
+    #[test]
+    fn test_github_summary_markdown() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let summary_path = temp_dir.path().join("step_summary.md");
+        let result = sample_result();
+
+        result.append_markdown(&summary_path).unwrap();
+        result.append_markdown(&summary_path).unwrap();
+
+        let markdown = std::fs::read_to_string(&summary_path).unwrap();
+        assert_eq!(
+            markdown
+                .matches("### Deployment: my-token on sepolia")
+                .count(),
+            2
+        );
+        assert!(markdown.contains("| Status | succeeded |"));
+        assert!(markdown.contains("| Git SHA | `4b825dc642cb6eb9a060e54bf8d69288fbee4904` |"));
+        assert!(markdown.contains(
+            "| MyToken | [`0x5fbdb2315678afecb367f032d93f642f64180aa3`](https://sepolia.etherscan.io/address/0x5fbdb2315678afecb367f032d93f642f64180aa3) | - |"
+        ));
+    }
+}
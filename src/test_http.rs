@@ -0,0 +1,126 @@
+//! Local HTTP servers standing in for RPC nodes and APIs in tests
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+
+/// Answer every request on a local port with the JSON body `respond` returns for
+/// the request body, until the test process exits. Returns the server URL.
+pub fn serve(respond: impl Fn(&str) -> String + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let mut reader = BufReader::new(stream);
+            let Some(body) = read_body(&mut reader) else {
+                continue;
+            };
+            let _ = write_response(reader.get_mut(), "200 OK", &respond(&body));
+        }
+    });
+
+    url
+}
+
+/// Answer one request with `response`, returning the request body
+pub fn serve_once(response: &str) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let response = response.to_string();
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let body = read_body(&mut reader).unwrap();
+        write_response(reader.get_mut(), "200 OK", &response).unwrap();
+        body
+    });
+
+    (url, handle)
+}
+
+/// Refuse one request with a 502, as a proxy would, returning its request line
+pub fn refuse_once() -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let _ = write_response(reader.get_mut(), "502 Bad Gateway", "");
+        line
+    });
+
+    (url, handle)
+}
+
+/// Accept connections and never answer, until the test process exits
+pub fn stalled() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let _held: Vec<_> = listener.incoming().collect();
+    });
+    url
+}
+
+/// JSON-RPC node answering each method in `results` with its result, and any
+/// other method with an error
+pub fn rpc_node(results: &[(&str, Value)]) -> String {
+    let results: Vec<(String, Value)> = results
+        .iter()
+        .map(|(method, result)| (method.to_string(), result.clone()))
+        .collect();
+
+    serve(move |body| {
+        let request: Value = serde_json::from_str(body).unwrap_or_default();
+        let method = request["method"].as_str().unwrap_or_default();
+        let response = match results.iter().find(|(name, _)| name == method) {
+            Some((_, result)) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": "method not found" }
+            }),
+        };
+        response.to_string()
+    })
+}
+
+/// Body of the HTTP request on `reader`, read up to its `Content-Length`
+fn read_body(reader: &mut BufReader<TcpStream>) -> Option<String> {
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+            content_length = value.trim().parse().ok()?;
+        }
+        if line == "\r\n" {
+            break;
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    String::from_utf8(body).ok()
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+/// Result of the `validate` subcommand, shared by the human and JSON output
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub network: Option<String>,
+    pub script: Option<String>,
+    pub passed: bool,
+    pub checks: Vec<ValidationCheck>,
+}
+
+impl ValidationReport {
+    pub fn new(network: Option<String>, script: Option<String>) -> Self {
+        ValidationReport {
+            network,
+            script,
+            passed: true,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, status: CheckStatus, message: impl Into<String>) {
+        if status == CheckStatus::Fail {
+            self.passed = false;
+        }
+
+        self.checks.push(ValidationCheck {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        });
+    }
+
+    /// Record a check from a `Result`, using the error chain as the failure message
+    pub fn add_result(&mut self, name: &str, result: Result<String>) {
+        match result {
+            Ok(message) => self.add(name, CheckStatus::Pass, message),
+            Err(e) => self.add(name, CheckStatus::Fail, format!("{:#}", e)),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize validation report")
+    }
+
+    pub fn print_human(&self) {
+        println!("\n{}", "Validating deployment configuration...".blue());
+
+        if let Some(network) = &self.network {
+            println!("{}: {}", "NETWORK".blue(), network);
+        }
+        if let Some(script) = &self.script {
+            println!("{}: {}", "SCRIPT".blue(), script);
+        }
+        println!();
+
+        for check in &self.checks {
+            let marker = match check.status {
+                CheckStatus::Pass => "✓".green(),
+                CheckStatus::Fail => "✗".red(),
+                CheckStatus::Skip => "-".yellow(),
+            };
+            println!("{} {}: {}", marker, check.name, check.message);
+        }
+
+        println!();
+        if self.passed {
+            println!("{}", "Validation passed!".green());
+        } else {
+            println!("{}", "Validation failed!".red());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let mut report =
+            ValidationReport::new(Some("sepolia".to_string()), Some("Deploy.s.sol".into()));
+        report.add("config", CheckStatus::Pass, "Loaded deploy.toml");
+        report.add_result("network", Err(anyhow::anyhow!("Network 'foo' not found")));
+        report.add("script", CheckStatus::Skip, "Checked after cloning");
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["network"], "sepolia");
+        assert_eq!(json["script"], "Deploy.s.sol");
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["checks"][0]["status"], "pass");
+        assert_eq!(json["checks"][1]["name"], "network");
+        assert_eq!(json["checks"][1]["status"], "fail");
+        assert_eq!(json["checks"][1]["message"], "Network 'foo' not found");
+        assert_eq!(json["checks"][2]["status"], "skip");
+    }
+}
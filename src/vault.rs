@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::VaultConfig;
+
+/// Timeout for each Vault request
+const VAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fetch every configured secret, returning a map of env var name to value.
+///
+/// Error messages name the secret path and key but never include values.
+pub fn fetch_secrets(
+    config: &VaultConfig,
+    address: &str,
+    token: &str,
+) -> Result<HashMap<String, String>> {
+    let agent = ureq::AgentBuilder::new().timeout(VAULT_TIMEOUT).build();
+    let address = address.trim_end_matches('/');
+
+    // Several keys usually come from the same path, so fetch each path once
+    let mut responses: HashMap<&str, Value> = HashMap::new();
+    let mut secrets = HashMap::new();
+
+    for secret in &config.secrets {
+        if !responses.contains_key(secret.path.as_str()) {
+            let url = format!("{}/v1/{}", address, secret.path.trim_start_matches('/'));
+            let response: Value = agent
+                .get(&url)
+                .set("X-Vault-Token", token)
+                .call()
+                .map_err(|e| match e {
+                    ureq::Error::Status(code, _) => anyhow::anyhow!(
+                        "Vault request for '{}' failed with HTTP {}",
+                        secret.path,
+                        code
+                    ),
+                    ureq::Error::Transport(t) => {
+                        anyhow::anyhow!("Vault request for '{}' failed: {}", secret.path, t.kind())
+                    }
+                })?
+                .into_json()
+                .context(format!(
+                    "Failed to parse Vault response for '{}'",
+                    secret.path
+                ))?;
+            responses.insert(secret.path.as_str(), response);
+        }
+
+        let value =
+            extract_secret(&responses[secret.path.as_str()], &secret.key).context(format!(
+                "Key '{}' not found in Vault secret '{}'",
+                secret.key, secret.path
+            ))?;
+        secrets.insert(secret.var.clone(), value);
+    }
+
+    Ok(secrets)
+}
+
+/// Read a key from a KV v2 (`data.data.key`) or KV v1 (`data.key`) response
+fn extract_secret(response: &Value, key: &str) -> Option<String> {
+    let data = response.get("data")?;
+    let value = data
+        .get("data")
+        .and_then(|inner| inner.get(key))
+        .or_else(|| data.get(key))?;
+
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_secret_kv_versions() {
+        let kv2 = json!({ "data": { "data": { "password": "p2" }, "metadata": {} } });
+        let kv1 = json!({ "data": { "password": "p1", "port": 8545 } });
+
+        assert_eq!(extract_secret(&kv2, "password"), Some("p2".to_string()));
+        assert_eq!(extract_secret(&kv1, "password"), Some("p1".to_string()));
+        assert_eq!(extract_secret(&kv1, "port"), Some("8545".to_string()));
+        assert_eq!(extract_secret(&kv1, "missing"), None);
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::broadcast::DeployedContract;
+use crate::config::NetworkConfig;
+use crate::sourcify;
+
+/// When to attempt deferred verification: wait `delay` first, then retry with doubling backoff
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub delay: Duration,
+    pub retries: u32,
+}
+
+/// Run `attempt` after the initial delay, retrying up to `policy.retries` more times.
+///
+/// The wait doubles after every failed attempt. `sleep` is injected so the
+/// schedule can be tested without waiting.
+pub fn with_retries<F, S>(policy: RetryPolicy, mut sleep: S, mut attempt: F) -> Result<()>
+where
+    F: FnMut(u32) -> Result<()>,
+    S: FnMut(Duration),
+{
+    let mut wait = policy.delay;
+    let mut tries = 0;
+
+    loop {
+        sleep(wait);
+        tries += 1;
+
+        match attempt(tries) {
+            Ok(()) => return Ok(()),
+            Err(e) if tries > policy.retries => return Err(e),
+            Err(_) => wait = (wait * 2).max(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// `forge verify-contract` for one contract with the network's verifier settings
+pub fn verify_command(contract: &DeployedContract, network: &NetworkConfig) -> Command {
+    let mut cmd = Command::new("forge");
+    cmd.arg("verify-contract")
+        .arg(&contract.address)
+        .arg(&contract.name)
+        .arg("--chain")
+        .arg(network.chain_id.to_string())
+        .arg("--rpc-url")
+        .arg(&network.rpc_url);
+    if let Some(verifier) = &network.verifier {
+        cmd.arg("--verifier").arg(verifier);
+    }
+    if let Some(verifier_url) = &network.verifier_url {
+        cmd.arg("--verifier-url").arg(verifier_url);
+    }
+    cmd.arg("--guess-constructor-args").arg("--watch");
+    cmd
+}
+
+/// Verify one deployed contract with `forge verify-contract`, waiting for the explorer's verdict
+pub fn verify_contract(
+    project_dir: &Path,
+    contract: &DeployedContract,
+    network: &NetworkConfig,
+    env: &HashMap<String, String>,
+) -> Result<()> {
+    let output = verify_command(contract, network)
+        .current_dir(project_dir)
+        .envs(env)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run forge verify-contract")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let reason = stderr
+            .lines()
+            .chain(stdout.lines())
+            .rfind(|line| !line.trim().is_empty())
+            .unwrap_or("unknown error");
+        anyhow::bail!("{}", reason.trim());
+    }
+
+    Ok(())
+}
+
+/// Verify every contract, returning why each one that didn't end up verified failed.
+///
+/// With `verifier = "sourcify"` the artifact metadata and sources below
+/// `out_dir` are uploaded to the Sourcify API directly, and the full or
+/// partial match is reported per contract.
+pub fn verify_all(
+    project_dir: &Path,
+    out_dir: &Path,
+    contracts: &[DeployedContract],
+    network: &NetworkConfig,
+    env: &HashMap<String, String>,
+    policy: RetryPolicy,
+) -> Vec<Result<()>> {
+    println!(
+        "{}",
+        format!(
+            "Verifying {} contract(s) in {}s (up to {} retries)...",
+            contracts.len(),
+            policy.delay.as_secs(),
+            policy.retries
+        )
+        .blue()
+    );
+
+    contracts
+        .iter()
+        .map(|contract| {
+            let mut matched = None;
+            let result = with_retries(policy, thread::sleep, |attempt| {
+                println!("Verifying {} (attempt {})", contract.name, attempt);
+                if network.verifier.as_deref() == Some("sourcify") {
+                    let server = network
+                        .verifier_url
+                        .as_deref()
+                        .unwrap_or(sourcify::DEFAULT_SERVER);
+                    matched = Some(sourcify::verify_contract(
+                        server,
+                        project_dir,
+                        out_dir,
+                        contract,
+                        network.chain_id,
+                    )?);
+                    Ok(())
+                } else {
+                    verify_contract(project_dir, contract, network, env)
+                }
+            });
+
+            if result.is_ok() {
+                match matched {
+                    Some(kind) => println!(
+                        "{} {} verified on Sourcify ({})",
+                        "✓".green(),
+                        contract.name,
+                        kind
+                    ),
+                    None => println!("{} {} verified", "✓".green(), contract.name),
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            delay: Duration::from_secs(10),
+            retries: 3,
+        };
+
+        let mut waits = Vec::new();
+        let result = with_retries(
+            policy,
+            |wait| waits.push(wait.as_secs()),
+            |attempt| {
+                if attempt < 3 {
+                    anyhow::bail!("not indexed yet");
+                }
+                Ok(())
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(waits, vec![10, 20, 40]);
+
+        let mut attempts = 0;
+        let result = with_retries(
+            policy,
+            |_| {},
+            |_| {
+                attempts += 1;
+                anyhow::bail!("explorer down")
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn test_verify_command() {
+        let network: NetworkConfig = toml::from_str(
+            r#"
+chain_id = 100
+rpc_url = "https://rpc.gnosischain.com"
+verify = true
+verifier = "blockscout"
+verifier_url = "https://gnosis.blockscout.com/api/"
+"#,
+        )
+        .unwrap();
+        let contract = DeployedContract {
+            name: "src/Token.sol:Token".to_string(),
+            address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+        };
+
+        let cmd = verify_command(&contract, &network);
+        let args: Vec<&str> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(cmd.get_program(), "forge");
+        assert_eq!(
+            args,
+            vec![
+                "verify-contract",
+                "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "src/Token.sol:Token",
+                "--chain",
+                "100",
+                "--rpc-url",
+                "https://rpc.gnosischain.com",
+                "--verifier",
+                "blockscout",
+                "--verifier-url",
+                "https://gnosis.blockscout.com/api/",
+                "--guess-constructor-args",
+                "--watch",
+            ]
+        );
+    }
+}
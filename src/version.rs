@@ -0,0 +1,120 @@
+use colored::*;
+use serde::Serialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::deployer::head_sha;
+
+/// Toolchain fingerprint for reproducibility reports (`version --full`)
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub contract_deployer: String,
+    /// `None` when the tool is not installed or its output is not recognized
+    pub forge: Option<String>,
+    pub git: Option<String>,
+    /// HEAD of the repository containing the current directory
+    pub git_sha: Option<String>,
+}
+
+impl VersionInfo {
+    pub fn detect() -> Self {
+        VersionInfo {
+            contract_deployer: env!("CARGO_PKG_VERSION").to_string(),
+            forge: command_output("forge", &["--version"])
+                .and_then(|out| parse_forge_version(&out)),
+            git: command_output("git", &["--version"]).and_then(|out| parse_git_version(&out)),
+            git_sha: head_sha(Path::new(".")),
+        }
+    }
+
+    pub fn print_human(&self) {
+        let unknown = || "not found".yellow().to_string();
+        println!("{}: {}", "contract-deployer".blue(), self.contract_deployer);
+        println!(
+            "{}: {}",
+            "forge".blue(),
+            self.forge.clone().unwrap_or_else(unknown)
+        );
+        println!(
+            "{}: {}",
+            "git".blue(),
+            self.git.clone().unwrap_or_else(unknown)
+        );
+        if let Some(sha) = &self.git_sha {
+            println!("{}: {}", "git_sha".blue(), sha);
+        }
+    }
+}
+
+/// Stdout of a successful command, `None` when it can't be run or fails
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Version (and short commit, when reported) from `forge --version`.
+///
+/// Handles the multi-line format of Foundry 1.x (`forge Version: 1.2.3-stable`
+/// followed by `Commit SHA: ...`) and the older one-line
+/// `forge 0.2.0 (5be158b 2024-05-01T00:14:10Z)`.
+pub fn parse_forge_version(output: &str) -> Option<String> {
+    let mut lines = output.lines().map(str::trim);
+    let first = lines.next()?.strip_prefix("forge")?.trim();
+
+    if let Some(version) = first.strip_prefix("Version:") {
+        let version = version.trim();
+        let commit = lines
+            .find_map(|line| line.strip_prefix("Commit SHA:"))
+            .map(|sha| sha.trim().chars().take(7).collect::<String>());
+        return Some(match commit {
+            Some(commit) if !commit.is_empty() => format!("{} ({})", version, commit),
+            _ => version.to_string(),
+        });
+    }
+
+    (!first.is_empty()).then(|| first.to_string())
+}
+
+/// Version from `git --version`, e.g. `2.43.0` from `git version 2.43.0`
+pub fn parse_git_version(output: &str) -> Option<String> {
+    output
+        .trim()
+        .strip_prefix("git version ")
+        .map(|version| version.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forge_version() {
+        let current = "forge Version: 1.2.3-stable\n\
+                       Commit SHA: a813a2cee7dd4926e7c56fd8a785b54f32e0d10f\n\
+                       Build Timestamp: 2025-06-08T15:40:35.219927000Z (1749397235)\n\
+                       Build Profile: maxperf\n";
+        assert_eq!(
+            parse_forge_version(current).as_deref(),
+            Some("1.2.3-stable (a813a2c)")
+        );
+
+        let legacy = "forge 0.2.0 (5be158b 2024-05-01T00:14:10.650386000Z)\n";
+        assert_eq!(
+            parse_forge_version(legacy).as_deref(),
+            Some("0.2.0 (5be158b 2024-05-01T00:14:10.650386000Z)")
+        );
+
+        assert_eq!(parse_forge_version("command not found"), None);
+        assert_eq!(
+            parse_git_version("git version 2.43.0\n").as_deref(),
+            Some("2.43.0")
+        );
+    }
+}
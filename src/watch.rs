@@ -0,0 +1,181 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::config::NetworkConfig;
+
+/// Directories under the project whose changes trigger a redeploy
+pub const WATCHED_DIRS: &[&str] = &["src", "script"];
+
+/// Whether a network is a local development node, judged by its RPC host.
+///
+/// The chain id alone proves nothing: a public RPC can be configured with
+/// anvil's 31337.
+pub fn is_local_network(network: &NetworkConfig) -> bool {
+    let authority = network
+        .rpc_url
+        .split("://")
+        .nth(1)
+        .unwrap_or(&network.rpc_url)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+
+    matches!(host, "localhost" | "127.0.0.1" | "0.0.0.0" | "::1")
+}
+
+/// A flag that becomes true once Ctrl-C is pressed.
+///
+/// The default handler would kill the process before the clone is cleaned
+/// up, so the signal is caught on a background thread instead.
+pub fn ctrl_c_flag() -> Result<Arc<AtomicBool>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let flag = stop.clone();
+    thread::spawn(move || {
+        if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    });
+
+    Ok(stop)
+}
+
+/// Modification time of every file below `dirs`, recursively
+fn snapshot(dirs: &[PathBuf]) -> BTreeMap<PathBuf, SystemTime> {
+    fn visit(dir: &Path, files: &mut BTreeMap<PathBuf, SystemTime>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                visit(&path, files);
+            } else if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                files.insert(path, modified);
+            }
+        }
+    }
+
+    let mut files = BTreeMap::new();
+    for dir in dirs {
+        visit(dir, &mut files);
+    }
+    files
+}
+
+/// Block until a file below `dirs` is added, removed or modified and no
+/// further change happened for `debounce`. Returns `false` if `stop` was set first.
+pub fn wait_for_change(
+    dirs: &[PathBuf],
+    poll_interval: Duration,
+    debounce: Duration,
+    stop: &AtomicBool,
+) -> bool {
+    let baseline = snapshot(dirs);
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(poll_interval);
+
+        let mut current = snapshot(dirs);
+        if current == baseline {
+            continue;
+        }
+
+        // Editors and formatters often write several times in a row
+        loop {
+            thread::sleep(debounce);
+            if stop.load(Ordering::SeqCst) {
+                return false;
+            }
+            let settled = snapshot(dirs);
+            if settled == current {
+                return true;
+            }
+            current = settled;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_wait_for_change() {
+        let temp_dir = tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("Token.sol"), "contract Token {}").unwrap();
+        let dirs = vec![src.clone(), temp_dir.path().join("script")];
+
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            fs::write(src.join("Vault.sol"), "contract Vault {}").unwrap();
+        });
+        let stop = AtomicBool::new(false);
+        assert!(wait_for_change(
+            &dirs,
+            Duration::from_millis(20),
+            Duration::from_millis(50),
+            &stop
+        ));
+        writer.join().unwrap();
+
+        stop.store(true, Ordering::SeqCst);
+        assert!(!wait_for_change(
+            &dirs,
+            Duration::from_millis(20),
+            Duration::from_millis(50),
+            &stop
+        ));
+    }
+
+    #[test]
+    fn test_is_local_network() {
+        let network: NetworkConfig = toml::from_str(
+            r#"
+chain_id = 1
+rpc_url = "https://eth-mainnet.g.alchemy.com/v2/test"
+verify = true
+"#,
+        )
+        .unwrap();
+        assert!(!is_local_network(&network));
+
+        let public_anvil_id = NetworkConfig {
+            chain_id: 31337,
+            ..network.clone()
+        };
+        assert!(!is_local_network(&public_anvil_id));
+
+        let forked = NetworkConfig {
+            rpc_url: "http://127.0.0.1:8545".to_string(),
+            ..network.clone()
+        };
+        assert!(is_local_network(&forked));
+
+        let ipv6 = NetworkConfig {
+            rpc_url: "http://[::1]:8545".to_string(),
+            ..network
+        };
+        assert!(is_local_network(&ipv6));
+    }
+}